@@ -0,0 +1,369 @@
+use std::{fs, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::file::{get_files, hve::win32_ts_to_datetime};
+
+/// The fixed size of the ShellLinkHeader structure
+const HEADER_SIZE: usize = 0x4C;
+/// The ShellLinkHeader class identifier, 00021401-0000-0000-C000-000000000046
+const LINK_CLSID: [u8; 16] = [
+    0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+/// The signature of the TrackerDataBlock, which holds the NetBIOS name of the machine
+const TRACKER_SIGNATURE: u32 = 0xA0000003;
+
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x1;
+const HAS_LINK_INFO: u32 = 0x2;
+const HAS_NAME: u32 = 0x4;
+const HAS_RELATIVE_PATH: u32 = 0x8;
+const HAS_WORKING_DIR: u32 = 0x10;
+const HAS_ARGUMENTS: u32 = 0x20;
+const HAS_ICON_LOCATION: u32 = 0x40;
+const IS_UNICODE: u32 = 0x80;
+
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x1;
+const COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX: u32 = 0x2;
+
+#[derive(Debug, Default, Serialize)]
+pub struct LnkEntity {
+    pub arguments: Option<String>,
+    pub description: Option<String>,
+    pub file_size: u32,
+    pub icon_location: Option<String>,
+    pub lnk_path: PathBuf,
+    pub machine_id: Option<String>,
+    pub relative_path: Option<String>,
+    pub target_accessed_ts: Option<DateTime<Utc>>,
+    pub target_created_ts: Option<DateTime<Utc>>,
+    pub target_modified_ts: Option<DateTime<Utc>>,
+    pub target_path: Option<String>,
+    pub volume_label: Option<String>,
+    pub volume_serial: Option<String>,
+    pub working_dir: Option<String>,
+}
+
+pub struct LnkAnalyzer {
+    path: PathBuf,
+}
+
+impl LnkAnalyzer {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn lnk_entries(&self) -> crate::Result<Vec<LnkEntity>> {
+        let mut entities: Vec<LnkEntity> = Vec::new();
+        let files = get_files(&self.path, &None, false, false, None)?;
+        for file in files {
+            let is_lnk = file
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(|e| e.eq_ignore_ascii_case("lnk"))
+                .unwrap_or(false);
+            if !is_lnk {
+                continue;
+            }
+            let bytes = fs::read(&file)?;
+            // NOTE: A single corrupt or truncated shortcut should not stop the rest being analysed.
+            match parse(&bytes, file.clone()) {
+                Ok(entity) => entities.push(entity),
+                Err(e) => {
+                    cs_eyellowln!("[!] Could not parse shortcut file {:?} - {}", file, e);
+                }
+            }
+        }
+        cs_eprintln!(
+            "[+] {} shortcut files loaded from {:?}",
+            entities.len(),
+            fs::canonicalize(&self.path).expect("could not get absolute path")
+        );
+        // Most recently modified targets first, shortcuts without timestamps go last
+        entities.sort_by(|x, y| y.target_modified_ts.cmp(&x.target_modified_ts));
+        Ok(entities)
+    }
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn slice(&self, offset: usize, len: usize) -> crate::Result<&'a [u8]> {
+        self.bytes
+            .get(offset..offset + len)
+            .ok_or_else(|| anyhow!("LNK byte indexing error at offset {}!", offset))
+    }
+
+    fn u16_at(&self, offset: usize) -> crate::Result<u16> {
+        Ok(u16::from_le_bytes(self.slice(offset, 2)?.try_into()?))
+    }
+
+    fn u32_at(&self, offset: usize) -> crate::Result<u32> {
+        Ok(u32::from_le_bytes(self.slice(offset, 4)?.try_into()?))
+    }
+
+    fn u64_at(&self, offset: usize) -> crate::Result<u64> {
+        Ok(u64::from_le_bytes(self.slice(offset, 8)?.try_into()?))
+    }
+
+    /// Reads a null terminated ANSI string, which is decoded lossily as we do not know the code page
+    fn cstr_at(&self, offset: usize) -> crate::Result<String> {
+        let bytes = self
+            .bytes
+            .get(offset..)
+            .ok_or_else(|| anyhow!("LNK byte indexing error at offset {}!", offset))?;
+        let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+        Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+
+    /// Reads a null terminated UTF-16 string
+    fn wstr_at(&self, offset: usize) -> crate::Result<String> {
+        let bytes = self
+            .bytes
+            .get(offset..)
+            .ok_or_else(|| anyhow!("LNK byte indexing error at offset {}!", offset))?;
+        let chars: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|c| *c != 0)
+            .collect();
+        Ok(String::from_utf16_lossy(&chars))
+    }
+}
+
+fn timestamp(value: u64) -> crate::Result<Option<DateTime<Utc>>> {
+    if value == 0 {
+        return Ok(None);
+    }
+    let naive = win32_ts_to_datetime(value)?;
+    Ok(Some(DateTime::<Utc>::from_utc(naive, Utc)))
+}
+
+fn non_empty(value: String) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+fn parse(bytes: &[u8], lnk_path: PathBuf) -> crate::Result<LnkEntity> {
+    let cursor = Cursor { bytes };
+    if cursor.u32_at(0)? as usize != HEADER_SIZE || cursor.slice(4, 16)? != LINK_CLSID {
+        anyhow::bail!("Invalid shell link header");
+    }
+    let flags = cursor.u32_at(0x14)?;
+    let mut entity = LnkEntity {
+        file_size: cursor.u32_at(0x34)?,
+        lnk_path,
+        target_accessed_ts: timestamp(cursor.u64_at(0x24)?)?,
+        target_created_ts: timestamp(cursor.u64_at(0x1C)?)?,
+        target_modified_ts: timestamp(cursor.u64_at(0x2C)?)?,
+        ..Default::default()
+    };
+
+    let mut offset = HEADER_SIZE;
+    if flags & HAS_LINK_TARGET_ID_LIST != 0 {
+        // NOTE: The shell item list duplicates the target path in a far less friendly format, so
+        // we skip over it and rely on the link info instead.
+        offset += 2 + cursor.u16_at(offset)? as usize;
+    }
+
+    if flags & HAS_LINK_INFO != 0 {
+        let info = cursor.slice(offset, cursor.u32_at(offset)? as usize)?;
+        parse_link_info(&Cursor { bytes: info }, &mut entity)?;
+        offset += info.len();
+    }
+
+    let unicode = flags & IS_UNICODE != 0;
+    let strings = [
+        (HAS_NAME, &mut entity.description),
+        (HAS_RELATIVE_PATH, &mut entity.relative_path),
+        (HAS_WORKING_DIR, &mut entity.working_dir),
+        (HAS_ARGUMENTS, &mut entity.arguments),
+        (HAS_ICON_LOCATION, &mut entity.icon_location),
+    ];
+    for (flag, field) in strings {
+        if flags & flag == 0 {
+            continue;
+        }
+        let count = cursor.u16_at(offset)? as usize;
+        offset += 2;
+        let value = if unicode {
+            let chars: Vec<u16> = cursor
+                .slice(offset, count * 2)?
+                .chunks_exact(2)
+                .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                .collect();
+            offset += count * 2;
+            String::from_utf16_lossy(&chars)
+        } else {
+            let value = String::from_utf8_lossy(cursor.slice(offset, count)?).into_owned();
+            offset += count;
+            value
+        };
+        *field = non_empty(value);
+    }
+
+    // The extra data blocks are terminated by a block smaller than 4 bytes, but we are lenient
+    // with truncated files as the fields we care about have already been parsed.
+    while let Ok(size) = cursor.u32_at(offset) {
+        let size = size as usize;
+        if size < 8 {
+            break;
+        }
+        if cursor.u32_at(offset + 4)? == TRACKER_SIGNATURE {
+            entity.machine_id = non_empty(cursor.cstr_at(offset + 16)?);
+        }
+        offset += size;
+    }
+
+    Ok(entity)
+}
+
+fn parse_link_info(info: &Cursor, entity: &mut LnkEntity) -> crate::Result<()> {
+    let header_size = info.u32_at(4)?;
+    let flags = info.u32_at(8)?;
+    // NOTE: Newer writers also store unicode copies of the paths when the header is extended.
+    let unicode = header_size >= 0x24;
+    let suffix = if unicode {
+        info.wstr_at(info.u32_at(0x20)? as usize)?
+    } else {
+        info.cstr_at(info.u32_at(0x18)? as usize)?
+    };
+
+    if flags & VOLUME_ID_AND_LOCAL_BASE_PATH != 0 {
+        let volume = info.u32_at(0x0C)? as usize;
+        entity.volume_serial = Some(format!("{:08X}", info.u32_at(volume + 8)?));
+        let label = info.u32_at(volume + 12)? as usize;
+        entity.volume_label = if label == 0x14 {
+            non_empty(info.wstr_at(volume + info.u32_at(volume + 16)? as usize)?)
+        } else {
+            non_empty(info.cstr_at(volume + label)?)
+        };
+        let base = if unicode {
+            info.wstr_at(info.u32_at(0x1C)? as usize)?
+        } else {
+            info.cstr_at(info.u32_at(0x10)? as usize)?
+        };
+        entity.target_path = non_empty(format!("{}{}", base, suffix));
+    } else if flags & COMMON_NETWORK_RELATIVE_LINK_AND_PATH_SUFFIX != 0 {
+        let network = info.u32_at(0x14)? as usize;
+        let share = info.cstr_at(network + info.u32_at(network + 8)? as usize)?;
+        entity.target_path = if suffix.is_empty() {
+            non_empty(share)
+        } else {
+            Some(format!("{}\\{}", share, suffix))
+        };
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    // 2020-09-13T12:26:40Z as a FILETIME
+    const FILETIME: u64 = 132_444_736_000_000_000;
+
+    fn header(flags: u32) -> Vec<u8> {
+        let mut bytes = vec![0u8; HEADER_SIZE];
+        bytes[0..4].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        bytes[4..20].copy_from_slice(&LINK_CLSID);
+        bytes[0x14..0x18].copy_from_slice(&flags.to_le_bytes());
+        bytes[0x1C..0x24].copy_from_slice(&FILETIME.to_le_bytes());
+        bytes[0x2C..0x34].copy_from_slice(&FILETIME.to_le_bytes());
+        bytes[0x34..0x38].copy_from_slice(&1024u32.to_le_bytes());
+        bytes
+    }
+
+    fn link_info() -> Vec<u8> {
+        let mut volume = vec![];
+        volume.extend(21u32.to_le_bytes());
+        volume.extend(3u32.to_le_bytes());
+        volume.extend(0xDEADBEEFu32.to_le_bytes());
+        volume.extend(16u32.to_le_bytes());
+        volume.extend(b"DATA\0");
+        let base = b"C:\\Windows\\notepad.exe\0";
+        let mut info = vec![0u8; 0x1C];
+        info[4..8].copy_from_slice(&0x1Cu32.to_le_bytes());
+        info[8..12].copy_from_slice(&VOLUME_ID_AND_LOCAL_BASE_PATH.to_le_bytes());
+        info[0x0C..0x10].copy_from_slice(&0x1Cu32.to_le_bytes());
+        let base_offset = 0x1C + volume.len() as u32;
+        info[0x10..0x14].copy_from_slice(&base_offset.to_le_bytes());
+        let suffix_offset = base_offset + base.len() as u32;
+        info[0x18..0x1C].copy_from_slice(&suffix_offset.to_le_bytes());
+        info.extend(volume);
+        info.extend(base);
+        info.push(0);
+        let size = info.len() as u32;
+        info[0..4].copy_from_slice(&size.to_le_bytes());
+        info
+    }
+
+    fn string_data(value: &str) -> Vec<u8> {
+        let chars: Vec<u16> = value.encode_utf16().collect();
+        let mut bytes = (chars.len() as u16).to_le_bytes().to_vec();
+        for c in chars {
+            bytes.extend(c.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn tracker(machine_id: &str) -> Vec<u8> {
+        let mut block = vec![0u8; 0x60];
+        block[0..4].copy_from_slice(&0x60u32.to_le_bytes());
+        block[4..8].copy_from_slice(&TRACKER_SIGNATURE.to_le_bytes());
+        block[8..12].copy_from_slice(&0x58u32.to_le_bytes());
+        block[16..16 + machine_id.len()].copy_from_slice(machine_id.as_bytes());
+        block
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut bytes = header(HAS_LINK_INFO | HAS_NAME | HAS_ARGUMENTS | IS_UNICODE);
+        bytes.extend(link_info());
+        bytes.extend(string_data("Notepad"));
+        bytes.extend(string_data("/p notes.txt"));
+        bytes.extend(tracker("desktop-1"));
+        bytes.extend(0u32.to_le_bytes());
+
+        let entity = parse(&bytes, PathBuf::from("notepad.lnk")).unwrap();
+        let expected = Utc.timestamp_opt(1_600_000_000, 0).unwrap();
+        assert_eq!(entity.target_created_ts, Some(expected));
+        assert_eq!(entity.target_modified_ts, Some(expected));
+        assert_eq!(entity.target_accessed_ts, None);
+        assert_eq!(entity.file_size, 1024);
+        assert_eq!(
+            entity.target_path.as_deref(),
+            Some("C:\\Windows\\notepad.exe")
+        );
+        assert_eq!(entity.volume_label.as_deref(), Some("DATA"));
+        assert_eq!(entity.volume_serial.as_deref(), Some("DEADBEEF"));
+        assert_eq!(entity.description.as_deref(), Some("Notepad"));
+        assert_eq!(entity.arguments.as_deref(), Some("/p notes.txt"));
+        assert_eq!(entity.working_dir, None);
+        assert_eq!(entity.machine_id.as_deref(), Some("desktop-1"));
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        let mut bytes = header(0);
+        bytes[4] = 0;
+        assert!(parse(&bytes, PathBuf::from("bad.lnk")).is_err());
+
+        // The string data claims more characters than the file holds
+        let mut bytes = header(HAS_NAME | IS_UNICODE);
+        bytes.extend(string_data("Notepad"));
+        bytes.truncate(bytes.len() - 4);
+        assert!(parse(&bytes, PathBuf::from("truncated.lnk")).is_err());
+
+        // The extra data blocks are optional, so their absence is not an error
+        let bytes = header(0);
+        assert!(parse(&bytes, PathBuf::from("minimal.lnk")).is_ok());
+    }
+}
@@ -1 +1,4 @@
+pub mod lnk;
+pub mod recyclebin;
 pub mod shimcache;
+pub mod userassist;
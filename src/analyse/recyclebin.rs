@@ -0,0 +1,186 @@
+use std::{fs, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::file::{get_files, hve::win32_ts_to_datetime};
+
+/// The size of the original path field in the Vista/7/8 ($I version 1) layout, MAX_PATH characters
+const V1_PATH_BYTES: usize = 520;
+
+#[derive(Debug, Serialize)]
+pub struct RecycleBinEntity {
+    pub deleted_ts: DateTime<Utc>,
+    pub file_size: u64,
+    pub metadata_path: PathBuf,
+    pub original_path: String,
+    pub version: u64,
+}
+
+pub struct RecycleBinAnalyzer {
+    path: PathBuf,
+}
+
+impl RecycleBinAnalyzer {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn recyclebin_entries(&self) -> crate::Result<Vec<RecycleBinEntity>> {
+        let mut entities: Vec<RecycleBinEntity> = Vec::new();
+        // NOTE: The metadata files live in a folder per user SID, so we walk the whole directory.
+        let files = get_files(&self.path, &None, false, false, None)?;
+        for file in files {
+            let is_metadata = file
+                .file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with("$I"))
+                .unwrap_or(false);
+            if !is_metadata {
+                continue;
+            }
+            let bytes = fs::read(&file)?;
+            // NOTE: A single corrupt or truncated metadata file should not stop the rest being
+            // analysed.
+            match parse(&bytes, file.clone()) {
+                Ok(entity) => entities.push(entity),
+                Err(e) => {
+                    cs_eyellowln!(
+                        "[!] Could not parse recycle bin metadata file {:?} - {}",
+                        file,
+                        e
+                    );
+                }
+            }
+        }
+        cs_eprintln!(
+            "[+] {} recycle bin entries loaded from {:?}",
+            entities.len(),
+            fs::canonicalize(&self.path).expect("could not get absolute path")
+        );
+        // Most recent deletions first
+        entities.sort_by(|x, y| y.deleted_ts.cmp(&x.deleted_ts));
+        Ok(entities)
+    }
+}
+
+fn parse(bytes: &[u8], metadata_path: PathBuf) -> crate::Result<RecycleBinEntity> {
+    let e = || anyhow!("$I byte indexing error!");
+    let u64_at = |offset: usize| -> crate::Result<u64> {
+        Ok(u64::from_le_bytes(
+            bytes.get(offset..offset + 8).ok_or_else(e)?.try_into()?,
+        ))
+    };
+    let version = u64_at(0)?;
+    let file_size = u64_at(8)?;
+    let deleted = u64_at(16)?;
+    let path = match version {
+        // Windows Vista, 7 and 8, where the path is a fixed size null padded field
+        1 => bytes.get(24..24 + V1_PATH_BYTES).ok_or_else(e)?,
+        // Windows 10 and later, where the path is prefixed with its length in characters
+        2 => {
+            let length = u32::from_le_bytes(bytes.get(24..28).ok_or_else(e)?.try_into()?) as usize;
+            bytes.get(28..28 + length * 2).ok_or_else(e)?
+        }
+        _ => anyhow::bail!("Unsupported $I version {}", version),
+    };
+    let path: Vec<u16> = path
+        .chunks_exact(2)
+        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+        .take_while(|c| *c != 0)
+        .collect();
+    let naive = win32_ts_to_datetime(deleted)?;
+    Ok(RecycleBinEntity {
+        deleted_ts: DateTime::<Utc>::from_utc(naive, Utc),
+        file_size,
+        metadata_path,
+        original_path: String::from_utf16_lossy(&path),
+        version,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    // 2020-09-13T12:26:40Z as a FILETIME
+    const FILETIME: u64 = 132_444_736_000_000_000;
+    const PATH: &str = "C:\\Users\\alice\\Documents\\notes.txt";
+
+    fn header(version: u64) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend(version.to_le_bytes());
+        bytes.extend(2048u64.to_le_bytes());
+        bytes.extend(FILETIME.to_le_bytes());
+        bytes
+    }
+
+    fn utf16(value: &str) -> Vec<u8> {
+        value.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_parse_v1() {
+        let mut bytes = header(1);
+        let mut path = utf16(PATH);
+        path.resize(V1_PATH_BYTES, 0);
+        bytes.extend(path);
+
+        let entity = parse(&bytes, PathBuf::from("$IABC123.txt")).unwrap();
+        assert_eq!(entity.version, 1);
+        assert_eq!(entity.file_size, 2048);
+        assert_eq!(
+            entity.deleted_ts,
+            Utc.timestamp_opt(1_600_000_000, 0).unwrap()
+        );
+        assert_eq!(entity.original_path, PATH);
+    }
+
+    #[test]
+    fn test_parse_v2() {
+        let mut bytes = header(2);
+        // The length includes the null terminator
+        bytes.extend((PATH.len() as u32 + 1).to_le_bytes());
+        bytes.extend(utf16(PATH));
+        bytes.extend([0, 0]);
+
+        let entity = parse(&bytes, PathBuf::from("$IABC123.txt")).unwrap();
+        assert_eq!(entity.version, 2);
+        assert_eq!(entity.file_size, 2048);
+        assert_eq!(entity.original_path, PATH);
+    }
+
+    #[test]
+    fn test_parse_truncated() {
+        let mut bytes = header(1);
+        bytes.extend(utf16(PATH));
+        assert!(parse(&bytes, PathBuf::from("$IABC123.txt")).is_err());
+
+        let mut bytes = header(2);
+        bytes.extend((PATH.len() as u32 + 1).to_le_bytes());
+        bytes.extend(utf16(PATH));
+        assert!(parse(&bytes, PathBuf::from("$IABC123.txt")).is_err());
+
+        assert!(parse(&header(1)[..12], PathBuf::from("$IABC123.txt")).is_err());
+        assert!(parse(&header(3), PathBuf::from("$IABC123.txt")).is_err());
+    }
+
+    #[test]
+    fn test_entries_skip_unparsable() {
+        let root = tempfile::tempdir().unwrap();
+        let mut bytes = header(2);
+        bytes.extend((PATH.len() as u32 + 1).to_le_bytes());
+        bytes.extend(utf16(PATH));
+        bytes.extend([0, 0]);
+        fs::write(root.path().join("$IABC123.txt"), bytes).unwrap();
+        fs::write(root.path().join("$IDEF456.txt"), header(3)).unwrap();
+
+        let entities = RecycleBinAnalyzer::new(root.path().to_path_buf())
+            .recyclebin_entries()
+            .unwrap();
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].original_path, PATH);
+    }
+}
@@ -1,11 +1,18 @@
-use std::{fs, path::PathBuf, rc::Rc};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use regex::Regex;
 
 use crate::file::hve::{
-    amcache::{AmcacheArtifact, FileEntry, ProgramEntry},
-    shimcache::{EntryType, ShimcacheEntry},
+    amcache::{AmcacheArtifact, DriverEntry, FileEntry, ProgramEntry},
+    shimcache::{AssumedVersion, EntryType, ShimcacheEntry},
     Parser as HveParser,
 };
 
@@ -28,10 +35,46 @@ pub enum TimelineTimestamp {
     RangeStart(DateTime<Utc>),
 }
 
+impl TimelineTimestamp {
+    /// The timestamp used to order the timeline, which is the start of a range when it is known
+    fn sort_key(&self) -> DateTime<Utc> {
+        match self {
+            Self::Exact(timestamp, _) | Self::RangeEnd(timestamp) | Self::RangeStart(timestamp) => {
+                *timestamp
+            }
+            Self::Range { from, .. } => *from,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl FromStr for SortOrder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let v = match s {
+            "asc" => Self::Asc,
+            "desc" => Self::Desc,
+            _ => anyhow::bail!("unknown sort order, must be: asc or desc"),
+        };
+        Ok(v)
+    }
+}
+
 #[derive(Debug)]
 pub struct TimelineEntity {
+    pub amcache_driver: Option<Rc<DriverEntry>>,
     pub amcache_file: Option<Rc<FileEntry>>,
+    /// The amcache file entry with the most similar path, for entities that only have a range
+    pub amcache_candidate: Option<Rc<FileEntry>>,
     pub amcache_program: Option<Rc<ProgramEntry>>,
+    /// The values of the named capture groups in the pattern that matched the shimcache entry
+    pub captures: BTreeMap<String, String>,
     pub shimcache_entry: Option<ShimcacheEntry>,
     pub timestamp: Option<TimelineTimestamp>,
 }
@@ -39,24 +82,84 @@ pub struct TimelineEntity {
 impl TimelineEntity {
     fn with_shimcache_entry(shimcache_entry: ShimcacheEntry) -> Self {
         Self {
+            amcache_driver: None,
             amcache_file: None,
+            amcache_candidate: None,
             amcache_program: None,
+            captures: BTreeMap::new(),
             shimcache_entry: Some(shimcache_entry),
             timestamp: None,
         }
     }
+
+    /// Whether the shimcache entry is missing from a baseline loaded with [`load_baseline`]
+    pub fn is_new(&self, baseline: &HashSet<String>) -> bool {
+        match &self.shimcache_entry {
+            Some(shimcache_entry) => !baseline.contains(&baseline_key(&shimcache_entry.entry_type)),
+            None => false,
+        }
+    }
+}
+
+/// Paths are compared without case, as they are on Windows
+fn baseline_key(entry_type: &EntryType) -> String {
+    match entry_type {
+        EntryType::File { path } => path.to_lowercase(),
+        EntryType::Program { program_name, .. } => program_name.to_lowercase(),
+    }
+}
+
+/// Loads the shimcache file paths and program names from a timeline csv previously exported by
+/// the shimcache analysis, to compare other timelines against
+pub fn load_baseline(path: &Path) -> crate::Result<HashSet<String>> {
+    let mut reader = prettytable::csv::Reader::from_path(path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let (file_path, program_name, entry_type) = match (
+        column("File Path"),
+        column("Program Name"),
+        column("Entry Type"),
+    ) {
+        (Some(file_path), Some(program_name), Some(entry_type)) => {
+            (file_path, program_name, entry_type)
+        }
+        _ => anyhow::bail!("{} is not a shimcache timeline csv", path.display()),
+    };
+    let mut baseline = HashSet::new();
+    for record in reader.records() {
+        let record = record?;
+        // NOTE: The amcache rows are enrichments rather than shimcache entries, so they are skipped
+        let value = match record.get(entry_type) {
+            Some("ShimcacheFileEntry") => record.get(file_path),
+            Some("ShimcacheProgramEntry") => record.get(program_name),
+            _ => None,
+        };
+        if let Some(value) = value.filter(|v| !v.is_empty()) {
+            baseline.insert(value.to_lowercase());
+        }
+    }
+    Ok(baseline)
 }
 
 pub struct ShimcacheAnalyzer {
     amcache_path: Option<PathBuf>,
+    assume_version: Option<AssumedVersion>,
     shimcache_path: PathBuf,
+    transaction_logs: Vec<PathBuf>,
 }
 
 impl ShimcacheAnalyzer {
-    pub fn new(shimcache_path: PathBuf, amcache_path: Option<PathBuf>) -> Self {
+    pub fn new(
+        shimcache_path: PathBuf,
+        amcache_path: Option<PathBuf>,
+        transaction_logs: Vec<PathBuf>,
+        assume_version: Option<AssumedVersion>,
+    ) -> Self {
         Self {
             amcache_path,
+            assume_version,
             shimcache_path,
+            transaction_logs,
         }
     }
 
@@ -64,6 +167,8 @@ impl ShimcacheAnalyzer {
         &self,
         regex_patterns: &Vec<String>,
         ts_near_pair_matching: bool,
+        near_window: Duration,
+        verbose: bool,
     ) -> crate::Result<Vec<TimelineEntity>> {
         if regex_patterns.is_empty() {
             cs_eyellowln!("[!] No regex patterns defined for matching shimcache entries!")
@@ -74,12 +179,18 @@ impl ShimcacheAnalyzer {
             .collect::<Result<Vec<_>, _>>()?;
 
         // Load shimcache
-        let mut shimcache_parser = HveParser::load(&self.shimcache_path)?;
-        let shimcache = shimcache_parser.parse_shimcache()?;
+        let mut shimcache_parser =
+            HveParser::load_with_transaction_logs(&self.shimcache_path, &self.transaction_logs)?;
+        let shimcache = shimcache_parser.parse_shimcache(self.assume_version)?;
         cs_eprintln!(
-            "[+] {} shimcache hive file loaded from {:?}",
+            "[+] {} shimcache hive file loaded from {:?}{}",
             shimcache.version,
-            fs::canonicalize(&self.shimcache_path).expect("could not get absolute path")
+            fs::canonicalize(&self.shimcache_path).expect("could not get absolute path"),
+            if shimcache.assumed {
+                " (version assumed, detection skipped)"
+            } else {
+                ""
+            }
         );
 
         // Load amcache
@@ -167,8 +278,11 @@ impl ShimcacheAnalyzer {
         timeline_entities.insert(
             0,
             TimelineEntity {
+                amcache_driver: None,
                 amcache_file: None,
+                amcache_candidate: None,
                 amcache_program: None,
+                captures: BTreeMap::new(),
                 shimcache_entry: None,
                 timestamp: Some(TimelineTimestamp::Exact(
                     shimcache.last_update_ts,
@@ -177,35 +291,43 @@ impl ShimcacheAnalyzer {
             },
         );
 
-        let mut pattern_match_count = 0;
+        // The indices of the entities whose timestamp was set by a pattern match
+        let mut pattern_matched = BTreeSet::new();
         // Check for matches with config patterns and set timestamp
-        for entity in timeline_entities.iter_mut() {
+        for (i, entity) in timeline_entities.iter_mut().enumerate() {
             for re in &regexes {
                 let shimcache_entry = if let Some(entry) = &entity.shimcache_entry {
                     entry
                 } else {
                     continue;
                 };
-                let pattern_matches = match &shimcache_entry.entry_type {
-                    EntryType::File { path, .. } => re.is_match(&path.to_lowercase()),
-                    EntryType::Program { .. } => false,
+                let path = match &shimcache_entry.entry_type {
+                    EntryType::File { path, .. } => path.to_lowercase(),
+                    EntryType::Program { .. } => continue,
                 };
-                if pattern_matches {
+                if let Some(captures) = re.captures(&path) {
+                    for name in re.capture_names().flatten() {
+                        if let Some(value) = captures.name(name) {
+                            entity
+                                .captures
+                                .insert(name.to_owned(), value.as_str().to_owned());
+                        }
+                    }
                     if let Some(ts) = shimcache_entry.last_modified_ts {
                         entity.timestamp =
                             Some(TimelineTimestamp::Exact(ts, TimestampType::PatternMatch));
-                        pattern_match_count += 1;
+                        pattern_matched.insert(i);
                     }
                     break;
                 }
             }
         }
-        if pattern_match_count == 0 {
+        if pattern_matched.is_empty() {
             cs_eyellowln!("[!] 0 pattern matching entries found from shimcache")
         } else {
             cs_eprintln!(
                 "[+] {} pattern matching entries found from shimcache",
-                pattern_match_count
+                pattern_matched.len()
             );
         }
 
@@ -218,8 +340,9 @@ impl ShimcacheAnalyzer {
         // Amcache enrichments
         if let Some(amcache) = amcache {
             // Match shimcache and amcache file entries
-            for file_entry in amcache.file_entries.into_iter() {
-                let file_entry = Rc::new(file_entry);
+            let file_entries: Vec<Rc<FileEntry>> =
+                amcache.file_entries.into_iter().map(Rc::new).collect();
+            for file_entry in &file_entries {
                 for mut entity in &mut timeline_entities {
                     let shimcache_entry = if let Some(entry) = &entity.shimcache_entry {
                         entry
@@ -228,7 +351,24 @@ impl ShimcacheAnalyzer {
                     };
                     if let EntryType::File { path } = &shimcache_entry.entry_type {
                         if file_entry.path.to_lowercase() == path.to_lowercase() {
-                            entity.amcache_file = Some(Rc::clone(&file_entry));
+                            entity.amcache_file = Some(Rc::clone(file_entry));
+                        }
+                    }
+                }
+            }
+
+            // Match shimcache and amcache driver entries
+            for driver_entry in amcache.driver_entries.into_iter() {
+                let driver_entry = Rc::new(driver_entry);
+                for mut entity in &mut timeline_entities {
+                    let shimcache_entry = if let Some(entry) = &entity.shimcache_entry {
+                        entry
+                    } else {
+                        continue;
+                    };
+                    if let EntryType::File { path } = &shimcache_entry.entry_type {
+                        if is_driver_path(&driver_entry.path, path) {
+                            entity.amcache_driver = Some(Rc::clone(&driver_entry));
                         }
                     }
                 }
@@ -260,40 +400,13 @@ impl ShimcacheAnalyzer {
 
             if ts_near_pair_matching {
                 // Find near Amcache and Shimcache timestamp pairs
-                const MAX_TIME_DIFFERENCE: i64 = 60 * 1000; // 1 min
-                let mut near_timestamps_count = 0;
-                let mut pattern_match_overlap_count = 0;
-                for mut entity in &mut timeline_entities {
-                    if let (Some(shimcache_entry), Some(amcache_entry)) =
-                        (&entity.shimcache_entry, &entity.amcache_file)
-                    {
-                        if let Some(shimcache_ts) = shimcache_entry.last_modified_ts {
-                            let difference = shimcache_ts - amcache_entry.key_last_modified_ts;
-                            if difference.num_milliseconds().abs() > MAX_TIME_DIFFERENCE {
-                                continue;
-                            }
-                            near_timestamps_count += 1;
-                            // Do not overwrite pattern matched timestamps
-                            if let Some(TimelineTimestamp::Exact(
-                                _ts,
-                                TimestampType::PatternMatch,
-                            )) = entity.timestamp
-                            {
-                                pattern_match_overlap_count += 1;
-                                continue;
-                            }
-                            entity.timestamp = Some(TimelineTimestamp::Exact(
-                                amcache_entry.key_last_modified_ts,
-                                TimestampType::NearTSMatch,
-                            ));
-                        }
-                    }
-                }
+                let near_matched =
+                    set_near_ts_matches(&mut timeline_entities, &pattern_matched, near_window);
                 let new_exact_ts_indices = get_exact_ts_indices(&timeline_entities);
                 cs_eprintln!(
                     "[+] {} near shimcache & amcache timestamp pairs found (with {} overlapping the pattern matched entries)",
-                    near_timestamps_count,
-                    pattern_match_overlap_count,
+                    near_matched.len(),
+                    near_matched.intersection(&pattern_matched).count(),
                 );
 
                 // Set timestamp ranges again, including Amcache & Shimcache timestamp near pairs
@@ -336,7 +449,337 @@ impl ShimcacheAnalyzer {
                 &get_exact_ts_indices(&timeline_entities),
                 &mut timeline_entities,
             );
+
+            if verbose {
+                let candidates = set_amcache_candidates(&mut timeline_entities, &file_entries);
+                cs_eprintln!(
+                    "[+] {} amcache candidates found for entries with only a timestamp range",
+                    candidates
+                );
+            }
         }
         Ok(timeline_entities)
     }
 }
+
+/// Whether the amcache driver path is the shimcache path, where the driver path is taken from a
+/// registry key name and so uses forward slashes as its separator
+fn is_driver_path(driver_path: &str, shimcache_path: &str) -> bool {
+    driver_path.replace('/', "\\").to_lowercase() == shimcache_path.to_lowercase()
+}
+
+/// Sorts the timeline entities by their resolved timestamp, keeping the insertion order of ties.
+/// Entities without a timestamp are always placed last.
+pub fn sort_timeline(timeline_entities: &mut [TimelineEntity], order: SortOrder) {
+    timeline_entities.sort_by(|x, y| {
+        let x = x.timestamp.as_ref().map(|t| t.sort_key());
+        let y = y.timestamp.as_ref().map(|t| t.sort_key());
+        match (x, y) {
+            (Some(x), Some(y)) => match order {
+                SortOrder::Asc => x.cmp(&y),
+                SortOrder::Desc => y.cmp(&x),
+            },
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    });
+}
+
+/// Sets the timestamp of entities whose shimcache and amcache timestamps are within the window of
+/// each other, returning the indices of all such near pairs. The timestamps of the pattern matched
+/// entities are left as they are, but they are still included in the returned indices.
+fn set_near_ts_matches(
+    timeline_entities: &mut [TimelineEntity],
+    pattern_matched: &BTreeSet<usize>,
+    near_window: Duration,
+) -> BTreeSet<usize> {
+    let mut near_matched = BTreeSet::new();
+    for (i, entity) in timeline_entities.iter_mut().enumerate() {
+        if let (Some(shimcache_entry), Some(amcache_entry)) =
+            (&entity.shimcache_entry, &entity.amcache_file)
+        {
+            if let Some(shimcache_ts) = shimcache_entry.last_modified_ts {
+                let difference = shimcache_ts - amcache_entry.key_last_modified_ts;
+                if difference.num_milliseconds().abs() > near_window.num_milliseconds() {
+                    continue;
+                }
+                near_matched.insert(i);
+                // Do not overwrite pattern matched timestamps
+                if pattern_matched.contains(&i) {
+                    continue;
+                }
+                entity.timestamp = Some(TimelineTimestamp::Exact(
+                    amcache_entry.key_last_modified_ts,
+                    TimestampType::NearTSMatch,
+                ));
+            }
+        }
+    }
+    near_matched
+}
+
+/// Sets the amcache file entry with the most similar path as the candidate of each entity that
+/// only has a timestamp range, returning how many were set. The similarity is the number of trailing
+/// path components that are the same, so at least the file names have to match, with ties broken
+/// by the number of leading components that are the same.
+fn set_amcache_candidates(
+    timeline_entities: &mut [TimelineEntity],
+    file_entries: &[Rc<FileEntry>],
+) -> usize {
+    let file_entries = file_entries
+        .iter()
+        .map(|e| (e.path.to_lowercase(), e))
+        .collect::<Vec<_>>();
+    let mut count = 0;
+    for entity in timeline_entities.iter_mut() {
+        if let Some(TimelineTimestamp::Exact(..)) | None = entity.timestamp {
+            continue;
+        }
+        let path = match &entity.shimcache_entry {
+            Some(ShimcacheEntry {
+                entry_type: EntryType::File { path },
+                ..
+            }) => path.to_lowercase(),
+            _ => continue,
+        };
+        let candidate = file_entries
+            .iter()
+            .map(|(p, e)| {
+                let trailing = p
+                    .rsplit('\\')
+                    .zip(path.rsplit('\\'))
+                    .take_while(|(x, y)| x == y)
+                    .count();
+                let leading = p
+                    .split('\\')
+                    .zip(path.split('\\'))
+                    .take_while(|(x, y)| x == y)
+                    .count();
+                ((trailing, leading), e)
+            })
+            .filter(|((trailing, _), _)| *trailing > 0)
+            .max_by_key(|(similarity, _)| *similarity);
+        if let Some((_, e)) = candidate {
+            entity.amcache_candidate = Some(Rc::clone(e));
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use chrono::TimeZone;
+
+    fn entity(shimcache_ts: i64, amcache_ts: Option<i64>) -> TimelineEntity {
+        let mut entity = TimelineEntity::with_shimcache_entry(ShimcacheEntry {
+            cache_entry_position: 0,
+            controlset: 1,
+            data_size: None,
+            data: None,
+            entry_type: EntryType::File {
+                path: "c:\\windows\\system32\\cmd.exe".to_owned(),
+            },
+            executed: None,
+            last_modified_ts: Some(Utc.timestamp_opt(shimcache_ts, 0).unwrap()),
+            path_size: 0,
+            signature: None,
+        });
+        entity.amcache_file = amcache_ts.map(|ts| {
+            Rc::new(FileEntry {
+                file_id: None,
+                key_last_modified_ts: Utc.timestamp_opt(ts, 0).unwrap(),
+                file_last_modified_ts: None,
+                link_date: None,
+                path: "c:\\windows\\system32\\cmd.exe".to_owned(),
+                program_id: None,
+                sha1_hash: None,
+            })
+        });
+        entity
+    }
+
+    #[test]
+    fn test_is_driver_path() {
+        assert!(is_driver_path(
+            "c:/windows/system32/drivers/beep.sys",
+            "C:\\Windows\\system32\\drivers\\beep.sys"
+        ));
+        assert!(!is_driver_path(
+            "c:/windows/system32/drivers/beep.sys",
+            "C:\\Windows\\system32\\drivers\\null.sys"
+        ));
+    }
+
+    #[test]
+    fn test_baseline() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().join("baseline.csv");
+        let mut csv = prettytable::csv::Writer::from_path(&path).unwrap();
+        csv.write_record([
+            "Timestamp",
+            "File Path",
+            "Program Name",
+            "SHA-1 Hash",
+            "Timeline Entry Number",
+            "Entry Type",
+            "Timestamp Description",
+            "Raw Entry",
+        ])
+        .unwrap();
+        csv.write_record([
+            "",
+            "C:\\Windows\\System32\\cmd.exe",
+            "",
+            "",
+            "1",
+            "ShimcacheFileEntry",
+            "",
+            "",
+        ])
+        .unwrap();
+        // Amcache rows are not shimcache entries, so they are not part of the baseline
+        csv.write_record([
+            "",
+            "c:\\tools\\evil.exe",
+            "",
+            "",
+            "",
+            "AmcacheFileEntry",
+            "",
+            "",
+        ])
+        .unwrap();
+        csv.flush().unwrap();
+        drop(csv);
+
+        let baseline = load_baseline(&path).unwrap();
+        assert_eq!(baseline.len(), 1);
+        assert!(!entity(1_000, None).is_new(&baseline));
+
+        let mut new = entity(2_000, None);
+        new.shimcache_entry.as_mut().unwrap().entry_type = EntryType::File {
+            path: "c:\\tools\\evil.exe".to_owned(),
+        };
+        assert!(new.is_new(&baseline));
+    }
+
+    #[test]
+    fn test_near_ts_match_overlap() {
+        let mut entities = vec![
+            // A near pair that was also pattern matched
+            entity(1_000, Some(1_030)),
+            // A near pair only
+            entity(2_000, Some(1_950)),
+            // Too far apart to be a pair
+            entity(3_000, Some(3_600)),
+            // Pattern matched without an amcache entry
+            entity(4_000, None),
+            // Another near pair only
+            entity(5_000, Some(5_000)),
+        ];
+        let pattern_matched = BTreeSet::from([0, 3]);
+        for i in &pattern_matched {
+            let entry = entities[*i].shimcache_entry.as_ref().unwrap();
+            let ts = entry.last_modified_ts.unwrap();
+            entities[*i].timestamp =
+                Some(TimelineTimestamp::Exact(ts, TimestampType::PatternMatch));
+        }
+
+        let near_matched =
+            set_near_ts_matches(&mut entities, &pattern_matched, Duration::minutes(1));
+        assert_eq!(near_matched, BTreeSet::from([0, 1, 4]));
+        assert_eq!(near_matched.intersection(&pattern_matched).count(), 1);
+        assert!(matches!(
+            entities[0].timestamp,
+            Some(TimelineTimestamp::Exact(_, TimestampType::PatternMatch))
+        ));
+        assert!(matches!(
+            entities[1].timestamp,
+            Some(TimelineTimestamp::Exact(_, TimestampType::NearTSMatch))
+        ));
+        assert!(entities[2].timestamp.is_none());
+    }
+
+    #[test]
+    fn test_amcache_candidates() {
+        let file_entry = |path: &str| {
+            Rc::new(FileEntry {
+                file_id: None,
+                key_last_modified_ts: Utc.timestamp_opt(1_000, 0).unwrap(),
+                file_last_modified_ts: None,
+                link_date: None,
+                path: path.to_owned(),
+                program_id: None,
+                sha1_hash: None,
+            })
+        };
+        let file_entries = vec![
+            file_entry("C:\\Windows\\SysWOW64\\cmd.exe"),
+            file_entry("C:\\Tools\\cmd.exe"),
+            file_entry("C:\\Windows\\System32\\notepad.exe"),
+        ];
+        let mut entities = vec![entity(1_000, None), entity(2_000, None)];
+        entities[0].timestamp = Some(TimelineTimestamp::Range {
+            from: Utc.timestamp_opt(500, 0).unwrap(),
+            to: Utc.timestamp_opt(1_500, 0).unwrap(),
+        });
+        entities[1].timestamp = Some(TimelineTimestamp::Exact(
+            Utc.timestamp_opt(2_000, 0).unwrap(),
+            TimestampType::PatternMatch,
+        ));
+
+        assert_eq!(set_amcache_candidates(&mut entities, &file_entries), 1);
+        assert_eq!(
+            entities[0].amcache_candidate.as_ref().unwrap().path,
+            "C:\\Windows\\SysWOW64\\cmd.exe"
+        );
+        assert!(entities[1].amcache_candidate.is_none());
+    }
+
+    #[test]
+    fn test_sort_timeline() {
+        let ts = |secs: i64| Utc.timestamp_opt(secs, 0).unwrap();
+        let mut entities = vec![
+            entity(1_000, None),
+            entity(2_000, None),
+            entity(3_000, None),
+            entity(4_000, None),
+        ];
+        entities[0].timestamp = Some(TimelineTimestamp::RangeEnd(ts(3_000)));
+        entities[2].timestamp = Some(TimelineTimestamp::Range {
+            from: ts(1_000),
+            to: ts(5_000),
+        });
+        entities[3].timestamp = Some(TimelineTimestamp::Exact(
+            ts(2_000),
+            TimestampType::PatternMatch,
+        ));
+        let order = |entities: &[TimelineEntity]| {
+            entities
+                .iter()
+                .map(|e| {
+                    e.shimcache_entry
+                        .as_ref()
+                        .unwrap()
+                        .last_modified_ts
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        sort_timeline(&mut entities, SortOrder::Asc);
+        assert_eq!(
+            order(&entities),
+            vec![ts(3_000), ts(4_000), ts(1_000), ts(2_000)]
+        );
+        sort_timeline(&mut entities, SortOrder::Desc);
+        assert_eq!(
+            order(&entities),
+            vec![ts(1_000), ts(4_000), ts(3_000), ts(2_000)]
+        );
+    }
+}
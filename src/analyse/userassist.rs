@@ -0,0 +1,39 @@
+use std::{fs, path::PathBuf};
+
+use crate::file::hve::{userassist::UserAssistEntry, Parser as HveParser};
+
+#[derive(Debug)]
+pub struct UserAssistEntity {
+    pub entry: UserAssistEntry,
+    pub hive: PathBuf,
+}
+
+pub struct UserAssistAnalyzer {
+    hive_paths: Vec<PathBuf>,
+}
+
+impl UserAssistAnalyzer {
+    pub fn new(hive_paths: Vec<PathBuf>) -> Self {
+        Self { hive_paths }
+    }
+
+    pub fn userassist_entries(&self) -> crate::Result<Vec<UserAssistEntity>> {
+        let mut entities: Vec<UserAssistEntity> = Vec::new();
+        for hive_path in &self.hive_paths {
+            let mut parser = HveParser::load(hive_path)?;
+            let entries = parser.parse_userassist()?;
+            cs_eprintln!(
+                "[+] {} UserAssist entries loaded from {:?}",
+                entries.len(),
+                fs::canonicalize(hive_path).expect("could not get absolute path")
+            );
+            entities.extend(entries.into_iter().map(|entry| UserAssistEntity {
+                entry,
+                hive: hive_path.clone(),
+            }));
+        }
+        // Most recent executions first, entries that have never been executed go last
+        entities.sort_by(|x, y| y.entry.last_executed_ts.cmp(&x.entry.last_executed_ts));
+        Ok(entities)
+    }
+}
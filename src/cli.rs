@@ -1,25 +1,31 @@
-use std::collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet};
+use std::collections::{hash_map::DefaultHasher, BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::io::*;
+use std::path::Path;
 use std::time::Duration;
 
 use chrono::{DateTime, NaiveDateTime, SecondsFormat, TimeZone, Utc};
-use chrono_tz::Tz;
+use crossterm::style::{Color, Stylize};
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use prettytable::{cell, format, Row, Table};
+use regex::Regex;
 use serde::Serialize;
 use serde_json::{value::RawValue, Map, Number, Value as Json};
 use tau_engine::{Document, Value as Tau};
 use uuid::Uuid;
 
+use crate::analyse::lnk::LnkEntity;
+use crate::analyse::recyclebin::RecycleBinEntity;
 use crate::analyse::shimcache::{TimelineEntity, TimelineTimestamp, TimestampType};
+use crate::analyse::userassist::UserAssistEntity;
+use crate::ext::timezone::Timezone;
 use crate::file::hve::shimcache::EntryType;
 use crate::file::Kind as FileKind;
-use crate::hunt::{Detections, Hunt, Kind};
+use crate::hunt::{Detections, Hunt, Kind, RuleMetadata};
 use crate::rule::{Kind as RuleKind, Level, Rule, Status};
 use crate::value::Value;
-use crate::write::WRITER;
+use crate::write::{Output, WRITER};
 
 #[cfg(not(windows))]
 pub const RULE_PREFIX: &str = "‣";
@@ -33,6 +39,7 @@ const TICK_SETTINGS: (&str, u64) = ("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏ ", 80);
 #[cfg(windows)]
 const TICK_SETTINGS: (&str, u64) = (r"-\|/-", 200);
 
+/// A progress bar over the total size in bytes of the artefacts, showing the throughput and ETA.
 pub fn init_progress_bar(size: u64, msg: String) -> indicatif::ProgressBar {
     let pb = ProgressBar::new(size);
     unsafe {
@@ -43,7 +50,9 @@ pub fn init_progress_bar(size: u64, msg: String) -> indicatif::ProgressBar {
     };
     pb.set_style(
         ProgressStyle::default_bar()
-            .template("[+] {msg}: [{bar:40}] {pos}/{len} {spinner}")
+            .template(
+                "[+] {msg}: [{bar:40}] {bytes}/{total_bytes} {bytes_per_sec} (elapsed {elapsed}, eta {eta}) {spinner}",
+            )
             .expect("could not set template")
             .tick_chars(TICK_SETTINGS.0)
             .progress_chars("=>-"),
@@ -95,6 +104,14 @@ fn split_tag(tag_name: &str) -> String {
     chars.into_iter().collect()
 }
 
+fn format_matches(matched: &BTreeMap<String, String>, full: bool, length: u32) -> String {
+    matched
+        .iter()
+        .map(|(field, value)| format_field_length(&format!("{}: {}", field, value), full, length))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 fn format_time(event_time: String) -> String {
     let chunks = event_time.rsplit('.').last();
     match chunks {
@@ -107,10 +124,12 @@ pub struct Grouping<'a> {
     hits: Vec<Hit<'a>>,
     kind: &'a Kind<'a>,
     timestamp: &'a NaiveDateTime,
+    timezone: Option<Timezone>,
 }
 
 pub struct Hit<'a> {
     hunt: &'a Hunt,
+    matched: &'a BTreeMap<String, String>,
     rule: &'a Rule,
 }
 
@@ -119,9 +138,11 @@ pub fn print_log(
     hunts: &[Hunt],
     rules: &BTreeMap<Uuid, Rule>,
     local: bool,
-    timezone: Option<Tz>,
+    timezone: Option<Timezone>,
+    template: Option<&str>,
 ) -> crate::Result<()> {
     let hunts: HashMap<_, _> = hunts.iter().map(|h| (&h.id, h)).collect();
+    let mut warned = false;
     let mut rule_width = 1;
     for rule in rules.values() {
         let width = rule.name().len();
@@ -133,16 +154,16 @@ pub fn print_log(
     let mut rows = vec![];
     for detection in detections {
         for hit in &detection.hits {
-            rows.push((hit, &detection.kind));
+            rows.push((hit, &detection.kind, detection.timezone));
         }
     }
     rows.sort_by(|x, y| x.0.timestamp.cmp(&y.0.timestamp));
-    for (hit, kind) in rows {
+    for (hit, kind, tz) in rows {
         let hunt = &hunts.get(&hit.hunt).expect("could not get hunt");
         let rule = &rules.get(&hit.rule).expect("could not get rule");
         let mut columns = vec![];
 
-        let localised = if let Some(timezone) = timezone {
+        let localised = if let Some(timezone) = tz.or(timezone) {
             timezone
                 .from_local_datetime(&hit.timestamp)
                 .single()
@@ -171,18 +192,51 @@ pub fn print_log(
             _ => unimplemented!(),
         };
 
-        let name = match rule {
-            Rule::Chainsaw(rule) => {
-                columns.push("c".to_string());
-                &rule.name
+        let metadata = RuleMetadata::new(hunt, rule);
+        columns.push(
+            match metadata.source {
+                RuleKind::Chainsaw => "c",
+                RuleKind::Keyword => "k",
+                RuleKind::Sigma => "σ",
             }
-            Rule::Sigma(rule) => {
-                columns.push("σ".to_string());
-                &rule.name
-            }
-        };
+            .to_string(),
+        );
+        let name = metadata.name;
         //columns.push(format!("{: <width$}", name, width = rule_width - 1));
         //columns.push(format!("{: >6}", count));
+        let path = document.path;
+        if let Some(template) = template {
+            let data: Value;
+            let wrapper;
+            let document: Option<&dyn Document> = match &document.kind {
+                FileKind::Evtx => {
+                    data = bincode::deserialize::<Value>(&document.data)?;
+                    wrapper = crate::evtx::Wrapper(&data);
+                    Some(&wrapper)
+                }
+                FileKind::Hve
+                | FileKind::Json
+                | FileKind::Jsonl
+                | FileKind::Mft
+                | FileKind::Xml => {
+                    data = bincode::deserialize::<Value>(&document.data)?;
+                    Some(&data)
+                }
+                FileKind::Unknown => None,
+            };
+            let line = render_template(
+                template,
+                &localised,
+                name,
+                metadata.level,
+                path,
+                document,
+                &mut warned,
+            );
+            cs_println!("{}", line);
+            continue;
+        }
+
         columns.push(name.to_string());
         columns.push(format!("{}", count));
 
@@ -224,12 +278,256 @@ pub fn print_log(
             }
         }
         columns.push(values.join("  ::  "));
+        columns.push(path.display().to_string());
+
+        cs_println!("{}", columns.join("  |  "));
+    }
+    Ok(())
+}
+
+/// Interpolates the `{timestamp}`, `{rule}`, `{level}` and `{source_path}` tokens, with any other
+/// token being looked up as a field path in the document.
+fn render_template(
+    template: &str,
+    timestamp: &str,
+    rule: &str,
+    level: &Level,
+    source_path: &Path,
+    document: Option<&dyn Document>,
+    warned: &mut bool,
+) -> String {
+    let mut line = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        line.push_str(&rest[..start]);
+        let token = &rest[start + 1..end];
+        match token {
+            "timestamp" => line.push_str(timestamp),
+            "rule" => line.push_str(rule),
+            "level" => line.push_str(&level.to_string()),
+            "source_path" => line.push_str(&source_path.to_string_lossy()),
+            _ => match document.and_then(|d| d.find(token)) {
+                Some(value) => match value.to_string() {
+                    Some(v) => line.push_str(&v),
+                    None => line.push_str("<see raw event>"),
+                },
+                None => {
+                    // NOTE: Only warn once, otherwise a typo would drown out the detections.
+                    if !*warned {
+                        cs_eyellowln!(
+                            "[!] Could not resolve '{{{}}}' in the output template, rendering it as empty",
+                            token
+                        );
+                        *warned = true;
+                    }
+                }
+            },
+        }
+        rest = &rest[end + 1..];
+    }
+    line.push_str(rest);
+    line
+}
+
+/// Colours the parts of the text matched by each pattern, with each pattern getting its own colour.
+pub fn highlight(text: &str, patterns: &[Regex]) -> String {
+    const COLOURS: [Color; 6] = [
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+    ];
+    let mut spans = vec![];
+    for (i, pattern) in patterns.iter().enumerate() {
+        for m in pattern.find_iter(text) {
+            if !m.as_str().is_empty() {
+                spans.push((m.start(), m.end(), COLOURS[i % COLOURS.len()]));
+            }
+        }
+    }
+    spans.sort_by_key(|s| s.0);
+    let mut highlighted = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end, colour) in spans {
+        // NOTE: Where matches overlap the earliest one wins.
+        if start < last {
+            continue;
+        }
+        highlighted.push_str(&text[last..start]);
+        highlighted.push_str(&text[start..end].with(colour).to_string());
+        last = end;
+    }
+    highlighted.push_str(&text[last..]);
+    highlighted
+}
+
+fn localise(timestamp: &NaiveDateTime, local: bool, timezone: Option<Timezone>) -> String {
+    if let Some(timezone) = timezone {
+        timezone
+            .from_local_datetime(timestamp)
+            .single()
+            .expect("failed to localise timestamp")
+            .to_rfc3339()
+    } else if local {
+        Utc.from_local_datetime(timestamp)
+            .single()
+            .expect("failed to localise timestamp")
+            .to_rfc3339()
+    } else {
+        DateTime::<Utc>::from_utc(*timestamp, Utc).to_rfc3339()
+    }
+}
+
+/// The timestamp in UTC, undoing any localisation from the hunt so that hits from documents in
+/// different timezones can be ordered.
+fn utc(timestamp: &NaiveDateTime, timezone: Option<Timezone>) -> DateTime<Utc> {
+    match timezone {
+        Some(timezone) => timezone
+            .from_local_datetime(timestamp)
+            .single()
+            .expect("failed to localise timestamp")
+            .with_timezone(&Utc),
+        None => DateTime::<Utc>::from_utc(*timestamp, Utc),
+    }
+}
+
+pub fn print_time_span(
+    span: Option<(NaiveDateTime, NaiveDateTime)>,
+    local: bool,
+    timezone: Option<Timezone>,
+) {
+    match span {
+        Some((earliest, latest)) => {
+            cs_eprintln!(
+                "[+] Detections span: {} to {}",
+                localise(&earliest, local, timezone),
+                localise(&latest, local, timezone)
+            );
+        }
+        None => {
+            cs_eprintln!("[+] Detections span: no dated detections");
+        }
+    }
+}
 
-        println!("{}", columns.join("  |  "));
+/// The overview of a hunt as json, the counts are taken before any head or tail is applied.
+pub fn summary(
+    hits: usize,
+    documents: usize,
+    rule_hits: &HashMap<Uuid, usize>,
+    rules: &BTreeMap<Uuid, Rule>,
+    span: Option<(NaiveDateTime, NaiveDateTime)>,
+    local: bool,
+    timezone: Option<Timezone>,
+) -> Json {
+    let mut per_rule: BTreeMap<String, usize> = BTreeMap::new();
+    let mut per_level: BTreeMap<String, usize> = BTreeMap::new();
+    for (id, count) in rule_hits {
+        let rule = rules.get(id).expect("could not get rule");
+        *per_rule.entry(rule.name().clone()).or_insert(0) += count;
+        *per_level.entry(rule.level().to_string()).or_insert(0) += count;
+    }
+    let span = span.map(|(earliest, latest)| {
+        serde_json::json!({
+            "earliest": localise(&earliest, local, timezone),
+            "latest": localise(&latest, local, timezone),
+        })
+    });
+    serde_json::json!({
+        "detections": hits,
+        "documents": documents,
+        "levels": per_level,
+        "rules": per_rule,
+        "span": span,
+    })
+}
+
+pub fn print_group_by(detections: &[Detections], hunts: &[Hunt], field: &str) -> crate::Result<()> {
+    let hunts: HashMap<_, _> = hunts.iter().map(|h| (&h.id, h)).collect();
+    let mut groups: HashMap<String, usize> = HashMap::new();
+    for detection in detections {
+        let document = match &detection.kind {
+            Kind::Individual { document } => document,
+            Kind::Aggregate { documents } => documents.first().expect("could not get document"),
+            _ => unimplemented!(),
+        };
+        let data = bincode::deserialize::<Value>(&document.data)?;
+        for hit in &detection.hits {
+            let hunt = &hunts.get(&hit.hunt).expect("could not get hunt");
+            let wrapper;
+            let mapped = match &document.kind {
+                FileKind::Evtx => {
+                    wrapper = crate::evtx::Wrapper(&data);
+                    hunt.mapper.mapped(&wrapper)
+                }
+                FileKind::Hve
+                | FileKind::Json
+                | FileKind::Jsonl
+                | FileKind::Mft
+                | FileKind::Xml => hunt.mapper.mapped(&data),
+                FileKind::Unknown => continue,
+            };
+            // NOTE: Documents that lack the field, or where it is not a scalar, are bucketed
+            // together rather than dropped so that the counts still add up to the hits.
+            let value = hunt
+                .mapper
+                .fields()
+                .iter()
+                .find(|f| f.name == field)
+                .and_then(|f| mapped.find(&f.from))
+                .and_then(|v| v.to_string())
+                .unwrap_or_else(|| "(unknown)".to_owned());
+            *groups.entry(value).or_insert(0) += 1;
+        }
     }
+
+    let mut groups = groups.into_iter().collect::<Vec<_>>();
+    groups.sort_by(|x, y| y.1.cmp(&x.1).then_with(|| x.0.cmp(&y.0)));
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_BOX_CHARS);
+    table.set_titles(Row::new(vec![
+        cell!(field).style_spec("c"),
+        cell!("count").style_spec("c"),
+    ]));
+    for (value, count) in groups {
+        table.add_row(Row::new(vec![cell!(value), cell!(count)]));
+    }
+    cs_greenln!("\n[+] Detections grouped by: {}", field);
+    cs_print_table!(table);
     Ok(())
 }
 
+/// Prints why each hit matched, for hunts that were run with explanations enabled.
+pub fn print_explanations(
+    detections: &[Detections],
+    rules: &BTreeMap<Uuid, Rule>,
+    local: bool,
+    timezone: Option<Timezone>,
+) {
+    for detection in detections {
+        for hit in &detection.hits {
+            let explanation = match &hit.explanation {
+                Some(explanation) => explanation,
+                None => continue,
+            };
+            let rule = rules.get(&hit.rule).expect("could not get rule");
+            cs_greenln!(
+                "\n[+] Why '{}' matched at {}:",
+                rule.name(),
+                localise(&hit.timestamp, local, detection.timezone.or(timezone))
+            );
+            cs_println!("{}", explanation);
+        }
+    }
+}
+
 pub fn print_detections(
     detections: &[Detections],
     hunts: &[Hunt],
@@ -237,8 +535,9 @@ pub fn print_detections(
     column_width: u32,
     full: bool,
     local: bool,
+    matches: bool,
     metadata: bool,
-    timezone: Option<Tz>,
+    timezone: Option<Timezone>,
 ) {
     let format = format::FormatBuilder::new()
         .column_separator('│')
@@ -289,7 +588,11 @@ pub fn print_detections(
             let hunt = &hunts.get(&hit.hunt).expect("could not get hunt");
             let rule = &rules.get(&hit.rule).expect("could not get rule");
             let hits = hits.entry((&hunt.group, &hit.timestamp)).or_insert(vec![]);
-            (*hits).push(Hit { hunt, rule });
+            (*hits).push(Hit {
+                hunt,
+                matched: &hit.matched,
+                rule,
+            });
         }
         for ((group, timestamp), mut hits) in hits {
             hits.sort_by(|x, y| x.rule.name().cmp(y.rule.name()));
@@ -297,6 +600,7 @@ pub fn print_detections(
             (*groups).push(Grouping {
                 kind: &detection.kind,
                 timestamp,
+                timezone: detection.timezone,
                 hits,
             });
         }
@@ -315,6 +619,7 @@ pub fn print_detections(
             let mut cells = vec![
                 cell!("timestamp").style_spec("c"),
                 cell!("detections").style_spec("c"),
+                cell!("source_path").style_spec("c"),
             ];
             if headers.is_empty() {
                 cells.push(cell!("data").style_spec("c"));
@@ -323,10 +628,13 @@ pub fn print_detections(
                     cells.push(cell!(header).style_spec("c"));
                 }
             }
+            if matches {
+                cells.push(cell!("matches").style_spec("c"));
+            }
             table.add_row(Row::new(cells));
 
             for grouping in group {
-                let mut localised = if let Some(timezone) = timezone {
+                let mut localised = if let Some(timezone) = grouping.timezone.or(timezone) {
                     timezone
                         .from_local_datetime(grouping.timestamp)
                         .single()
@@ -364,10 +672,15 @@ pub fn print_detections(
                     let json = serde_json::to_string(&document.data)
                         .expect("could not serialise document");
                     let rules = grouping.hits.iter().map(|hit| hit.rule).collect();
-                    rows.push((
-                        0,
-                        vec![cell!(format_field_length(&json, false, column_width))],
-                    ));
+                    let mut cells = vec![cell!(format_field_length(&json, false, column_width))];
+                    if matches {
+                        let mut matched = BTreeMap::new();
+                        for hit in &grouping.hits {
+                            matched.extend(hit.matched.clone());
+                        }
+                        cells.push(cell!(format_matches(&matched, full, column_width)));
+                    }
+                    rows.push((0, cells));
                     seen.insert(0, rules);
                 } else {
                     // What we do here is hash each row since if the fields are the same but the values
@@ -437,6 +750,11 @@ pub fn print_detections(
                             }
                             cells.push(cell!(""));
                         }
+                        if matches {
+                            let matched = format_matches(hit.matched, full, column_width);
+                            matched.hash(&mut hasher);
+                            cells.push(cell!(matched));
+                        }
                         let id = hasher.finish();
                         if !seen.contains_key(&id) {
                             rows.push((id, cells));
@@ -457,6 +775,8 @@ pub fn print_detections(
                             cell!("authors").style_spec("c"),
                             cell!("level").style_spec("c"),
                             cell!("status").style_spec("c"),
+                            cell!("tags").style_spec("c"),
+                            cell!("references").style_spec("c"),
                         ]));
                         for rule in &rules {
                             match rule {
@@ -467,6 +787,23 @@ pub fn print_detections(
                                         cell!(c.authors.join("\n")),
                                         cell!(c.level),
                                         cell!(c.status),
+                                        cell!(c.tags.as_deref().unwrap_or_default().join("\n")),
+                                        cell!(c
+                                            .references
+                                            .as_deref()
+                                            .unwrap_or_default()
+                                            .join("\n")),
+                                    ]));
+                                }
+                                Rule::Keyword(k) => {
+                                    table.add_row(Row::new(vec![
+                                        cell!('k'),
+                                        cell!(split_tag(&k.name)),
+                                        cell!(k.authors.join("\n")),
+                                        cell!(k.level),
+                                        cell!(k.status),
+                                        cell!(""),
+                                        cell!(""),
                                     ]));
                                 }
                                 Rule::Sigma(s) => {
@@ -476,6 +813,12 @@ pub fn print_detections(
                                         cell!(s.authors.join("\n")),
                                         cell!(s.level),
                                         cell!(s.status),
+                                        cell!(s.tags.as_deref().unwrap_or_default().join("\n")),
+                                        cell!(s
+                                            .references
+                                            .as_deref()
+                                            .unwrap_or_default()
+                                            .join("\n")),
                                     ]));
                                 }
                             }
@@ -488,6 +831,11 @@ pub fn print_detections(
                             .collect::<Vec<_>>()
                             .join("\n")));
                     }
+                    cells.push(cell!(format_field_length(
+                        &document.path.to_string_lossy(),
+                        full,
+                        column_width
+                    )));
                     cells.extend(row);
                     table.add_row(Row::new(cells));
                 }
@@ -499,7 +847,11 @@ pub fn print_detections(
     }
 }
 
-pub fn print_shimcache_analysis_csv(timeline: &Vec<TimelineEntity>) -> crate::Result<()> {
+pub fn print_shimcache_analysis_csv(
+    timeline: &Vec<TimelineEntity>,
+    verbose: bool,
+    baseline: Option<&HashSet<String>>,
+) -> crate::Result<()> {
     let path = unsafe { &WRITER.path };
     let csv = if let Some(path) = path {
         Some(prettytable::csv::Writer::from_path(path)?)
@@ -540,8 +892,34 @@ pub fn print_shimcache_analysis_csv(timeline: &Vec<TimelineEntity>) -> crate::Re
         "Timestamp Description",
         "Raw Entry",
     ];
-    let header_cells = headers.map(|s| cell!(s)).to_vec();
+    // Named capture groups in the patterns get a column each, after the fixed columns
+    let capture_names = timeline
+        .iter()
+        .flat_map(|e| e.captures.keys())
+        .collect::<BTreeSet<_>>();
+    let mut header_cells = headers.map(|s| cell!(s)).to_vec();
+    if verbose {
+        header_cells.push(cell!("Amcache Candidate Path"));
+        header_cells.push(cell!("Amcache Candidate Timestamp"));
+    }
+    if baseline.is_some() {
+        header_cells.push(cell!("Baseline"));
+    }
+    header_cells.extend(capture_names.iter().map(|n| cell!(n)));
     table.add_row(Row::new(header_cells));
+    // The amcache rows have no candidate or baseline status, but still need the cells to keep the
+    // columns aligned
+    let no_candidate = || {
+        let mut cells = if verbose {
+            vec![cell!(""), cell!("")]
+        } else {
+            vec![]
+        };
+        if baseline.is_some() {
+            cells.push(cell!(""));
+        }
+        cells
+    };
 
     let mut timeline_entry_nr = 0;
     for entity in timeline {
@@ -593,7 +971,23 @@ pub fn print_shimcache_analysis_csv(timeline: &Vec<TimelineEntity>) -> crate::Re
             ts_description,
             &raw_entry,
         ];
-        let cells = shimcache_row.map(|s| cell!(s)).to_vec();
+        let mut cells = shimcache_row.map(|s| cell!(s)).to_vec();
+        match &entity.amcache_candidate {
+            Some(candidate) if verbose => {
+                cells.push(cell!(candidate.path));
+                cells.push(cell!(format_ts(&candidate.key_last_modified_ts)));
+            }
+            _ if verbose => cells.extend([cell!(""), cell!("")]),
+            _ => {}
+        }
+        if let Some(baseline) = baseline {
+            cells.push(cell!(if entity.is_new(baseline) { "new" } else { "" }));
+        }
+        cells.extend(
+            capture_names
+                .iter()
+                .map(|n| cell!(entity.captures.get(*n).map(|c| c.as_str()).unwrap_or(""))),
+        );
         table.add_row(Row::new(cells));
         timeline_entry_nr += 1;
 
@@ -624,11 +1018,42 @@ pub fn print_shimcache_analysis_csv(timeline: &Vec<TimelineEntity>) -> crate::Re
                     "",
                     &raw_entry,
                 ];
-                let cells = amcache_row.map(|s| cell!(s)).to_vec();
+                let mut cells = amcache_row.map(|s| cell!(s)).to_vec();
+                cells.extend(no_candidate());
+                cells.extend(capture_names.iter().map(|_| cell!("")));
                 table.add_row(Row::new(cells));
                 timeline_entry_nr += 1;
             }
         }
+
+        // If there is a matching amcache driver, add a separate row for it
+        if let Some(driver_entry) = &entity.amcache_driver {
+            let driver_timestamp = format_ts(&driver_entry.key_last_modified_ts);
+            let file_path = driver_entry.path.clone();
+            let sha1_hash = driver_entry
+                .sha1_hash
+                .as_ref()
+                .unwrap_or(&String::new())
+                .to_string();
+            let entry_type = "AmcacheDriverEntry";
+            let raw_entry = serde_json::to_string(driver_entry.as_ref())?;
+            let timeline_entry_nr_string = timeline_entry_nr.to_string();
+            let driver_row = [
+                &driver_timestamp,
+                &file_path,
+                "",
+                &sha1_hash,
+                &timeline_entry_nr_string,
+                entry_type,
+                "Amcache driver last update",
+                &raw_entry,
+            ];
+            let mut cells = driver_row.map(|s| cell!(s)).to_vec();
+            cells.extend(no_candidate());
+            cells.extend(capture_names.iter().map(|_| cell!("")));
+            table.add_row(Row::new(cells));
+            timeline_entry_nr += 1;
+        }
     }
     if let Some(writer) = csv {
         table.to_csv_writer(writer)?;
@@ -647,12 +1072,283 @@ pub fn print_shimcache_analysis_csv(timeline: &Vec<TimelineEntity>) -> crate::Re
     Ok(())
 }
 
+pub fn print_recyclebin_analysis_csv(entities: &[RecycleBinEntity]) -> crate::Result<()> {
+    let path = unsafe { &WRITER.path };
+    let csv = if let Some(path) = path {
+        Some(prettytable::csv::Writer::from_path(path)?)
+    } else {
+        None
+    };
+    let format = format::FormatBuilder::new()
+        .column_separator('│')
+        .borders('│')
+        .separators(
+            &[format::LinePosition::Top],
+            format::LineSeparator::new('─', '┬', '┌', '┐'),
+        )
+        .separators(
+            &[format::LinePosition::Intern],
+            format::LineSeparator::new('─', '┼', '├', '┤'),
+        )
+        .separators(
+            &[format::LinePosition::Bottom],
+            format::LineSeparator::new('─', '┴', '└', '┘'),
+        )
+        .padding(1, 1)
+        .build();
+
+    let mut table = Table::new();
+    table.set_format(format);
+    let headers = [
+        "Deleted",
+        "Original Path",
+        "File Size",
+        "$I File",
+        "Raw Entry",
+    ];
+    let header_cells = headers.map(|s| cell!(s)).to_vec();
+    table.add_row(Row::new(header_cells));
+
+    for entity in entities {
+        let deleted = entity
+            .deleted_ts
+            .to_rfc3339_opts(SecondsFormat::AutoSi, true);
+        let file_size = entity.file_size.to_string();
+        let metadata_path = entity.metadata_path.display().to_string();
+        let raw_entry = serde_json::to_string(entity)?;
+        let row = [
+            &deleted,
+            &entity.original_path,
+            &file_size,
+            &metadata_path,
+            &raw_entry,
+        ];
+        let cells = row.map(|s| cell!(s)).to_vec();
+        table.add_row(Row::new(cells));
+    }
+    if let Some(writer) = csv {
+        table.to_csv_writer(writer)?;
+    } else {
+        // Truncate the number of columns for terminal output
+        const N_FIRST_COLUMNS: usize = 3;
+        for row in &mut table {
+            for i in (N_FIRST_COLUMNS..row.len()).rev() {
+                row.remove_cell(i);
+            }
+        }
+        cs_print_table!(table);
+        cs_eyellowln!("[!] Truncated output. Use --output to get all columns.");
+    }
+
+    Ok(())
+}
+
+pub fn print_userassist_analysis_csv(entities: &[UserAssistEntity]) -> crate::Result<()> {
+    let path = unsafe { &WRITER.path };
+    let csv = if let Some(path) = path {
+        Some(prettytable::csv::Writer::from_path(path)?)
+    } else {
+        None
+    };
+    let format = format::FormatBuilder::new()
+        .column_separator('│')
+        .borders('│')
+        .separators(
+            &[format::LinePosition::Top],
+            format::LineSeparator::new('─', '┬', '┌', '┐'),
+        )
+        .separators(
+            &[format::LinePosition::Intern],
+            format::LineSeparator::new('─', '┼', '├', '┤'),
+        )
+        .separators(
+            &[format::LinePosition::Bottom],
+            format::LineSeparator::new('─', '┴', '└', '┘'),
+        )
+        .padding(1, 1)
+        .build();
+
+    fn format_ts(ts: &DateTime<Utc>) -> String {
+        ts.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+    }
+
+    fn format_focus_time(ms: u32) -> String {
+        let seconds = ms / 1000;
+        format!(
+            "{:02}:{:02}:{:02}.{:03}",
+            seconds / 3600,
+            (seconds / 60) % 60,
+            seconds % 60,
+            ms % 1000
+        )
+    }
+
+    let mut table = Table::new();
+    table.set_format(format);
+    let headers = [
+        "Last Executed",
+        "Program Path",
+        "Run Count",
+        "Focus Count",
+        "Focus Time",
+        "GUID",
+        "Hive",
+        "Raw Entry",
+    ];
+    let header_cells = headers.map(|s| cell!(s)).to_vec();
+    table.add_row(Row::new(header_cells));
+
+    for entity in entities {
+        let entry = &entity.entry;
+        let last_executed = entry
+            .last_executed_ts
+            .as_ref()
+            .map(format_ts)
+            .unwrap_or_default();
+        let run_count = entry.run_count.to_string();
+        let focus_count = entry.focus_count.map(|c| c.to_string()).unwrap_or_default();
+        let focus_time = entry
+            .focus_time_ms
+            .map(format_focus_time)
+            .unwrap_or_default();
+        let hive = entity.hive.display().to_string();
+        let raw_entry = serde_json::to_string(entry)?;
+        let row = [
+            &last_executed,
+            &entry.path,
+            &run_count,
+            &focus_count,
+            &focus_time,
+            &entry.guid,
+            &hive,
+            &raw_entry,
+        ];
+        let cells = row.map(|s| cell!(s)).to_vec();
+        table.add_row(Row::new(cells));
+    }
+    if let Some(writer) = csv {
+        table.to_csv_writer(writer)?;
+    } else {
+        // Truncate the number of columns for terminal output
+        const N_FIRST_COLUMNS: usize = 5;
+        for row in &mut table {
+            for i in (N_FIRST_COLUMNS..row.len()).rev() {
+                row.remove_cell(i);
+            }
+        }
+        cs_print_table!(table);
+        cs_eyellowln!("[!] Truncated output. Use --output to get all columns.");
+    }
+
+    Ok(())
+}
+
+pub fn print_lnk_analysis_csv(entities: &[LnkEntity]) -> crate::Result<()> {
+    let path = unsafe { &WRITER.path };
+    let csv = if let Some(path) = path {
+        Some(prettytable::csv::Writer::from_path(path)?)
+    } else {
+        None
+    };
+    let format = format::FormatBuilder::new()
+        .column_separator('│')
+        .borders('│')
+        .separators(
+            &[format::LinePosition::Top],
+            format::LineSeparator::new('─', '┬', '┌', '┐'),
+        )
+        .separators(
+            &[format::LinePosition::Intern],
+            format::LineSeparator::new('─', '┼', '├', '┤'),
+        )
+        .separators(
+            &[format::LinePosition::Bottom],
+            format::LineSeparator::new('─', '┴', '└', '┘'),
+        )
+        .padding(1, 1)
+        .build();
+
+    fn format_ts(ts: &Option<DateTime<Utc>>) -> String {
+        ts.as_ref()
+            .map(|ts| ts.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+            .unwrap_or_default()
+    }
+
+    let mut table = Table::new();
+    table.set_format(format);
+    let headers = [
+        "Target Modified",
+        "Target Path",
+        "Arguments",
+        "Target Created",
+        "Target Accessed",
+        "Working Dir",
+        "Machine ID",
+        "Volume Serial",
+        "Volume Label",
+        "Target Size",
+        "LNK File",
+        "Raw Entry",
+    ];
+    let header_cells = headers.map(|s| cell!(s)).to_vec();
+    table.add_row(Row::new(header_cells));
+
+    for entity in entities {
+        let modified = format_ts(&entity.target_modified_ts);
+        let created = format_ts(&entity.target_created_ts);
+        let accessed = format_ts(&entity.target_accessed_ts);
+        // NOTE: Shortcuts to shell folders have no target path, so fall back to the relative path.
+        let target_path = entity
+            .target_path
+            .as_ref()
+            .or(entity.relative_path.as_ref())
+            .cloned()
+            .unwrap_or_default();
+        let file_size = entity.file_size.to_string();
+        let lnk_path = entity.lnk_path.display().to_string();
+        let raw_entry = serde_json::to_string(entity)?;
+        let row: [&str; 12] = [
+            &modified,
+            &target_path,
+            entity.arguments.as_deref().unwrap_or_default(),
+            &created,
+            &accessed,
+            entity.working_dir.as_deref().unwrap_or_default(),
+            entity.machine_id.as_deref().unwrap_or_default(),
+            entity.volume_serial.as_deref().unwrap_or_default(),
+            entity.volume_label.as_deref().unwrap_or_default(),
+            &file_size,
+            &lnk_path,
+            &raw_entry,
+        ];
+        let cells = row.map(|s| cell!(s)).to_vec();
+        table.add_row(Row::new(cells));
+    }
+    if let Some(writer) = csv {
+        table.to_csv_writer(writer)?;
+    } else {
+        // Truncate the number of columns for terminal output
+        const N_FIRST_COLUMNS: usize = 3;
+        for row in &mut table {
+            for i in (N_FIRST_COLUMNS..row.len()).rev() {
+                row.remove_cell(i);
+            }
+        }
+        cs_print_table!(table);
+        cs_eyellowln!("[!] Truncated output. Use --output to get all columns.");
+    }
+
+    Ok(())
+}
+
 pub fn print_csv(
     detections: &[Detections],
     hunts: &[Hunt],
     rules: &BTreeMap<Uuid, Rule>,
     local: bool,
-    timezone: Option<Tz>,
+    timezone: Option<Timezone>,
+    append: bool,
+    renames: &HashMap<String, String>,
 ) -> crate::Result<()> {
     let directory = unsafe {
         WRITER
@@ -660,6 +1356,7 @@ pub fn print_csv(
             .as_ref()
             .expect("could not get output directory")
     };
+    let compress = unsafe { WRITER.compress };
     fs::create_dir_all(directory)?;
 
     // Build headers
@@ -693,7 +1390,11 @@ pub fn print_csv(
             let hunt = &hunts.get(&hit.hunt).expect("could not get hunt");
             let rule = &rules.get(&hit.rule).expect("could not get rule");
             let hits = hits.entry((&hunt.group, &hit.timestamp)).or_insert(vec![]);
-            (*hits).push(Hit { hunt, rule });
+            (*hits).push(Hit {
+                hunt,
+                matched: &hit.matched,
+                rule,
+            });
         }
         for ((group, timestamp), mut hits) in hits {
             hits.sort_by(|x, y| x.rule.name().cmp(y.rule.name()));
@@ -701,6 +1402,7 @@ pub fn print_csv(
             (*groups).push(Grouping {
                 kind: &detection.kind,
                 timestamp,
+                timezone: detection.timezone,
                 hits,
             });
         }
@@ -713,10 +1415,12 @@ pub fn print_csv(
         group.sort_by(|x, y| x.timestamp.cmp(y.timestamp));
 
         // FIXME: Handle name clashes
-        let filename = format!("{}.csv", key.replace(' ', "_").to_lowercase());
+        let mut filename = format!("{}.csv", key.replace(' ', "_").to_lowercase());
+        if compress {
+            filename.push_str(".gz");
+        }
         let path = directory.join(&filename);
-        let mut csv = prettytable::csv::Writer::from_path(path)?;
-        cs_eprintln!("[+] Created {}", filename);
+        let appending = append && path.exists() && path.metadata()?.len() > 0;
 
         if let Some(headers) = headers.remove(key) {
             let mut cells = vec!["timestamp", "detections", "path"];
@@ -727,10 +1431,31 @@ pub fn print_csv(
                     cells.push(header);
                 }
             }
-            csv.write_record(cells)?;
+            let cells: Vec<&str> = cells.into_iter().map(|c| rename(c, renames)).collect();
+            let mut csv = if appending {
+                // NOTE: The header is only written once, so the rows we append must line up with
+                // the columns of the existing file or it would be corrupted.
+                let existing = prettytable::csv::Reader::from_path(&path)?
+                    .headers()?
+                    .clone();
+                if !existing.iter().eq(cells.iter().copied()) {
+                    anyhow::bail!(
+                        "Unable to append to {} as its columns do not match the detections",
+                        path.display()
+                    );
+                }
+                cs_eprintln!("[+] Appending to {}", filename);
+                prettytable::csv::Writer::from_writer(Output::append(&path, compress)?)
+            } else {
+                cs_eprintln!("[+] Created {}", filename);
+                let mut csv =
+                    prettytable::csv::Writer::from_writer(Output::create(&path, compress)?);
+                csv.write_record(&cells)?;
+                csv
+            };
 
             for grouping in group {
-                let localised = if let Some(timezone) = timezone {
+                let localised = if let Some(timezone) = grouping.timezone.or(timezone) {
                     timezone
                         .from_local_datetime(grouping.timestamp)
                         .single()
@@ -847,6 +1572,7 @@ pub fn print_csv(
                     csv.write_record(cells)?;
                 }
             }
+            csv.into_inner().map_err(|e| e.into_error())?.finish()?;
         }
     }
     Ok(())
@@ -860,10 +1586,20 @@ pub struct Detection<'a> {
     pub name: &'a String,
     pub timestamp: String,
 
+    #[serde(skip_serializing_if = "Vec::is_empty")]
     pub authors: &'a Vec<String>,
     pub level: &'a Level,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub matched: &'a BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub references: &'a Option<Vec<String>>,
     pub source: RuleKind,
+    pub source_path: &'a Path,
     pub status: &'a Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: &'a Option<Vec<String>>,
 
     #[serde(flatten, skip_serializing_if = "Option::is_none")]
     pub sigma: Option<Sigma<'a>>,
@@ -877,10 +1613,6 @@ pub struct Sigma<'a> {
     pub id: &'a Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logsource: &'a Option<crate::rule::sigma::LogSource>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub references: &'a Option<Vec<String>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tags: &'a Option<Vec<String>>,
 }
 
 pub fn print_json(
@@ -888,7 +1620,9 @@ pub fn print_json(
     hunts: &[Hunt],
     rules: &BTreeMap<Uuid, Rule>,
     local: bool,
-    timezone: Option<Tz>,
+    timezone: Option<Timezone>,
+    previous: &[Json],
+    renames: &HashMap<String, String>,
 ) -> crate::Result<()> {
     let hunts: HashMap<_, _> = hunts.iter().map(|h| (&h.id, h)).collect();
     let mut detections = detections
@@ -898,7 +1632,7 @@ pub fn print_json(
             for hit in &d.hits {
                 let hunt = hunts.get(&hit.hunt).expect("could not get rule!");
                 let rule = rules.get(&hit.rule).expect("could not get rule!");
-                let localised = if let Some(timezone) = timezone {
+                let localised = if let Some(timezone) = d.timezone.or(timezone) {
                     timezone
                         .from_local_datetime(&hit.timestamp)
                         .single()
@@ -912,47 +1646,192 @@ pub fn print_json(
                 } else {
                     DateTime::<Utc>::from_utc(hit.timestamp, Utc).to_rfc3339()
                 };
+                let utc = utc(&hit.timestamp, d.timezone.or(timezone));
                 match rule {
-                    Rule::Chainsaw(c) => detections.push(Detection {
-                        authors: &c.authors,
-                        group: &hunt.group,
-                        kind: &d.kind,
-                        level: &c.level,
-                        name: &c.name,
-                        source: RuleKind::Chainsaw,
-                        status: &c.status,
-                        timestamp: localised,
+                    Rule::Chainsaw(c) => detections.push((
+                        utc,
+                        Detection {
+                            authors: &c.authors,
+                            group: &hunt.group,
+                            kind: &d.kind,
+                            level: &c.level,
+                            matched: &hit.matched,
+                            explanation: hit.explanation.as_deref(),
+                            name: &c.name,
+                            references: &c.references,
+                            tags: &c.tags,
+                            source: RuleKind::Chainsaw,
+                            source_path: d.kind.path(),
+                            status: &c.status,
+                            timestamp: localised,
 
-                        sigma: None,
-                    }),
+                            sigma: None,
+                        },
+                    )),
+                    Rule::Keyword(k) => detections.push((
+                        utc,
+                        Detection {
+                            authors: &k.authors,
+                            group: &hunt.group,
+                            kind: &d.kind,
+                            level: &k.level,
+                            matched: &hit.matched,
+                            explanation: hit.explanation.as_deref(),
+                            name: &k.name,
+                            references: &None,
+                            tags: &None,
+                            source: RuleKind::Keyword,
+                            source_path: d.kind.path(),
+                            status: &k.status,
+                            timestamp: localised,
+
+                            sigma: None,
+                        },
+                    )),
                     Rule::Sigma(s) => {
                         let sigma = Sigma {
                             falsepositives: &s.falsepositives,
                             id: &s.id,
                             logsource: &s.logsource,
-                            references: &s.references,
-                            tags: &s.tags,
                         };
-                        detections.push(Detection {
-                            authors: &s.authors,
-                            group: &hunt.group,
-                            kind: &d.kind,
-                            level: &s.level,
-                            name: &s.name,
-                            source: RuleKind::Sigma,
-                            status: &s.status,
-                            timestamp: localised,
-
-                            sigma: Some(sigma),
-                        })
+                        detections.push((
+                            utc,
+                            Detection {
+                                authors: &s.authors,
+                                group: &hunt.group,
+                                kind: &d.kind,
+                                level: &s.level,
+                                matched: &hit.matched,
+                                explanation: hit.explanation.as_deref(),
+                                name: &s.name,
+                                references: &s.references,
+                                tags: &s.tags,
+                                source: RuleKind::Sigma,
+                                source_path: d.kind.path(),
+                                status: &s.status,
+                                timestamp: localised,
+
+                                sigma: Some(sigma),
+                            },
+                        ))
                     }
                 }
             }
             detections
         })
-        .collect::<Vec<Detection>>();
-    detections.sort_by(|x, y| x.timestamp.cmp(&y.timestamp));
-    cs_print_json!(&detections)?;
+        .collect::<Vec<(DateTime<Utc>, Detection)>>();
+    // NOTE: The localised timestamps can have different offsets, so we order on them in UTC.
+    detections.sort_by_key(|(utc, _)| *utc);
+    let detections = detections
+        .into_iter()
+        .map(|(_, detection)| detection)
+        .collect::<Vec<_>>();
+    if renames.is_empty() {
+        print_json_array(&detections, previous)
+    } else {
+        let detections = detections
+            .iter()
+            .map(|d| rename_keys(d, renames))
+            .collect::<crate::Result<Vec<_>>>()?;
+        print_json_array(&detections, previous)
+    }
+}
+
+fn print_json_array<T: Serialize>(detections: &[T], previous: &[Json]) -> crate::Result<()> {
+    if previous.is_empty() {
+        cs_print_json!(&detections)?;
+    } else {
+        // NOTE: When appending we rebuild the array with the previous results first, so that the
+        // output remains a single valid JSON array no matter how many runs are merged into it.
+        cs_print!("[");
+        for (i, value) in previous.iter().enumerate() {
+            if i != 0 {
+                cs_print!(",");
+            }
+            cs_print_json!(value)?;
+        }
+        for detection in &detections {
+            cs_print!(",");
+            cs_print_json!(detection)?;
+        }
+        cs_print!("]");
+    }
+    Ok(())
+}
+
+/// Prints the detections as a STIX 2.1 bundle, with an indicator for each rule that matched and a
+/// sighting of it for each artefact that it matched in.
+pub fn print_stix(
+    detections: &[Detections],
+    rules: &BTreeMap<Uuid, Rule>,
+    timezone: Option<Timezone>,
+) -> crate::Result<()> {
+    let mut sightings: BTreeMap<(&Uuid, &Path), (usize, DateTime<Utc>, DateTime<Utc>)> =
+        BTreeMap::new();
+    for detection in detections {
+        let path = detection.kind.path();
+        for hit in &detection.hits {
+            // NOTE: STIX timestamps must be in UTC.
+            let timestamp = utc(&hit.timestamp, detection.timezone.or(timezone));
+            let sighting = sightings
+                .entry((&hit.rule, path))
+                .or_insert((0, timestamp, timestamp));
+            sighting.0 += 1;
+            sighting.1 = sighting.1.min(timestamp);
+            sighting.2 = sighting.2.max(timestamp);
+        }
+    }
+
+    let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let mut indicators: HashMap<&Uuid, String> = HashMap::new();
+    let mut objects = vec![];
+    for ((id, path), (count, first, last)) in sightings {
+        let indicator = match indicators.get(id) {
+            Some(indicator) => indicator.clone(),
+            None => {
+                let rule = rules.get(id).expect("could not get rule");
+                let indicator = format!("indicator--{}", Uuid::new_v4());
+                // NOTE: Rules cannot be expressed in STIX patterning, so the pattern matches on
+                // the detection itself through a custom observable.
+                let name = rule.name().replace('\\', "\\\\").replace('\'', "\\'");
+                let mut object = serde_json::json!({
+                    "type": "indicator",
+                    "spec_version": "2.1",
+                    "id": indicator,
+                    "created": now,
+                    "modified": now,
+                    "name": rule.name(),
+                    "pattern": format!("[x-chainsaw-detection:rule = '{}']", name),
+                    "pattern_type": "stix",
+                    "valid_from": now,
+                    "x_chainsaw_level": rule.level().to_string(),
+                });
+                if !rule.tags().is_empty() {
+                    object["labels"] = serde_json::json!(rule.tags());
+                }
+                objects.push(object);
+                indicators.insert(id, indicator.clone());
+                indicator
+            }
+        };
+        objects.push(serde_json::json!({
+            "type": "sighting",
+            "spec_version": "2.1",
+            "id": format!("sighting--{}", Uuid::new_v4()),
+            "created": now,
+            "modified": now,
+            "sighting_of_ref": indicator,
+            "count": count,
+            "first_seen": first.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "last_seen": last.to_rfc3339_opts(SecondsFormat::Millis, true),
+            "x_chainsaw_path": path,
+        }));
+    }
+    cs_print_json!(&serde_json::json!({
+        "type": "bundle",
+        "id": format!("bundle--{}", Uuid::new_v4()),
+        "objects": objects,
+    }))?;
     Ok(())
 }
 
@@ -961,8 +1840,9 @@ pub fn print_jsonl(
     hunts: &[Hunt],
     rules: &BTreeMap<Uuid, Rule>,
     local: bool,
-    timezone: Option<Tz>,
+    timezone: Option<Timezone>,
     cache: Option<fs::File>,
+    renames: &HashMap<String, String>,
 ) -> crate::Result<()> {
     let hunts: HashMap<_, _> = hunts.iter().map(|h| (&h.id, h)).collect();
     let mut hits: Vec<(_, _, _)> = detections
@@ -970,7 +1850,7 @@ pub fn print_jsonl(
         .flat_map(|d| {
             let mut scratch = Vec::with_capacity(d.hits.len());
             for hit in &d.hits {
-                let localised = if let Some(timezone) = timezone {
+                let localised = if let Some(timezone) = d.timezone.or(timezone) {
                     timezone
                         .from_local_datetime(&hit.timestamp)
                         .single()
@@ -989,7 +1869,8 @@ pub fn print_jsonl(
             scratch
         })
         .collect();
-    hits.sort_by(|x, y| x.0.cmp(&y.0));
+    // NOTE: The localised timestamps can have different offsets, so we order on them in UTC.
+    hits.sort_by_key(|(_, hit, d)| utc(&hit.timestamp, d.timezone.or(timezone)));
     // TODO: Dedupe, maybe just macro it...
     if let Some(cache) = cache.as_ref() {
         let mut f = BufReader::new(cache);
@@ -1002,28 +1883,53 @@ pub fn print_jsonl(
                     group: &hunt.group,
                     kind: &d.kind,
                     level: &c.level,
+                    matched: &hit.matched,
+                    explanation: hit.explanation.as_deref(),
                     name: &c.name,
+                    references: &c.references,
+                    tags: &c.tags,
                     source: RuleKind::Chainsaw,
+                    source_path: d.kind.path(),
                     status: &c.status,
                     timestamp: localised,
 
                     sigma: None,
                 },
+                Rule::Keyword(k) => Detection {
+                    authors: &k.authors,
+                    group: &hunt.group,
+                    kind: &d.kind,
+                    level: &k.level,
+                    matched: &hit.matched,
+                    explanation: hit.explanation.as_deref(),
+                    name: &k.name,
+                    references: &None,
+                    tags: &None,
+                    source: RuleKind::Keyword,
+                    source_path: d.kind.path(),
+                    status: &k.status,
+                    timestamp: localised,
+
+                    sigma: None,
+                },
                 Rule::Sigma(s) => {
                     let sigma = Sigma {
                         falsepositives: &s.falsepositives,
                         id: &s.id,
                         logsource: &s.logsource,
-                        references: &s.references,
-                        tags: &s.tags,
                     };
                     Detection {
                         authors: &s.authors,
                         group: &hunt.group,
                         kind: &d.kind,
                         level: &s.level,
+                        matched: &hit.matched,
+                        explanation: hit.explanation.as_deref(),
                         name: &s.name,
+                        references: &s.references,
+                        tags: &s.tags,
                         source: RuleKind::Sigma,
+                        source_path: d.kind.path(),
                         status: &s.status,
                         timestamp: localised,
 
@@ -1052,20 +1958,28 @@ pub fn print_jsonl(
                         size: *size,
                     };
 
-                    cs_print_json!(&Detection {
-                        authors: det.authors,
-                        group: det.group,
-                        kind: &kind,
-                        level: det.level,
-                        name: det.name,
-                        source: det.source,
-                        status: det.status,
-                        timestamp: det.timestamp,
-                        sigma: det.sigma,
-                    })?;
+                    print_jsonl_detection(
+                        &Detection {
+                            authors: det.authors,
+                            group: det.group,
+                            kind: &kind,
+                            level: det.level,
+                            matched: det.matched,
+                            explanation: det.explanation,
+                            name: det.name,
+                            references: det.references,
+                            tags: det.tags,
+                            source: det.source,
+                            source_path: det.source_path,
+                            status: det.status,
+                            timestamp: det.timestamp,
+                            sigma: det.sigma,
+                        },
+                        renames,
+                    )?;
                 }
                 _ => {
-                    cs_print_json!(&det)?;
+                    print_jsonl_detection(&det, renames)?;
                 }
             }
             cs_println!();
@@ -1080,28 +1994,53 @@ pub fn print_jsonl(
                     group: &hunt.group,
                     kind: &d.kind,
                     level: &c.level,
+                    matched: &hit.matched,
+                    explanation: hit.explanation.as_deref(),
                     name: &c.name,
+                    references: &c.references,
+                    tags: &c.tags,
                     source: RuleKind::Chainsaw,
+                    source_path: d.kind.path(),
                     status: &c.status,
                     timestamp: localised,
 
                     sigma: None,
                 },
+                Rule::Keyword(k) => Detection {
+                    authors: &k.authors,
+                    group: &hunt.group,
+                    kind: &d.kind,
+                    level: &k.level,
+                    matched: &hit.matched,
+                    explanation: hit.explanation.as_deref(),
+                    name: &k.name,
+                    references: &None,
+                    tags: &None,
+                    source: RuleKind::Keyword,
+                    source_path: d.kind.path(),
+                    status: &k.status,
+                    timestamp: localised,
+
+                    sigma: None,
+                },
                 Rule::Sigma(s) => {
                     let sigma = Sigma {
                         falsepositives: &s.falsepositives,
                         id: &s.id,
                         logsource: &s.logsource,
-                        references: &s.references,
-                        tags: &s.tags,
                     };
                     Detection {
                         authors: &s.authors,
                         group: &hunt.group,
                         kind: &d.kind,
                         level: &s.level,
+                        matched: &hit.matched,
+                        explanation: hit.explanation.as_deref(),
                         name: &s.name,
+                        references: &s.references,
+                        tags: &s.tags,
                         source: RuleKind::Sigma,
+                        source_path: d.kind.path(),
                         status: &s.status,
                         timestamp: localised,
 
@@ -1109,12 +2048,41 @@ pub fn print_jsonl(
                     }
                 }
             };
-            cs_print_json!(&det)?;
+            print_jsonl_detection(&det, renames)?;
             cs_println!();
         }
     }
     Ok(())
 }
+
+fn print_jsonl_detection(
+    detection: &Detection,
+    renames: &HashMap<String, String>,
+) -> crate::Result<()> {
+    if renames.is_empty() {
+        cs_print_json!(detection)?;
+    } else {
+        cs_print_json!(&rename_keys(detection, renames)?)?;
+    }
+    Ok(())
+}
+
+fn rename<'a>(name: &'a str, renames: &'a HashMap<String, String>) -> &'a str {
+    renames.get(name).map(|n| n.as_str()).unwrap_or(name)
+}
+
+/// Serialises the detection and renames its keys, each key is only looked up once so that renames
+/// are not chained (e.g. a=b and b=a swap the two).
+fn rename_keys(detection: &Detection, renames: &HashMap<String, String>) -> crate::Result<Json> {
+    match serde_json::to_value(detection)? {
+        Json::Object(map) => Ok(Json::Object(
+            map.into_iter()
+                .map(|(k, v)| (rename(&k, renames).to_owned(), v))
+                .collect(),
+        )),
+        value => Ok(value),
+    }
+}
 pub fn tau_to_json(tau: Tau) -> Json {
     match tau {
         Tau::Null => Json::Null,
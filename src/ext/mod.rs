@@ -1 +1,3 @@
+pub mod redact;
 pub mod tau;
+pub mod timezone;
@@ -0,0 +1,123 @@
+use serde_json::Value as Json;
+use sha2::{Digest, Sha256};
+
+/// Replaces the values of the fields, given as dot separated paths, with a stable keyed hash. This
+/// hides the real values while still allowing the records that share them to be correlated by
+/// anyone, but only those with the key can check a guess of what a value was.
+pub fn redact(json: &mut Json, fields: &[String], key: &[u8]) {
+    for field in fields {
+        match lookup(json, field) {
+            Some(Json::Array(values)) => {
+                for value in values {
+                    *value = Json::String(redact_value(value, key));
+                }
+            }
+            Some(value) => *value = Json::String(redact_value(value, key)),
+            None => {}
+        }
+    }
+}
+
+fn lookup<'a>(json: &'a mut Json, field: &str) -> Option<&'a mut Json> {
+    let mut value = json;
+    for key in field.split('.') {
+        value = value.as_object_mut()?.get_mut(key)?;
+    }
+    Some(value)
+}
+
+/// The redacted form of a value, strings are hashed as is so that they match `redact_str`.
+fn redact_value(value: &Json, key: &[u8]) -> String {
+    match value {
+        Json::String(s) => redact_str(s, key),
+        _ => redact_str(&value.to_string(), key),
+    }
+}
+
+/// A truncated HMAC-SHA256 of the value, which only needs to tell the values apart.
+///
+/// NOTE: The hash is keyed as the values redacted are usually from a small space, such as IP
+/// addresses or user names, so a plain hash of them could be reversed by hashing every candidate.
+pub fn redact_str(value: &str, key: &[u8]) -> String {
+    let digest = hmac_sha256(key, value.as_bytes());
+    let hex: String = digest
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("[REDACTED:{}]", hex)
+}
+
+/// A random key to redact with, for when one was not provided.
+pub fn generate_key() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        let mut json = serde_json::json!({
+            "Event": {
+                "EventData": {
+                    "IpAddress": "10.0.0.1",
+                    "TargetUserName": "alice"
+                },
+                "System": {
+                    "Computer": "host"
+                }
+            }
+        });
+        redact(
+            &mut json,
+            &[
+                "Event.EventData.TargetUserName".to_owned(),
+                "Event.EventData.IpAddress.Missing".to_owned(),
+                "Event.System".to_owned(),
+            ],
+            b"key",
+        );
+        assert_eq!(
+            json["Event"]["EventData"]["TargetUserName"],
+            redact_str("alice", b"key")
+        );
+        assert_eq!(json["Event"]["EventData"]["IpAddress"], "10.0.0.1");
+        assert!(json["Event"]["System"].is_string());
+        assert_ne!(redact_str("alice", b"key"), redact_str("bob", b"key"));
+        assert_ne!(redact_str("alice", b"key"), redact_str("alice", b"other"));
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231 test case 2
+        let digest = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(
+            hex,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
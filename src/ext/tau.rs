@@ -1,11 +1,14 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use aho_corasick::{AhoCorasickBuilder, AhoCorasickKind};
+use regex::RegexBuilder;
 use serde::de;
 use serde_yaml::Value as Yaml;
 use tau_engine::core::parser::{
-    parse_identifier, BoolSym, Expression, IdentifierParser, MatchType, ModSym, Pattern, Search,
+    parse_identifier, BoolSym, Expression, IdentifierParser, Match, MatchType, ModSym, Pattern,
+    Search,
 };
+use tau_engine::{Document, Value as Tau};
 
 pub fn deserialize_expression<'de, D>(deserializer: D) -> Result<Expression, D::Error>
 where
@@ -79,6 +82,315 @@ pub fn extract_fields(expression: &Expression) -> HashSet<String> {
     set
 }
 
+pub fn extract_identifiers(expression: &Expression) -> HashSet<String> {
+    let mut set = HashSet::new();
+    match expression {
+        Expression::BooleanGroup(_, expressions) => {
+            for expression in expressions {
+                set.extend(extract_identifiers(expression));
+            }
+        }
+        Expression::BooleanExpression(left, _, right) => {
+            set.extend(extract_identifiers(left));
+            set.extend(extract_identifiers(right));
+        }
+        Expression::Identifier(i) => {
+            set.insert(i.to_owned());
+        }
+        Expression::Match(_, e) | Expression::Negate(e) => {
+            set.extend(extract_identifiers(e));
+        }
+        Expression::Boolean(_)
+        | Expression::Cast(_, _)
+        | Expression::Field(_)
+        | Expression::Float(_)
+        | Expression::Integer(_)
+        | Expression::Matrix(_, _)
+        | Expression::Nested(_, _)
+        | Expression::Null
+        | Expression::Search(_, _, _) => {}
+    }
+    set
+}
+
+/// The integer values that one of the given fields must be equal to for the expression to be true,
+/// or `None` when it could be true whatever the value of the fields.
+///
+/// NOTE: This errs towards `None`, so negations and `or` branches that do not constrain the fields
+/// are treated as allowing any value.
+pub fn extract_equalities(
+    expression: &Expression,
+    identifiers: Option<&HashMap<String, Expression>>,
+    fields: &[&str],
+) -> Option<HashSet<i64>> {
+    let all = |expressions: &[&Expression]| -> Option<HashSet<i64>> {
+        expressions
+            .iter()
+            .filter_map(|e| extract_equalities(e, identifiers, fields))
+            .reduce(|a, b| a.intersection(&b).cloned().collect())
+    };
+    let any = |expressions: &[&Expression]| -> Option<HashSet<i64>> {
+        let mut set = HashSet::new();
+        for expression in expressions {
+            set.extend(extract_equalities(expression, identifiers, fields)?);
+        }
+        Some(set)
+    };
+    match expression {
+        Expression::BooleanGroup(BoolSym::And, expressions) => {
+            all(&expressions.iter().collect::<Vec<_>>())
+        }
+        Expression::BooleanGroup(BoolSym::Or, expressions) => {
+            any(&expressions.iter().collect::<Vec<_>>())
+        }
+        Expression::BooleanExpression(left, BoolSym::And, right) => all(&[left, right]),
+        Expression::BooleanExpression(left, BoolSym::Or, right) => any(&[left, right]),
+        Expression::BooleanExpression(left, BoolSym::Equal, right) => {
+            match (left.as_ref(), right.as_ref()) {
+                (Expression::Cast(f, _) | Expression::Field(f), Expression::Integer(i))
+                    if fields.contains(&f.as_str()) =>
+                {
+                    Some(HashSet::from([*i]))
+                }
+                _ => None,
+            }
+        }
+        Expression::Identifier(i) => identifiers
+            .and_then(|ids| ids.get(i))
+            .and_then(|e| extract_equalities(e, identifiers, fields)),
+        Expression::Match(m, e) => {
+            let expressions = match e.as_ref() {
+                Expression::BooleanGroup(_, expressions) => expressions.iter().collect(),
+                e => vec![e],
+            };
+            match m {
+                Match::All => all(&expressions),
+                Match::Of(_) => any(&expressions),
+            }
+        }
+        Expression::Search(Search::Exact(v), f, _) if fields.contains(&f.as_str()) => {
+            v.parse::<i64>().ok().map(|i| HashSet::from([i]))
+        }
+        Expression::Boolean(_)
+        | Expression::BooleanGroup(_, _)
+        | Expression::BooleanExpression(_, _, _)
+        | Expression::Cast(_, _)
+        | Expression::Field(_)
+        | Expression::Float(_)
+        | Expression::Integer(_)
+        | Expression::Matrix(_, _)
+        | Expression::Negate(_)
+        | Expression::Nested(_, _)
+        | Expression::Null
+        | Expression::Search(_, _, _) => None,
+    }
+}
+
+/// Whether a value satisfies the predicate, where arrays (including nested ones) are checked
+/// element by element and match when any of their elements do. This is how tau itself treats array
+/// fields, so anything resolving fields outside of tau should use this to stay consistent.
+pub fn any_element(value: Tau<'_>, predicate: &mut dyn FnMut(Tau<'_>) -> bool) -> bool {
+    match value {
+        Tau::Array(array) => array.iter().any(|v| any_element(v, predicate)),
+        value => predicate(value),
+    }
+}
+
+/// The field values that caused a matching expression to match, keyed by field.
+///
+/// NOTE: Tau only returns whether an expression as a whole matched, so we walk the tree and
+/// re-solve each leaf against the document, recording the fields of those that are true. Negated
+/// subtrees are skipped as they match on the absence of a value.
+pub fn matched_fields(
+    expression: &Expression,
+    identifiers: Option<&HashMap<String, Expression>>,
+    document: &dyn Document,
+) -> BTreeMap<String, String> {
+    let mut matched = BTreeMap::new();
+    collect_matches(expression, identifiers, document, &mut matched);
+    matched
+}
+
+fn record_match(field: &str, document: &dyn Document, matched: &mut BTreeMap<String, String>) {
+    if let Some(value) = document.find(field) {
+        let value = match value.to_string() {
+            Some(v) => v,
+            None => crate::cli::tau_to_json(value).to_string(),
+        };
+        matched.insert(field.to_owned(), value);
+    }
+}
+
+fn collect_matches(
+    expression: &Expression,
+    identifiers: Option<&HashMap<String, Expression>>,
+    document: &dyn Document,
+    matched: &mut BTreeMap<String, String>,
+) {
+    match expression {
+        Expression::BooleanGroup(_, expressions) => {
+            for expression in expressions {
+                collect_matches(expression, identifiers, document, matched);
+            }
+        }
+        Expression::BooleanExpression(left, BoolSym::And | BoolSym::Or, right) => {
+            collect_matches(left, identifiers, document, matched);
+            collect_matches(right, identifiers, document, matched);
+        }
+        Expression::BooleanExpression(left, _, right) => {
+            if tau_engine::core::solve(expression, document) {
+                for side in [left, right] {
+                    if let Expression::Cast(f, _) | Expression::Field(f) = side.as_ref() {
+                        record_match(f, document, matched);
+                    }
+                }
+            }
+        }
+        Expression::Identifier(i) => {
+            if let Some(expression) = identifiers.and_then(|ids| ids.get(i)) {
+                collect_matches(expression, identifiers, document, matched);
+            }
+        }
+        Expression::Match(_, e) => {
+            collect_matches(e, identifiers, document, matched);
+        }
+        Expression::Matrix(fields, _) => {
+            if tau_engine::core::solve(expression, document) {
+                for field in fields {
+                    record_match(field, document, matched);
+                }
+            }
+        }
+        Expression::Nested(field, _) | Expression::Search(_, field, _) => {
+            if tau_engine::core::solve(expression, document) {
+                record_match(field, document, matched);
+            }
+        }
+        Expression::Boolean(_)
+        | Expression::Cast(_, _)
+        | Expression::Field(_)
+        | Expression::Float(_)
+        | Expression::Integer(_)
+        | Expression::Negate(_)
+        | Expression::Null => {}
+    }
+}
+
+/// Renders the expression as an indented tree, with each node marked `[+]` or `[-]` by whether it
+/// is true for the document and each leaf followed by the document values it was solved against.
+///
+/// NOTE: Like `matched_fields` we have to re-solve the leaves ourselves, and the branches are then
+/// resolved from their children as identifiers cannot be solved outside of a detection.
+pub fn explain(
+    expression: &Expression,
+    identifiers: Option<&HashMap<String, Expression>>,
+    document: &dyn Document,
+) -> String {
+    explain_node(expression, identifiers, document, 0)
+        .1
+        .join("\n")
+}
+
+fn explain_node(
+    expression: &Expression,
+    identifiers: Option<&HashMap<String, Expression>>,
+    document: &dyn Document,
+    depth: usize,
+) -> (bool, Vec<String>) {
+    let explain_all = |expressions: &[&Expression]| -> Vec<(bool, Vec<String>)> {
+        expressions
+            .iter()
+            .map(|e| explain_node(e, identifiers, document, depth + 1))
+            .collect()
+    };
+    let (result, label, children) = match expression {
+        Expression::BooleanGroup(symbol, expressions)
+            if matches!(symbol, BoolSym::And | BoolSym::Or) =>
+        {
+            let children = explain_all(&expressions.iter().collect::<Vec<_>>());
+            let (result, label) = if matches!(symbol, BoolSym::And) {
+                (children.iter().all(|(r, _)| *r), "and")
+            } else {
+                (children.iter().any(|(r, _)| *r), "or")
+            };
+            (result, label.to_owned(), children)
+        }
+        Expression::BooleanExpression(left, symbol, right)
+            if matches!(symbol, BoolSym::And | BoolSym::Or) =>
+        {
+            let children = explain_all(&[left.as_ref(), right.as_ref()]);
+            let (result, label) = if matches!(symbol, BoolSym::And) {
+                (children.iter().all(|(r, _)| *r), "and")
+            } else {
+                (children.iter().any(|(r, _)| *r), "or")
+            };
+            (result, label.to_owned(), children)
+        }
+        Expression::Identifier(i) => match identifiers.and_then(|ids| ids.get(i)) {
+            Some(expression) => {
+                let child = explain_node(expression, identifiers, document, depth + 1);
+                (child.0, i.to_owned(), vec![child])
+            }
+            None => (false, format!("{} (unknown identifier)", i), vec![]),
+        },
+        Expression::Match(m, e) => {
+            let children = match e.as_ref() {
+                Expression::BooleanGroup(_, expressions) => {
+                    explain_all(&expressions.iter().collect::<Vec<_>>())
+                }
+                e => explain_all(&[e]),
+            };
+            let count = children.iter().filter(|(r, _)| *r).count();
+            match m {
+                Match::All => (count == children.len(), "all of".to_owned(), children),
+                Match::Of(n) => (count as u64 >= *n, format!("{} of", n), children),
+            }
+        }
+        Expression::Negate(e) => {
+            let child = explain_node(e, identifiers, document, depth + 1);
+            (!child.0, "not".to_owned(), vec![child])
+        }
+        Expression::Boolean(b) => (*b, b.to_string(), vec![]),
+        Expression::BooleanGroup(_, _)
+        | Expression::BooleanExpression(_, _, _)
+        | Expression::Matrix(_, _)
+        | Expression::Nested(_, _)
+        | Expression::Search(_, _, _) => {
+            let result = tau_engine::core::solve(expression, document);
+            let mut values = BTreeMap::new();
+            for field in extract_fields(expression) {
+                record_match(&field, document, &mut values);
+            }
+            let label = if values.is_empty() {
+                expression.to_string()
+            } else {
+                let values = values
+                    .into_iter()
+                    .map(|(f, v)| format!("{}: {}", f, v))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{} <- {}", expression, values)
+            };
+            (result, label, vec![])
+        }
+        Expression::Cast(_, _)
+        | Expression::Field(_)
+        | Expression::Float(_)
+        | Expression::Integer(_)
+        | Expression::Null => (false, expression.to_string(), vec![]),
+    };
+    let mut lines = vec![format!(
+        "{}[{}] {}",
+        "    ".repeat(depth),
+        if result { '+' } else { '-' },
+        label
+    )];
+    for (_, child) in children {
+        lines.extend(child);
+    }
+    (result, lines)
+}
+
 pub fn update_fields(expression: Expression, lookup: &HashMap<String, String>) -> Expression {
     match expression {
         Expression::BooleanGroup(x, expressions) => {
@@ -139,7 +451,7 @@ pub fn parse_field(key: &str) -> Expression {
     }
 }
 
-pub fn parse_kv(kv: &str) -> crate::Result<Expression> {
+pub fn parse_kv(kv: &str, ignore_case: bool) -> crate::Result<Expression> {
     let mut parts = kv.split(": ");
     let key = parts
         .next()
@@ -166,12 +478,24 @@ pub fn parse_kv(kv: &str) -> crate::Result<Expression> {
     // NOTE: This is pinched from tau-engine as it is not exposed, we then slightly tweak it to
     // handle casting in a slightly different way :O
     // FIXME: The tau-engine is not able to cast string expressions, I need to fix this upstream :/
-    let identifier = if let Some(v) = value.strip_prefix('!') {
+    let mut identifier = if let Some(v) = value.strip_prefix('!') {
         not = true;
         v.to_owned().into_identifier()?
     } else {
         value.to_owned().into_identifier()?
     };
+    // NOTE: Forcing case insensitivity only applies to string values, the field lookups remain
+    // case sensitive as they would be when using the `i` identifier prefix.
+    if ignore_case && !identifier.ignore_case {
+        identifier.ignore_case = true;
+        if let Pattern::Regex(r) = identifier.pattern {
+            identifier.pattern = Pattern::Regex(
+                RegexBuilder::new(r.as_str())
+                    .case_insensitive(true)
+                    .build()?,
+            );
+        }
+    }
     // Type enforcement
     match (&field, &identifier.pattern) {
         (Expression::Cast(_, ModSym::Str), Pattern::Equal(_))
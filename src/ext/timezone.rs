@@ -0,0 +1,78 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, FixedOffset, LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+
+/// A timezone that is either a named IANA zone or a fixed UTC offset (e.g. +05:30).
+#[derive(Clone, Copy, Debug)]
+pub enum Timezone {
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl Timezone {
+    /// The names of the timezones that can be parsed, in alphabetical order.
+    pub fn names() -> Vec<&'static str> {
+        let mut names = chrono_tz::TZ_VARIANTS
+            .iter()
+            .map(|tz| tz.name())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<DateTime<FixedOffset>> {
+        match self {
+            Self::Fixed(offset) => offset.from_local_datetime(local),
+            Self::Named(tz) => tz
+                .from_local_datetime(local)
+                .map(|d| d.with_timezone(&d.offset().fix())),
+        }
+    }
+}
+
+impl fmt::Display for Timezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fixed(offset) => write!(f, "{}", offset),
+            Self::Named(tz) => write!(f, "{}", tz),
+        }
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(tz) = s.parse::<Tz>() {
+            return Ok(Self::Named(tz));
+        }
+        let (sign, offset) = match s.chars().next() {
+            Some('+') => (1, &s[1..]),
+            Some('-') => (-1, &s[1..]),
+            _ => anyhow::bail!(
+                "unknown timezone, must be a named timezone (see `chainsaw tz`) or an offset (e.g. +05:30)"
+            ),
+        };
+        let (hours, minutes) = match offset.split_once(':') {
+            Some((h, m)) => (h, m),
+            None if offset.len() == 4 && offset.is_ascii() => offset.split_at(2),
+            None => (offset, "0"),
+        };
+        let seconds = match (hours.parse::<i32>(), minutes.parse::<i32>()) {
+            (Ok(h), Ok(m)) if h < 24 && m < 60 => (h * 3600 + m * 60) * sign,
+            _ => anyhow::bail!(
+                "invalid timezone offset '{}', must be in the form +HH:MM",
+                s
+            ),
+        };
+        match FixedOffset::east_opt(seconds) {
+            Some(offset) => Ok(Self::Fixed(offset)),
+            None => anyhow::bail!(
+                "invalid timezone offset '{}', must be in the form +HH:MM",
+                s
+            ),
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use tempfile::{Builder, NamedTempFile};
+use zip::ZipArchive;
+
+use super::image;
+
+/// Whether the path is a container of artefacts, either a zip archive or a raw disk image.
+pub fn is_archive(path: &Path) -> bool {
+    is_zip(path) || image::is_image(path)
+}
+
+fn is_zip(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.eq_ignore_ascii_case("zip"))
+        .unwrap_or(false)
+}
+
+/// Splits a path that points within an archive into the archive and the name of the entry.
+pub fn split(path: &Path) -> Option<(&Path, String)> {
+    if path.exists() {
+        return None;
+    }
+    let archive = path
+        .ancestors()
+        .skip(1)
+        .find(|a| is_archive(a) && a.is_file())?;
+    // NOTE: Entry names always use forward slashes, regardless of the platform.
+    let name = path
+        .strip_prefix(archive)
+        .ok()?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Some((archive, name))
+}
+
+/// The files within an archive, as paths beneath the archive itself.
+pub fn entries(
+    archive: &Path,
+    extensions: &Option<HashSet<String>>,
+) -> crate::Result<Vec<PathBuf>> {
+    if !is_zip(archive) {
+        return image::entries(archive, extensions);
+    }
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+    let mut files = vec![];
+    for i in 0..zip.len() {
+        let entry = zip.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        // NOTE: Entries that would escape the archive, such as '../evil.evtx', are skipped.
+        let name = match entry.enclosed_name() {
+            Some(name) => name.to_path_buf(),
+            None => continue,
+        };
+        if let Some(e) = extensions {
+            match name.extension() {
+                Some(ext) if e.contains(&ext.to_string_lossy().into_owned()) => {}
+                _ => continue,
+            }
+        }
+        files.push(archive.join(name));
+    }
+    Ok(files)
+}
+
+/// The size of an entry once it has been decompressed.
+pub fn size(archive: &Path, name: &str) -> crate::Result<u64> {
+    if !is_zip(archive) {
+        return image::size(archive, name);
+    }
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+    let entry = zip.by_name(name)?;
+    Ok(entry.size())
+}
+
+/// Decompresses an entry into a temporary file, keeping its extension so that it is loaded by the
+/// correct parser. The file is removed when it is dropped.
+pub fn extract(archive: &Path, name: &str) -> crate::Result<NamedTempFile> {
+    if !is_zip(archive) {
+        return image::extract(archive, name);
+    }
+    let mut zip = ZipArchive::new(File::open(archive)?)?;
+    let mut entry = zip.by_name(name)?;
+    let suffix = Path::new(name)
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut file = Builder::new().suffix(&suffix).tempfile()?;
+    io::copy(&mut entry, &mut file)?;
+    Ok(file)
+}
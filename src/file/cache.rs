@@ -0,0 +1,269 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use evtx::SerializedEvtxRecord;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+
+use super::{archive, Document, Kind, Parser as FileParser, Reader};
+
+/// Bumped whenever the parsers change what they produce, so that stale caches are not reused.
+const VERSION: u32 = 1;
+
+#[derive(Deserialize, Serialize)]
+struct Header {
+    version: u32,
+    kind: Kind,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Entry<T> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    record: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    timestamp: Option<String>,
+    data: T,
+}
+
+/// A directory of parsed documents, keyed by the SHA256 of the artefact they were parsed from, so
+/// that hunting and searching the same collection only parses each artefact once.
+pub struct Cache {
+    directory: PathBuf,
+}
+
+impl Cache {
+    pub fn new(directory: PathBuf) -> crate::Result<Self> {
+        fs::create_dir_all(&directory).map_err(|e| {
+            anyhow!(
+                "Unable to create parsed cache directory - {} - {}",
+                directory.display(),
+                e
+            )
+        })?;
+        Ok(Self { directory })
+    }
+
+    /// Loads the artefact from the cache when it has been parsed before, otherwise it is parsed as
+    /// usual and the documents are written to the cache as they are read.
+    pub fn load(
+        &self,
+        file: &Path,
+        load_unknown: bool,
+        skip_errors: bool,
+    ) -> crate::Result<Reader> {
+        if let Some((archive, name)) = archive::split(file) {
+            let extracted = match archive::extract(archive, &name) {
+                Ok(extracted) => extracted,
+                Err(_) => return Reader::load(file, load_unknown, skip_errors),
+            };
+            let mut reader = self.load(extracted.path(), load_unknown, skip_errors)?;
+            reader.extracted = Some(extracted);
+            return Ok(reader);
+        }
+        let key = match digest(file) {
+            Ok(key) => key,
+            Err(_) => return Reader::load(file, load_unknown, skip_errors),
+        };
+        let path = self.directory.join(format!("{}.jsonl", key));
+        if path.exists() {
+            match Parser::load(&path) {
+                Ok(parser) => {
+                    return Ok(Reader {
+                        parser: FileParser::Cached(parser),
+                        extracted: None,
+                    })
+                }
+                Err(e) => {
+                    cs_eyellowln!(
+                        "[!] ignoring parsed cache for '{}' - {}\n",
+                        file.display(),
+                        e
+                    );
+                }
+            }
+        }
+        let mut reader = Reader::load(file, load_unknown, skip_errors)?;
+        let kind = reader.kind();
+        if kind == Kind::Unknown {
+            return Ok(reader);
+        }
+        // NOTE: Failing to populate the cache should never stop the artefact from being read.
+        match Writer::create(&self.directory, path, kind) {
+            Ok(writer) => {
+                let parser = std::mem::replace(&mut reader.parser, FileParser::Unknown);
+                reader.parser = FileParser::Caching(Box::new(parser), writer);
+            }
+            Err(e) => {
+                cs_eyellowln!(
+                    "[!] failed to create parsed cache for '{}' - {}\n",
+                    file.display(),
+                    e
+                );
+            }
+        }
+        Ok(reader)
+    }
+}
+
+fn digest(file: &Path) -> crate::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(file)?, &mut hasher)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+pub struct Parser {
+    pub kind: Kind,
+    lines: Lines<BufReader<File>>,
+}
+
+impl Parser {
+    fn load(path: &Path) -> crate::Result<Self> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header: Header = match lines.next() {
+            Some(line) => serde_json::from_str(&line?)?,
+            None => anyhow::bail!("missing header"),
+        };
+        if header.version != VERSION {
+            anyhow::bail!("unsupported version {}", header.version);
+        }
+        if header.kind == Kind::Unknown {
+            anyhow::bail!("unknown kind");
+        }
+        Ok(Self {
+            kind: header.kind,
+            lines,
+        })
+    }
+
+    pub fn parse(&mut self) -> impl Iterator<Item = crate::Result<Document>> + '_ {
+        let kind = self.kind.clone();
+        self.lines
+            .by_ref()
+            .map(move |line| -> crate::Result<Document> {
+                let entry: Entry<Json> = serde_json::from_str(&line?)?;
+                let document = match kind {
+                    Kind::Evtx => {
+                        let (record, timestamp) = match (entry.record, entry.timestamp) {
+                            (Some(record), Some(timestamp)) => (record, timestamp),
+                            _ => anyhow::bail!("cached event log record is missing its header"),
+                        };
+                        Document::Evtx(SerializedEvtxRecord {
+                            event_record_id: record,
+                            timestamp: DateTime::parse_from_rfc3339(&timestamp)?
+                                .with_timezone(&Utc),
+                            data: entry.data,
+                        })
+                    }
+                    Kind::Hve => Document::Hve(entry.data),
+                    Kind::Json | Kind::Jsonl => Document::Json(entry.data),
+                    Kind::Mft => Document::Mft(entry.data),
+                    Kind::Xml => Document::Xml(entry.data),
+                    Kind::Unknown => unreachable!(),
+                };
+                Ok(document)
+            })
+    }
+}
+
+/// Writes the documents of an artefact to a temporary file in the cache, which only takes the
+/// place of the cache entry once every document has been read without error.
+pub struct Writer {
+    file: Option<BufWriter<NamedTempFile>>,
+    path: PathBuf,
+}
+
+impl Writer {
+    fn create(directory: &Path, path: PathBuf, kind: Kind) -> crate::Result<Self> {
+        let mut file = BufWriter::new(NamedTempFile::new_in(directory)?);
+        serde_json::to_writer(
+            &mut file,
+            &Header {
+                version: VERSION,
+                kind,
+            },
+        )?;
+        file.write_all(b"\n")?;
+        Ok(Self {
+            file: Some(file),
+            path,
+        })
+    }
+
+    fn write(&mut self, document: &Document) -> crate::Result<()> {
+        let file = match self.file.as_mut() {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        let entry = match document {
+            Document::Evtx(evtx) => Entry {
+                record: Some(evtx.event_record_id),
+                timestamp: Some(evtx.timestamp.to_rfc3339()),
+                data: &evtx.data,
+            },
+            Document::Hve(json)
+            | Document::Json(json)
+            | Document::Mft(json)
+            | Document::Xml(json) => Entry {
+                record: None,
+                timestamp: None,
+                data: json,
+            },
+        };
+        serde_json::to_writer(&mut *file, &entry)?;
+        file.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> crate::Result<()> {
+        if let Some(file) = self.file.take() {
+            let file = file.into_inner().map_err(|e| e.into_error())?;
+            file.persist(&self.path)?;
+        }
+        Ok(())
+    }
+
+    /// Passes the documents through, writing each one to the cache as it is read.
+    pub fn tee<'a>(
+        &'a mut self,
+        documents: Box<dyn Iterator<Item = crate::Result<Document>> + Send + Sync + 'a>,
+    ) -> Tee<'a> {
+        Tee {
+            documents,
+            writer: self,
+        }
+    }
+}
+
+pub struct Tee<'a> {
+    documents: Box<dyn Iterator<Item = crate::Result<Document>> + Send + Sync + 'a>,
+    writer: &'a mut Writer,
+}
+
+impl<'a> Iterator for Tee<'a> {
+    type Item = crate::Result<Document>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let document = self.documents.next();
+        let result = match &document {
+            Some(Ok(document)) => self.writer.write(document),
+            Some(Err(_)) => {
+                // NOTE: A partial cache would silently drop documents from later runs.
+                self.writer.file = None;
+                Ok(())
+            }
+            None => self.writer.finish(),
+        };
+        if result.is_err() {
+            self.writer.file = None;
+        }
+        document
+    }
+}
@@ -31,6 +31,32 @@ impl Parser {
     }
 }
 
+/// Event data can repeat a `Data` name, which the parser stores under suffixed keys (e.g. `Name`,
+/// `Name_1`, `Name_2`), so we collect these back into an array under the original name.
+///
+/// NOTE: This is opt-in as it changes the shape of every record that repeats a name, which existing
+/// rules matching on the suffixed keys rely on.
+pub fn merge_repeated_data(data: &mut Json) {
+    let event_data = match data.pointer_mut("/Event/EventData") {
+        Some(Json::Object(event_data)) => event_data,
+        _ => return,
+    };
+    let repeated = event_data
+        .keys()
+        .filter(|k| event_data.contains_key(&format!("{}_1", k)))
+        .cloned()
+        .collect::<Vec<_>>();
+    for name in repeated {
+        let mut values = vec![event_data.remove(&name).expect("could not get data")];
+        let mut i = 1;
+        while let Some(value) = event_data.remove(&format!("{}_{}", name, i)) {
+            values.push(value);
+            i += 1;
+        }
+        event_data.insert(name, Json::Array(values));
+    }
+}
+
 pub struct Wrapper<'a>(pub &'a Value);
 impl<'a> Document for Wrapper<'a> {
     fn find(&self, key: &str) -> Option<Tau<'_>> {
@@ -67,4 +93,35 @@ impl Searchable for SerializedEvtxRecord<Json> {
     fn matches(&self, regex: &RegexSet) -> bool {
         regex.is_match(&self.data.to_string())
     }
+
+    fn patterns(&self, regex: &RegexSet) -> Vec<usize> {
+        regex.matches(&self.data.to_string()).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_repeated_data() {
+        let mut data = serde_json::json!({
+            "Event": {
+                "EventData": {
+                    "Group": "Administrators",
+                    "Group_1": "Remote Desktop Users",
+                    "Group_2": "Backup Operators",
+                    "TargetUserName": "alice"
+                }
+            }
+        });
+        merge_repeated_data(&mut data);
+        assert_eq!(
+            data["Event"]["EventData"],
+            serde_json::json!({
+                "Group": ["Administrators", "Remote Desktop Users", "Backup Operators"],
+                "TargetUserName": "alice"
+            })
+        );
+    }
 }
@@ -15,6 +15,17 @@ pub struct FileEntry {
     pub sha1_hash: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct DriverEntry {
+    pub driver_id: Option<String>,
+    pub driver_last_write_ts: Option<DateTime<Utc>>,
+    pub key_last_modified_ts: DateTime<Utc>,
+    pub company: Option<String>,
+    pub path: String,
+    pub product: Option<String>,
+    pub sha1_hash: Option<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct ProgramEntry {
     pub install_date: Option<DateTime<Utc>>,
@@ -30,6 +41,7 @@ pub struct ProgramEntry {
 #[derive(Debug)]
 pub struct AmcacheArtifact {
     pub is_new_format: bool,
+    pub driver_entries: Vec<DriverEntry>,
     pub file_entries: Vec<FileEntry>,
     pub program_entries: Vec<ProgramEntry>,
 }
@@ -54,6 +66,7 @@ impl super::Parser {
             })
         }
 
+        let mut driver_entries: Vec<DriverEntry> = Vec::new();
         let mut program_entries: Vec<ProgramEntry> = Vec::new();
         let mut file_entries: Vec<FileEntry> = Vec::new();
 
@@ -155,6 +168,47 @@ impl super::Parser {
                 };
                 file_entries.push(file_entry);
             }
+
+            // Get and parse data from InventoryDriverBinary, which is not always present
+            if let Some(mut key_inventory_driver_binary) =
+                self.inner.get_key(r"Root\InventoryDriverBinary", false)?
+            {
+                let subkeys = key_inventory_driver_binary.read_sub_keys(&mut self.inner);
+                for key in subkeys {
+                    // The key name is the lowercase path of the driver
+                    let path = key.key_name.clone();
+                    let driver_id = string_value_from_key(&key, "DriverId")?;
+                    let driver_last_write_ts =
+                        match string_value_from_key(&key, "DriverLastWriteTime")? {
+                            Some(ts) if !ts.is_empty() => Some(win_reg_str_ts_to_date_time(&ts)?),
+                            _ => None,
+                        };
+                    let company = string_value_from_key(&key, "DriverCompany")?;
+                    let product = string_value_from_key(&key, "Product")?;
+
+                    // DriverId is the SHA-1 hash of the driver with "0000" prepended. Discard prefix
+                    let sha1_hash = driver_id.as_ref().and_then(|id| {
+                        if id.len() == 44 && &id[..4] == "0000" {
+                            Some(String::from(&id[4..]))
+                        } else {
+                            // In case unexpected value
+                            None
+                        }
+                    });
+
+                    let key_last_modified_ts = key.last_key_written_date_and_time();
+                    let driver_entry = DriverEntry {
+                        driver_id,
+                        driver_last_write_ts,
+                        key_last_modified_ts,
+                        company,
+                        path,
+                        product,
+                        sha1_hash,
+                    };
+                    driver_entries.push(driver_entry);
+                }
+            }
         // Older amcache format
         } else {
             /// A helper function for extracting unix timestamps from key values
@@ -280,6 +334,7 @@ impl super::Parser {
         }
 
         Ok(AmcacheArtifact {
+            driver_entries,
             file_entries,
             program_entries,
             is_new_format,
@@ -1,5 +1,6 @@
 use std::{
-    fs,
+    fs::{self, File},
+    io::Read,
     path::{Path, PathBuf},
 };
 
@@ -12,6 +13,7 @@ use serde_json::Value as Json;
 
 pub mod amcache;
 pub mod shimcache;
+pub mod userassist;
 
 pub type Hve = Json;
 
@@ -21,8 +23,15 @@ pub struct Parser {
 
 impl Parser {
     pub fn load(path: &Path) -> crate::Result<Self> {
+        Self::load_with_transaction_logs(path, &[])
+    }
+
+    pub fn load_with_transaction_logs(
+        path: &Path,
+        transaction_logs: &[PathBuf],
+    ) -> crate::Result<Self> {
         // Find registry transaction logs from the same directory
-        let mut transaction_log_files: Vec<PathBuf> = Vec::new();
+        let mut transaction_log_files: Vec<PathBuf> = transaction_logs.to_vec();
         let parent_dir = path
             .parent()
             .ok_or(anyhow!("Could not get registry hive parent directory!"))?;
@@ -34,13 +43,22 @@ impl Parser {
             if path.file_stem() == hive_file_name {
                 let file_extension = path.extension();
                 if let Some(extension) = file_extension {
-                    if extension == "LOG" || extension == "LOG1" || extension == "LOG2" {
+                    if (extension == "LOG" || extension == "LOG1" || extension == "LOG2")
+                        && !transaction_log_files.contains(&path)
+                    {
                         transaction_log_files.push(path);
                     }
                 }
             }
         }
 
+        if transaction_log_files.is_empty() && is_dirty(path)? {
+            cs_eyellowln!(
+                "[!] Registry hive {:?} is dirty but no transaction logs were found, some data may be missing",
+                path
+            );
+        }
+
         // Build parser
         let mut parser_builder = ParserBuilder::from_path(PathBuf::from(path));
         parser_builder.recover_deleted(true);
@@ -74,7 +92,20 @@ impl Parser {
     }
 }
 
-fn win32_ts_to_datetime(ts_win32: u64) -> crate::Result<NaiveDateTime> {
+fn is_dirty(path: &Path) -> crate::Result<bool> {
+    // NOTE: A hive is dirty when the primary and secondary sequence numbers in its base block do
+    // not match, meaning that there is data in the transaction logs yet to be written to it.
+    let mut header = [0u8; 12];
+    File::open(path)?.read_exact(&mut header)?;
+    if &header[0..4] != b"regf" {
+        return Ok(false);
+    }
+    let primary = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+    let secondary = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+    Ok(primary != secondary)
+}
+
+pub(crate) fn win32_ts_to_datetime(ts_win32: u64) -> crate::Result<NaiveDateTime> {
     let ts_unix = (ts_win32 / 10_000) as i64 - 11644473600000;
     NaiveDateTime::from_timestamp_millis(ts_unix).ok_or(anyhow!("Timestamp out of range!"))
 }
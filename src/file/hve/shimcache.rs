@@ -1,4 +1,5 @@
 use std::fmt::Display;
+use std::str::FromStr;
 
 use chrono::{DateTime, Utc};
 use serde::Serialize;
@@ -108,11 +109,35 @@ impl std::fmt::Display for ShimcacheVersion {
     }
 }
 
+/// A shimcache layout to parse with instead of the detected one, for damaged or carved hives
+#[derive(Clone, Copy, Debug)]
+pub enum AssumedVersion {
+    Windows7,
+    Windows8,
+    Windows10,
+}
+
+impl FromStr for AssumedVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let v = match s {
+            "win7" => Self::Windows7,
+            "win8" => Self::Windows8,
+            "win10" => Self::Windows10,
+            _ => anyhow::bail!("unknown shimcache version, must be: win7, win8 or win10"),
+        };
+        Ok(v)
+    }
+}
+
 #[derive(Debug)]
 pub struct ShimcacheArtifact {
     pub entries: Vec<ShimcacheEntry>,
     pub last_update_ts: DateTime<Utc>,
     pub version: ShimcacheVersion,
+    /// Whether the version was assumed rather than detected
+    pub assumed: bool,
     pub controlset: u32,
 }
 
@@ -134,7 +159,10 @@ impl Display for ShimcacheEntry {
 }
 
 impl super::Parser {
-    pub fn parse_shimcache(&mut self) -> crate::Result<ShimcacheArtifact> {
+    pub fn parse_shimcache(
+        &mut self,
+        assume_version: Option<AssumedVersion>,
+    ) -> crate::Result<ShimcacheArtifact> {
         // Find current ControlSet
         let current_controlset_key = self
             .inner
@@ -178,70 +206,9 @@ impl super::Parser {
         };
 
         // Find shimcache version
-        let e = || anyhow!("Shimcache byte indexing error!");
-        let signature_number =
-            u32::from_le_bytes(shimcache_bytes.get(0..4).ok_or_else(e)?.try_into()?);
-
-        let shimcache_version: ShimcacheVersion = match signature_number {
-            // Windows XP shimcache
-            0xdeadbeef => ShimcacheVersion::WindowsXP,
-            // Windows Vista shimcache
-            0xbadc0ffe => ShimcacheVersion::WindowsVistaWin2k3Win2k8,
-            // Windows 7 shimcache
-            0xbadc0fee => {
-                // Check if system is 32-bit
-                let environment_key_path =
-                    format!("{controlset_name}\\Control\\Session Manager\\Environment");
-                let environment_key =
-                    self.inner
-                        .get_key(&environment_key_path, false)?
-                        .ok_or(anyhow!(
-                            "Key \"{environment_key_path}\" not found in shimcache!"
-                        ))?;
-                let processor_architecture_value = environment_key.get_value("PROCESSOR_ARCHITECTURE")
-                .ok_or(anyhow!("Value \"PROCESSOR_ARCHITECTURE\" not found under key \"{environment_key_path}\" in shimcache!"))?.get_content().0;
-                let is_32bit = match processor_architecture_value {
-                    notatin::cell_value::CellValue::String(s) => s == "x86",
-                    _ => bail!("Value \"PROCESSOR_ARCHITECTURE\" under key \"{environment_key_path}\" was not of type String in shimcache!")
-                };
-
-                // Windows 7 32-bit
-                if is_32bit {
-                    ShimcacheVersion::Windows7x86
-                }
-                // Windows 7 64-bit
-                else {
-                    ShimcacheVersion::Windows7x64Windows2008R2
-                }
-            }
-            _ => {
-                let win8_cache_signature =
-                    std::str::from_utf8(shimcache_bytes.get(128..132).ok_or_else(e)?);
-
-                match win8_cache_signature {
-                    // Windows 8 shimcache
-                    Ok("00ts") => ShimcacheVersion::Windows80Windows2012,
-                    // Windows 8.1 shimcache
-                    Ok("10ts") => ShimcacheVersion::Windows81Windows2012R2,
-                    // Windows 10 shimcache
-                    _ => {
-                        let offset_to_records = signature_number as usize;
-                        let win10_cache_signature = std::str::from_utf8(
-                            shimcache_bytes
-                                .get(offset_to_records..offset_to_records + 4)
-                                .ok_or_else(e)?,
-                        );
-                        match win10_cache_signature {
-                            Ok("10ts") => match offset_to_records {
-                                0x34 => ShimcacheVersion::Windows10Creators,
-                                _ => ShimcacheVersion::Windows10,
-                            },
-                            _ => ShimcacheVersion::Unknown,
-                        }
-                    }
-                }
-            }
-        };
+        let shimcache_version = detect_version(&shimcache_bytes, assume_version, || {
+            self.is_32bit(&controlset_name)
+        })?;
 
         // Parse shimcache entries
         let shimcache_entries = match shimcache_version {
@@ -275,9 +242,97 @@ impl super::Parser {
             entries: shimcache_entries,
             last_update_ts: shimcache_last_update_ts,
             version: shimcache_version,
+            assumed: assume_version.is_some(),
             controlset,
         })
     }
+
+    /// Whether the system is 32-bit, read from the processor architecture of the control set
+    fn is_32bit(&mut self, controlset_name: &str) -> crate::Result<bool> {
+        let environment_key_path =
+            format!("{controlset_name}\\Control\\Session Manager\\Environment");
+        let environment_key = self
+            .inner
+            .get_key(&environment_key_path, false)?
+            .ok_or(anyhow!(
+                "Key \"{environment_key_path}\" not found in shimcache!"
+            ))?;
+        let processor_architecture_value = environment_key.get_value("PROCESSOR_ARCHITECTURE")
+            .ok_or(anyhow!("Value \"PROCESSOR_ARCHITECTURE\" not found under key \"{environment_key_path}\" in shimcache!"))?.get_content().0;
+        match processor_architecture_value {
+            notatin::cell_value::CellValue::String(s) => Ok(s == "x86"),
+            _ => bail!("Value \"PROCESSOR_ARCHITECTURE\" under key \"{environment_key_path}\" was not of type String in shimcache!")
+        }
+    }
+}
+
+/// Picks the layout to parse the shimcache with, which is the assumed one when given, otherwise it
+/// is detected from the signature
+fn detect_version(
+    bytes: &[u8],
+    assume_version: Option<AssumedVersion>,
+    mut is_32bit: impl FnMut() -> crate::Result<bool>,
+) -> crate::Result<ShimcacheVersion> {
+    let e = || anyhow!("Shimcache byte indexing error!");
+    let signature_number = u32::from_le_bytes(bytes.get(0..4).ok_or_else(e)?.try_into()?);
+
+    let version = match (assume_version, signature_number) {
+        (Some(AssumedVersion::Windows7), _) => {
+            // NOTE: Damaged hives may be missing the environment key, so we fall back to the
+            // far more common 64-bit layout.
+            if is_32bit().unwrap_or(false) {
+                ShimcacheVersion::Windows7x86
+            } else {
+                ShimcacheVersion::Windows7x64Windows2008R2
+            }
+        }
+        (Some(AssumedVersion::Windows8), _) => ShimcacheVersion::Windows80Windows2012,
+        (Some(AssumedVersion::Windows10), _) => ShimcacheVersion::Windows10,
+        // Windows XP shimcache
+        (None, 0xdeadbeef) => ShimcacheVersion::WindowsXP,
+        // Windows Vista shimcache
+        (None, 0xbadc0ffe) => ShimcacheVersion::WindowsVistaWin2k3Win2k8,
+        // Windows 7 shimcache
+        (None, 0xbadc0fee) => {
+            let is_32bit = is_32bit()?;
+
+            // Windows 7 32-bit
+            if is_32bit {
+                ShimcacheVersion::Windows7x86
+            }
+            // Windows 7 64-bit
+            else {
+                ShimcacheVersion::Windows7x64Windows2008R2
+            }
+        }
+        (None, _) => {
+            let win8_cache_signature = std::str::from_utf8(bytes.get(128..132).ok_or_else(e)?);
+
+            match win8_cache_signature {
+                // Windows 8 shimcache
+                Ok("00ts") => ShimcacheVersion::Windows80Windows2012,
+                // Windows 8.1 shimcache
+                Ok("10ts") => ShimcacheVersion::Windows81Windows2012R2,
+                // Windows 10 shimcache
+                _ => {
+                    let offset_to_records = signature_number as usize;
+                    let win10_cache_signature = std::str::from_utf8(
+                        bytes
+                            .get(offset_to_records..offset_to_records + 4)
+                            .ok_or_else(e)?,
+                    );
+                    match win10_cache_signature {
+                        Ok("10ts") => match offset_to_records {
+                            0x34 => ShimcacheVersion::Windows10Creators,
+                            _ => ShimcacheVersion::Windows10,
+                        },
+                        _ => ShimcacheVersion::Unknown,
+                    }
+                }
+            }
+        }
+    };
+    Ok(version)
 }
 
 /// Converts a slice of bytes representing UTF-16 into a String
@@ -846,3 +901,35 @@ mod windows_xp_cache {
         bail!("Windows XP shimcache parsing not supported!");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_version_assumed() {
+        // A Windows XP signature, which is ignored when the layout is assumed
+        let bytes = 0xdeadbeefu32.to_le_bytes();
+        assert!(matches!(
+            detect_version(&bytes, None, || Ok(false)).unwrap(),
+            ShimcacheVersion::WindowsXP
+        ));
+        assert!(matches!(
+            detect_version(&bytes, Some(AssumedVersion::Windows8), || Ok(false)).unwrap(),
+            ShimcacheVersion::Windows80Windows2012
+        ));
+        assert!(matches!(
+            detect_version(&bytes, Some(AssumedVersion::Windows10), || Ok(false)).unwrap(),
+            ShimcacheVersion::Windows10
+        ));
+        assert!(matches!(
+            detect_version(&bytes, Some(AssumedVersion::Windows7), || Ok(true)).unwrap(),
+            ShimcacheVersion::Windows7x86
+        ));
+        // A damaged hive without the environment key falls back to the 64-bit layout
+        assert!(matches!(
+            detect_version(&bytes, Some(AssumedVersion::Windows7), || bail!("missing")).unwrap(),
+            ShimcacheVersion::Windows7x64Windows2008R2
+        ));
+    }
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use super::win32_ts_to_datetime;
+
+const USERASSIST_KEY_PATH: &str = r"Software\Microsoft\Windows\CurrentVersion\Explorer\UserAssist";
+
+#[derive(Debug, Serialize)]
+pub struct UserAssistEntry {
+    pub focus_count: Option<u32>,
+    pub focus_time_ms: Option<u32>,
+    pub guid: String,
+    pub key_last_modified_ts: DateTime<Utc>,
+    pub last_executed_ts: Option<DateTime<Utc>>,
+    pub path: String,
+    pub run_count: u32,
+}
+
+/// The value names under the UserAssist keys are obfuscated with ROT13
+fn rot13(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            'a'..='m' | 'A'..='M' => (c as u8 + 13) as char,
+            'n'..='z' | 'N'..='Z' => (c as u8 - 13) as char,
+            _ => c,
+        })
+        .collect()
+}
+
+impl super::Parser {
+    pub fn parse_userassist(&mut self) -> crate::Result<Vec<UserAssistEntry>> {
+        let mut entries: Vec<UserAssistEntry> = Vec::new();
+
+        let mut userassist_key = self
+            .inner
+            .get_key(USERASSIST_KEY_PATH, false)?
+            .ok_or(anyhow!("Could not find UserAssist key!"))?;
+        let guid_keys = userassist_key.read_sub_keys(&mut self.inner);
+        for mut guid_key in guid_keys {
+            let guid = guid_key.key_name.clone();
+            let count_keys = guid_key.read_sub_keys(&mut self.inner);
+            for count_key in count_keys {
+                if !count_key.key_name.eq_ignore_ascii_case("Count") {
+                    continue;
+                }
+                let key_last_modified_ts = count_key.last_key_written_date_and_time();
+                for value in count_key.value_iter() {
+                    let path = rot13(&value.value_name);
+                    // The session and counter values are bookkeeping rather than executions
+                    if path.starts_with("UEME_CTL") {
+                        continue;
+                    }
+                    let bytes = match value.get_content().0 {
+                        notatin::cell_value::CellValue::Binary(bytes) => bytes,
+                        _ => continue,
+                    };
+                    let e = || anyhow!("UserAssist byte indexing error for value {}!", path);
+                    let u32_at = |offset: usize| -> crate::Result<u32> {
+                        Ok(u32::from_le_bytes(
+                            bytes.get(offset..offset + 4).ok_or_else(e)?.try_into()?,
+                        ))
+                    };
+                    let u64_at = |offset: usize| -> crate::Result<u64> {
+                        Ok(u64::from_le_bytes(
+                            bytes.get(offset..offset + 8).ok_or_else(e)?.try_into()?,
+                        ))
+                    };
+                    let (run_count, focus_count, focus_time_ms, last_executed) = match bytes.len() {
+                        // Windows 7 and later
+                        72 => (u32_at(4)?, Some(u32_at(8)?), Some(u32_at(12)?), u64_at(60)?),
+                        // Windows XP and Vista, where the run count starts at 5
+                        16 => (u32_at(4)?.saturating_sub(5), None, None, u64_at(8)?),
+                        _ => continue,
+                    };
+                    let last_executed_ts = if last_executed != 0 {
+                        let naive = win32_ts_to_datetime(last_executed)?;
+                        Some(DateTime::<Utc>::from_utc(naive, Utc))
+                    } else {
+                        None
+                    };
+                    entries.push(UserAssistEntry {
+                        focus_count,
+                        focus_time_ms,
+                        guid: guid.clone(),
+                        key_last_modified_ts,
+                        last_executed_ts,
+                        path,
+                        run_count,
+                    });
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+}
@@ -0,0 +1,750 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use tempfile::{Builder, NamedTempFile};
+
+/// The artefacts that are pulled out of the volumes, as directories relative to the root of the
+/// volume and the extension of the files within them.
+const ARTEFACTS: &[(&str, &str)] = &[("windows/system32/winevt/logs", "evtx")];
+/// The size of a sector in the partition tables, and the stride of the update sequence.
+const SECTOR_SIZE: u64 = 512;
+/// The record number of the root directory in the master file table.
+const ROOT_RECORD: u64 = 5;
+/// The largest cluster size that NTFS supports.
+const MAX_CLUSTER_SIZE: u64 = 2 * 1024 * 1024;
+/// The largest record in the master file table that we accept, these are 1KB or 4KB in practice.
+const MAX_RECORD_SIZE: u64 = 64 * 1024;
+/// The largest entry in the GPT partition table that we accept, these are 128 bytes in practice.
+const MAX_GPT_ENTRY_SIZE: usize = 4096;
+
+const ATTRIBUTE_FILE_NAME: u32 = 0x30;
+const ATTRIBUTE_DATA: u32 = 0x80;
+const ATTRIBUTE_END: u32 = 0xFFFFFFFF;
+
+const RECORD_IN_USE: u16 = 0x1;
+const RECORD_IS_DIRECTORY: u16 = 0x2;
+
+lazy_static::lazy_static! {
+    // NOTE: Walking the master file table is expensive, so the artefacts found in each image are
+    // kept for the sizes and extractions that follow the enumeration.
+    static ref IMAGES: Mutex<HashMap<PathBuf, Arc<HashMap<String, Entry>>>> =
+        Mutex::new(HashMap::new());
+}
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| {
+            ["dd", "img", "raw"]
+                .iter()
+                .any(|i| e.eq_ignore_ascii_case(i))
+        })
+        .unwrap_or(false)
+}
+
+/// The artefacts within a raw disk image, as paths beneath the image itself.
+pub fn entries(image: &Path, extensions: &Option<HashSet<String>>) -> crate::Result<Vec<PathBuf>> {
+    let mut names = load(image)?
+        .keys()
+        .filter(|name| match extensions {
+            Some(e) => match Path::new(name).extension() {
+                Some(ext) => e.contains(&ext.to_string_lossy().to_lowercase()),
+                None => false,
+            },
+            None => true,
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    names.sort();
+    Ok(names.into_iter().map(|name| image.join(name)).collect())
+}
+
+/// The size of an artefact within the image.
+pub fn size(image: &Path, name: &str) -> crate::Result<u64> {
+    Ok(entry(image, name)?.size)
+}
+
+/// Copies an artefact out of the image into a temporary file, keeping its extension so that it is
+/// loaded by the correct parser. The file is removed when it is dropped.
+pub fn extract(image: &Path, name: &str) -> crate::Result<NamedTempFile> {
+    let entry = entry(image, name)?;
+    let suffix = Path::new(name)
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    let mut file = Builder::new().suffix(&suffix).tempfile()?;
+    let mut reader = File::open(image)?;
+    let mut remaining = entry.size;
+    for (lcn, clusters) in &entry.runs {
+        if remaining == 0 {
+            break;
+        }
+        let len = clusters.saturating_mul(entry.cluster_size).min(remaining);
+        let copied = match lcn {
+            Some(lcn) => {
+                reader.seek(SeekFrom::Start(cluster_offset(
+                    entry.offset,
+                    *lcn,
+                    entry.cluster_size,
+                )?))?;
+                io::copy(&mut Read::by_ref(&mut reader).take(len), &mut file)?
+            }
+            // NOTE: Sparse runs are not stored on disk and read back as zeros.
+            None => io::copy(&mut io::repeat(0).take(len), &mut file)?,
+        };
+        if copied != len {
+            anyhow::bail!("image is truncated, could not read all of '{}'", name);
+        }
+        remaining -= len;
+    }
+    Ok(file)
+}
+
+/// A file within a volume, located by the runs of clusters that hold its data.
+#[derive(Clone, Debug)]
+struct Entry {
+    cluster_size: u64,
+    offset: u64,
+    runs: Vec<(Option<u64>, u64)>,
+    size: u64,
+}
+
+fn entry(image: &Path, name: &str) -> crate::Result<Entry> {
+    load(image)?
+        .get(name)
+        .cloned()
+        .ok_or_else(|| anyhow!("could not find '{}' in image", name))
+}
+
+fn load(image: &Path) -> crate::Result<Arc<HashMap<String, Entry>>> {
+    if let Some(files) = IMAGES.lock().expect("could not lock images").get(image) {
+        return Ok(files.clone());
+    }
+    // NOTE: The lock is not held while walking the image so that other images are not blocked,
+    // which means that an image could be walked twice if it is loaded from two threads at once.
+    let mut reader = File::open(image)?;
+    let mut files = HashMap::new();
+    let mut count = 0;
+    for offset in volume_offsets(&mut reader)? {
+        let volume = match Volume::load(&mut reader, offset)? {
+            Some(volume) => volume,
+            None => continue,
+        };
+        count += 1;
+        for (path, entry) in volume.artefacts(&mut reader)? {
+            let path = format!("volume{}/{}", count, path);
+            match entry {
+                Some(entry) => {
+                    files.insert(path, entry);
+                }
+                None => cs_eyellowln!(
+                    "[!] Skipping '{}' in '{}' as its data is not held in its base record",
+                    path,
+                    image.display()
+                ),
+            }
+        }
+    }
+    // NOTE: Raw files are not always disk images, so these are skipped rather than failing.
+    if count == 0 {
+        cs_eyellowln!(
+            "[!] Skipping '{}' as it does not contain an NTFS volume",
+            image.display()
+        );
+    }
+    let files = IMAGES
+        .lock()
+        .expect("could not lock images")
+        .entry(image.to_path_buf())
+        .or_insert_with(|| Arc::new(files))
+        .clone();
+    Ok(files)
+}
+
+/// The offsets of the partitions in the image, which may be an image of a single volume.
+fn volume_offsets(reader: &mut File) -> crate::Result<Vec<u64>> {
+    let mut sector = [0u8; SECTOR_SIZE as usize];
+    read_at(reader, 0, &mut sector)?;
+    if &sector[3..11] == b"NTFS    " {
+        return Ok(vec![0]);
+    }
+    if sector[510..512] != [0x55, 0xAA] {
+        return Ok(vec![]);
+    }
+    // NOTE: Extended partitions are not followed, as system volumes are primary partitions.
+    let mut offsets = vec![];
+    for entry in sector[446..510].chunks_exact(16) {
+        match entry[4] {
+            0x00 => continue,
+            0xEE => offsets.extend(gpt_offsets(reader)?),
+            _ => offsets.push(u32_at(entry, 8)? as u64 * SECTOR_SIZE),
+        }
+    }
+    Ok(offsets)
+}
+
+fn gpt_offsets(reader: &mut File) -> crate::Result<Vec<u64>> {
+    let mut header = [0u8; SECTOR_SIZE as usize];
+    read_at(reader, SECTOR_SIZE, &mut header)?;
+    if &header[0..8] != b"EFI PART" {
+        anyhow::bail!("invalid GPT header in image");
+    }
+    let lba = u64_at(&header, 72)?
+        .checked_mul(SECTOR_SIZE)
+        .ok_or_else(|| anyhow!("invalid GPT header in image"))?;
+    let count = u32_at(&header, 80)?.min(1024) as usize;
+    let size = u32_at(&header, 84)? as usize;
+    if !(40..=MAX_GPT_ENTRY_SIZE).contains(&size) {
+        anyhow::bail!("invalid GPT entry size in image");
+    }
+    let mut entries = vec![0u8; count * size];
+    read_at(reader, lba, &mut entries)?;
+    let mut offsets = vec![];
+    for entry in entries.chunks_exact(size) {
+        let first = u64_at(entry, 32)?;
+        if first != 0 {
+            offsets.push(
+                first
+                    .checked_mul(SECTOR_SIZE)
+                    .ok_or_else(|| anyhow!("invalid GPT entry in image"))?,
+            );
+        }
+    }
+    Ok(offsets)
+}
+
+struct Volume {
+    cluster_size: u64,
+    /// The position of the master file table within the image.
+    mft: u64,
+    offset: u64,
+    record_size: u64,
+}
+
+/// What we need to know about a record in the master file table to build the paths.
+struct Node {
+    data: Option<(Vec<(Option<u64>, u64)>, u64)>,
+    directory: bool,
+    name: String,
+    parent: u64,
+}
+
+impl Volume {
+    fn load(reader: &mut File, offset: u64) -> crate::Result<Option<Self>> {
+        let mut boot = [0u8; SECTOR_SIZE as usize];
+        read_at(reader, offset, &mut boot)?;
+        if &boot[3..11] != b"NTFS    " {
+            return Ok(None);
+        }
+        let invalid = || anyhow!("invalid NTFS boot sector in image");
+        // NOTE: Large clusters store the sectors per cluster as a negative power of two.
+        let sectors = match boot[0x0D] {
+            s if s > 0x80 => 1u64.checked_shl(256 - s as u32).ok_or_else(invalid)?,
+            s => s as u64,
+        };
+        let cluster_size = (u16_at(&boot, 0x0B)? as u64)
+            .checked_mul(sectors)
+            .filter(|c| (1..=MAX_CLUSTER_SIZE).contains(c))
+            .ok_or_else(invalid)?;
+        // NOTE: Positive values are clusters per record, negative values are a power of two.
+        let record_size = match boot[0x40] as i8 {
+            c if c > 0 => c as u64 * cluster_size,
+            c => 1u64
+                .checked_shl(c.unsigned_abs() as u32)
+                .ok_or_else(invalid)?,
+        };
+        if !(SECTOR_SIZE..=MAX_RECORD_SIZE).contains(&record_size) {
+            return Err(invalid());
+        }
+        Ok(Some(Self {
+            cluster_size,
+            mft: cluster_offset(offset, u64_at(&boot, 0x30)?, cluster_size)?,
+            offset,
+            record_size,
+        }))
+    }
+
+    /// Walks the master file table for the artefacts, returning them keyed by their path.
+    ///
+    /// NOTE: Attribute lists are not followed, so the artefacts whose data is held in an extension
+    /// record (or is resident) have no entry and are returned so that they can be reported.
+    fn artefacts(&self, reader: &mut File) -> crate::Result<Vec<(String, Option<Entry>)>> {
+        let mut record = vec![0u8; self.record_size as usize];
+        read_at(reader, self.mft, &mut record)?;
+        fixup(&mut record)?;
+        // NOTE: The master file table describes itself, which tells us where its records are.
+        let (runs, size) = parse(&record, &[])?
+            .and_then(|n| n.data)
+            .ok_or_else(|| anyhow!("could not find the master file table in image"))?;
+        let total = size / self.record_size;
+
+        let extensions = ARTEFACTS.iter().map(|(_, e)| *e).collect::<Vec<_>>();
+        let mut nodes = HashMap::new();
+        let mut number = 0;
+        let mut reader = BufReader::with_capacity(1024 * 1024, reader);
+        for (lcn, clusters) in runs {
+            let records = clusters
+                .checked_mul(self.cluster_size)
+                .ok_or_else(|| anyhow!("invalid master file table run in image"))?
+                / self.record_size;
+            let lcn = match lcn {
+                Some(lcn) => lcn,
+                None => {
+                    number += records;
+                    continue;
+                }
+            };
+            reader.seek(SeekFrom::Start(cluster_offset(
+                self.offset,
+                lcn,
+                self.cluster_size,
+            )?))?;
+            for _ in 0..records {
+                if number >= total {
+                    break;
+                }
+                reader.read_exact(&mut record)?;
+                // NOTE: Records that have never been used are not initialised, so those that
+                // cannot be read are skipped rather than failing the whole image.
+                if fixup(&mut record).is_ok() {
+                    if let Ok(Some(node)) = parse(&record, &extensions) {
+                        nodes.insert(number, node);
+                    }
+                }
+                number += 1;
+            }
+        }
+
+        let mut artefacts = vec![];
+        for node in nodes.values() {
+            if node.directory {
+                continue;
+            }
+            let mut components = vec![node.name.as_str()];
+            let mut parent = node.parent;
+            // NOTE: The depth is capped as a corrupt table could have the parents form a cycle.
+            while parent != ROOT_RECORD && components.len() < 64 {
+                match nodes.get(&parent) {
+                    Some(p) if p.directory => {
+                        components.push(p.name.as_str());
+                        parent = p.parent;
+                    }
+                    _ => break,
+                }
+            }
+            if parent != ROOT_RECORD {
+                continue;
+            }
+            components.reverse();
+            let path = components.join("/");
+            let directory = components[..components.len() - 1].join("/").to_lowercase();
+            let extension = Path::new(&node.name)
+                .extension()
+                .map(|e| e.to_string_lossy().to_lowercase());
+            let wanted = ARTEFACTS
+                .iter()
+                .any(|(d, e)| directory == *d && extension.as_deref() == Some(*e));
+            if wanted {
+                let entry = node.data.as_ref().map(|(runs, size)| Entry {
+                    cluster_size: self.cluster_size,
+                    offset: self.offset,
+                    runs: runs.clone(),
+                    size: *size,
+                });
+                artefacts.push((path, entry));
+            }
+        }
+        Ok(artefacts)
+    }
+}
+
+/// Replaces the last two bytes of each sector in the record with their original values, these are
+/// swapped out for the update sequence number when the record is written to disk.
+fn fixup(record: &mut [u8]) -> crate::Result<()> {
+    if &record[0..4] != b"FILE" {
+        anyhow::bail!("invalid record signature");
+    }
+    let offset = u16_at(record, 4)? as usize;
+    let count = u16_at(record, 6)? as usize;
+    let usn = match record.get(offset..offset + 2) {
+        Some(usn) => [usn[0], usn[1]],
+        None => anyhow::bail!("invalid update sequence"),
+    };
+    for i in 1..count {
+        let end = i * SECTOR_SIZE as usize;
+        let fix = offset + i * 2;
+        if end > record.len() || fix + 2 > record.len() || record[end - 2..end] != usn {
+            anyhow::bail!("invalid update sequence");
+        }
+        let value = [record[fix], record[fix + 1]];
+        record[end - 2..end].copy_from_slice(&value);
+    }
+    Ok(())
+}
+
+/// Parses the record into a node, only keeping the data of the files with the provided extensions
+/// (or every file when the record is the master file table itself).
+fn parse(record: &[u8], extensions: &[&str]) -> crate::Result<Option<Node>> {
+    let flags = u16_at(record, 0x16)?;
+    // NOTE: Extension records only hold the attributes that did not fit in the base record.
+    if flags & RECORD_IN_USE == 0 || u64_at(record, 0x20)? & 0xFFFF_FFFF_FFFF != 0 {
+        return Ok(None);
+    }
+    let mut name: Option<(String, u64, u8)> = None;
+    let mut data = None;
+    let mut offset = u16_at(record, 0x14)? as usize;
+    loop {
+        let kind = u32_at(record, offset)?;
+        if kind == ATTRIBUTE_END {
+            break;
+        }
+        let len = u32_at(record, offset + 4)? as usize;
+        // NOTE: Every attribute has at least a 16 byte header, which also stops a corrupt length
+        // from looping forever.
+        let attribute = match record.get(offset..offset + len) {
+            Some(a) if len >= 0x10 => a,
+            _ => anyhow::bail!("invalid attribute length"),
+        };
+        let non_resident = attribute[8] != 0;
+        let named = attribute[9] != 0;
+        match kind {
+            ATTRIBUTE_FILE_NAME if !non_resident => {
+                let value_len = u32_at(attribute, 0x10)? as usize;
+                let value_offset = u16_at(attribute, 0x14)? as usize;
+                let value = attribute
+                    .get(value_offset..value_offset + value_len)
+                    .filter(|v| v.len() >= 0x42)
+                    .ok_or_else(|| anyhow!("invalid file name attribute"))?;
+                let namespace = value[0x41];
+                // NOTE: The short DOS names are only used when there is nothing better.
+                if name.as_ref().map(|(_, _, n)| *n == 2).unwrap_or(true) {
+                    let chars = value
+                        .get(0x42..0x42 + value[0x40] as usize * 2)
+                        .ok_or_else(|| anyhow!("invalid file name attribute"))?
+                        .chunks_exact(2)
+                        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                        .collect::<Vec<_>>();
+                    let parent = u64_at(value, 0)? & 0xFFFF_FFFF_FFFF;
+                    name = Some((String::from_utf16_lossy(&chars), parent, namespace));
+                }
+            }
+            ATTRIBUTE_DATA if non_resident && !named => {
+                data = Some((data_runs(attribute)?, u64_at(attribute, 0x30)?));
+            }
+            _ => {}
+        }
+        offset += len;
+    }
+    let (name, parent, _) = match name {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    let directory = flags & RECORD_IS_DIRECTORY != 0;
+    let wanted = extensions.iter().any(|e| {
+        Path::new(&name)
+            .extension()
+            .map(|x| x.to_string_lossy().eq_ignore_ascii_case(e))
+            .unwrap_or(false)
+    });
+    if !directory && !wanted && name != "$MFT" {
+        return Ok(None);
+    }
+    Ok(Some(Node {
+        data,
+        directory,
+        name,
+        parent,
+    }))
+}
+
+/// Decodes the runs of a non-resident attribute into their starting cluster and length, where
+/// sparse runs have no starting cluster.
+fn data_runs(attribute: &[u8]) -> crate::Result<Vec<(Option<u64>, u64)>> {
+    let mut offset = u16_at(attribute, 0x20)? as usize;
+    let mut lcn: i64 = 0;
+    let mut runs = vec![];
+    loop {
+        let header = *attribute
+            .get(offset)
+            .ok_or_else(|| anyhow!("unterminated data runs"))?;
+        if header == 0 {
+            break;
+        }
+        let length_size = (header & 0x0F) as usize;
+        let offset_size = (header >> 4) as usize;
+        if !(1..=8).contains(&length_size) || offset_size > 8 {
+            anyhow::bail!("invalid data run header");
+        }
+        let bytes = attribute
+            .get(offset + 1..offset + 1 + length_size + offset_size)
+            .ok_or_else(|| anyhow!("truncated data run"))?;
+        let length = bytes[..length_size]
+            .iter()
+            .rev()
+            .fold(0u64, |a, b| (a << 8) | *b as u64);
+        if offset_size == 0 {
+            runs.push((None, length));
+        } else {
+            // NOTE: The starting cluster is a signed delta from the previous run.
+            let delta = bytes[length_size..]
+                .iter()
+                .rev()
+                .fold(0u64, |a, b| (a << 8) | *b as u64);
+            let shift = 64 - offset_size * 8;
+            lcn = lcn
+                .checked_add(((delta << shift) as i64) >> shift)
+                .filter(|l| *l >= 0)
+                .ok_or_else(|| anyhow!("invalid data run offset"))?;
+            runs.push((Some(lcn as u64), length));
+        }
+        offset += 1 + length_size + offset_size;
+    }
+    Ok(runs)
+}
+
+/// The position of the cluster within the image, for the volume at the offset.
+fn cluster_offset(offset: u64, lcn: u64, cluster_size: u64) -> crate::Result<u64> {
+    lcn.checked_mul(cluster_size)
+        .and_then(|o| o.checked_add(offset))
+        .ok_or_else(|| anyhow!("cluster {} is out of range of the image", lcn))
+}
+
+fn read_at(reader: &mut File, offset: u64, buf: &mut [u8]) -> crate::Result<()> {
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(buf)?;
+    Ok(())
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> crate::Result<u16> {
+    let slice = bytes
+        .get(offset..offset + 2)
+        .ok_or_else(|| anyhow!("image byte indexing error at offset {}!", offset))?;
+    Ok(u16::from_le_bytes(slice.try_into()?))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> crate::Result<u32> {
+    let slice = bytes
+        .get(offset..offset + 4)
+        .ok_or_else(|| anyhow!("image byte indexing error at offset {}!", offset))?;
+    Ok(u32::from_le_bytes(slice.try_into()?))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> crate::Result<u64> {
+    let slice = bytes
+        .get(offset..offset + 8)
+        .ok_or_else(|| anyhow!("image byte indexing error at offset {}!", offset))?;
+    Ok(u64::from_le_bytes(slice.try_into()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    const CLUSTER_SIZE: usize = 512;
+    const RECORD_SIZE: usize = 1024;
+    /// The cluster that the master file table starts at, which holds 21 records.
+    const MFT_LCN: u8 = 4;
+    const ARTEFACT: &str = "Windows/System32/winevt/Logs/Security.evtx";
+    const ARTEFACT_SIZE: usize = 3 * CLUSTER_SIZE - 100;
+
+    fn file_name(parent: u64, name: &str) -> Vec<u8> {
+        let chars: Vec<u8> = name.encode_utf16().flat_map(|c| c.to_le_bytes()).collect();
+        let mut value = vec![0u8; 0x42];
+        value[0..8].copy_from_slice(&parent.to_le_bytes());
+        value[0x40] = name.encode_utf16().count() as u8;
+        value[0x41] = 1;
+        value.extend(chars);
+        let mut attribute = vec![0u8; 0x18];
+        attribute[0..4].copy_from_slice(&ATTRIBUTE_FILE_NAME.to_le_bytes());
+        attribute[0x10..0x14].copy_from_slice(&(value.len() as u32).to_le_bytes());
+        attribute[0x14..0x16].copy_from_slice(&0x18u16.to_le_bytes());
+        attribute.extend(value);
+        attribute
+    }
+
+    fn data(runs: &[u8], size: usize) -> Vec<u8> {
+        let mut attribute = vec![0u8; 0x40];
+        attribute[0..4].copy_from_slice(&ATTRIBUTE_DATA.to_le_bytes());
+        attribute[8] = 1;
+        attribute[0x20..0x22].copy_from_slice(&0x40u16.to_le_bytes());
+        attribute[0x30..0x38].copy_from_slice(&(size as u64).to_le_bytes());
+        attribute.extend(runs);
+        attribute.push(0);
+        attribute
+    }
+
+    /// A record as it is written to disk, with the update sequence number at the end of each
+    /// sector.
+    fn record(flags: u16, attributes: &[Vec<u8>]) -> Vec<u8> {
+        let mut record = vec![0u8; RECORD_SIZE];
+        record[0..4].copy_from_slice(b"FILE");
+        record[4..6].copy_from_slice(&0x30u16.to_le_bytes());
+        record[6..8].copy_from_slice(&3u16.to_le_bytes());
+        record[0x14..0x16].copy_from_slice(&0x38u16.to_le_bytes());
+        record[0x16..0x18].copy_from_slice(&flags.to_le_bytes());
+        let mut offset = 0x38;
+        for attribute in attributes {
+            let mut attribute = attribute.clone();
+            attribute.resize((attribute.len() + 7) & !7, 0);
+            let len = attribute.len() as u32;
+            attribute[4..8].copy_from_slice(&len.to_le_bytes());
+            record[offset..offset + attribute.len()].copy_from_slice(&attribute);
+            offset += attribute.len();
+        }
+        record[offset..offset + 4].copy_from_slice(&ATTRIBUTE_END.to_le_bytes());
+        let usn = [0x01, 0x00];
+        record[0x30..0x32].copy_from_slice(&usn);
+        for i in 1..3 {
+            let end = i * SECTOR_SIZE as usize;
+            record.copy_within(end - 2..end, 0x30 + i * 2);
+            record[end - 2..end].copy_from_slice(&usn);
+        }
+        record
+    }
+
+    /// An NTFS volume holding a single event log, whose data is split into two runs around a
+    /// sparse run.
+    fn volume() -> (Vec<u8>, Vec<u8>) {
+        let mut volume = vec![0u8; 54 * CLUSTER_SIZE];
+        volume[3..11].copy_from_slice(b"NTFS    ");
+        volume[0x0B..0x0D].copy_from_slice(&(SECTOR_SIZE as u16).to_le_bytes());
+        volume[0x0D] = 1;
+        volume[0x30] = MFT_LCN;
+        // 2^10 bytes per record
+        volume[0x40] = -10i8 as u8;
+
+        let mut records = vec![(
+            0,
+            record(
+                RECORD_IN_USE,
+                &[
+                    file_name(ROOT_RECORD, "$MFT"),
+                    data(&[0x11, 0x2A, MFT_LCN], 21 * RECORD_SIZE),
+                ],
+            ),
+        )];
+        let mut parent = ROOT_RECORD;
+        for (number, name) in (16..).zip(["Windows", "System32", "winevt", "Logs"]) {
+            let flags = RECORD_IN_USE | RECORD_IS_DIRECTORY;
+            records.push((number, record(flags, &[file_name(parent, name)])));
+            parent = number;
+        }
+        // 1 cluster at 50, 1 sparse cluster, then 1 cluster at 48
+        let runs = [0x11, 0x01, 0x32, 0x01, 0x01, 0x11, 0x01, 0xFE];
+        let attributes = [
+            file_name(parent, "Security.evtx"),
+            data(&runs, ARTEFACT_SIZE),
+        ];
+        records.push((20, record(RECORD_IN_USE, &attributes)));
+        for (number, record) in records {
+            let offset = MFT_LCN as usize * CLUSTER_SIZE + number as usize * RECORD_SIZE;
+            volume[offset..offset + RECORD_SIZE].copy_from_slice(&record);
+        }
+
+        for (lcn, byte) in [(50, 0xAA), (48, 0xBB)] {
+            volume[lcn * CLUSTER_SIZE..(lcn + 1) * CLUSTER_SIZE].fill(byte);
+        }
+        let mut expected = vec![0xAA; CLUSTER_SIZE];
+        expected.extend(vec![0; CLUSTER_SIZE]);
+        expected.extend(vec![0xBB; CLUSTER_SIZE]);
+        expected.truncate(ARTEFACT_SIZE);
+        (volume, expected)
+    }
+
+    fn check(image: Vec<u8>, expected: &[u8]) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.img");
+        fs::write(&path, image).unwrap();
+        let name = format!("volume1/{}", ARTEFACT);
+        assert_eq!(entries(&path, &None).unwrap(), vec![path.join(&name)]);
+        assert_eq!(size(&path, &name).unwrap(), ARTEFACT_SIZE as u64);
+        let extracted = extract(&path, &name).unwrap();
+        assert!(extracted.path().to_string_lossy().ends_with(".evtx"));
+        assert_eq!(fs::read(extracted.path()).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_extract_volume() {
+        let (volume, expected) = volume();
+        check(volume, &expected);
+    }
+
+    #[test]
+    fn test_extract_mbr() {
+        let (volume, expected) = volume();
+        let mut image = vec![0u8; 2 * SECTOR_SIZE as usize];
+        image[446 + 4] = 0x07;
+        image[446 + 8..446 + 12].copy_from_slice(&2u32.to_le_bytes());
+        image[510..512].copy_from_slice(&[0x55, 0xAA]);
+        image.extend(volume);
+        check(image, &expected);
+    }
+
+    #[test]
+    fn test_extract_gpt() {
+        let (volume, expected) = volume();
+        let mut image = vec![0u8; 6 * SECTOR_SIZE as usize];
+        image[446 + 4] = 0xEE;
+        image[510..512].copy_from_slice(&[0x55, 0xAA]);
+        let header = SECTOR_SIZE as usize;
+        image[header..header + 8].copy_from_slice(b"EFI PART");
+        image[header + 72..header + 80].copy_from_slice(&2u64.to_le_bytes());
+        image[header + 80..header + 84].copy_from_slice(&4u32.to_le_bytes());
+        image[header + 84..header + 88].copy_from_slice(&128u32.to_le_bytes());
+        let entry = 2 * SECTOR_SIZE as usize;
+        image[entry + 32..entry + 40].copy_from_slice(&6u64.to_le_bytes());
+        image.extend(volume);
+        check(image, &expected);
+    }
+
+    #[test]
+    fn test_invalid_boot_sector() {
+        for (offset, value) in [(0x0D, 0x81), (0x0D, 0xF0), (0x40, 0x80), (0x40, 0x00)] {
+            let (mut volume, _) = volume();
+            volume[offset] = value;
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("disk.img");
+            fs::write(&path, volume).unwrap();
+            assert!(Volume::load(&mut File::open(&path).unwrap(), 0).is_err());
+        }
+    }
+
+    #[test]
+    fn test_invalid_gpt_entry_size() {
+        let mut image = vec![0u8; 2 * SECTOR_SIZE as usize];
+        image[446 + 4] = 0xEE;
+        image[510..512].copy_from_slice(&[0x55, 0xAA]);
+        let header = SECTOR_SIZE as usize;
+        image[header..header + 8].copy_from_slice(b"EFI PART");
+        image[header + 80..header + 84].copy_from_slice(&1024u32.to_le_bytes());
+        image[header + 84..header + 88].copy_from_slice(&u32::MAX.to_le_bytes());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("disk.img");
+        fs::write(&path, image).unwrap();
+        assert!(volume_offsets(&mut File::open(&path).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_data_runs() {
+        let mut attribute = vec![0u8; 0x40];
+        attribute[0x20] = 0x40;
+        attribute.extend([
+            // 0x18 clusters starting at cluster 0x5634
+            0x21, 0x18, 0x34, 0x56, //
+            // A sparse run of 0x10 clusters
+            0x01, 0x10, //
+            // 0x08 clusters starting 0x100 clusters before the previous run
+            0x21, 0x08, 0x00, 0xFF, //
+            0x00,
+        ]);
+        assert_eq!(
+            data_runs(&attribute).unwrap(),
+            vec![(Some(0x5634), 0x18), (None, 0x10), (Some(0x5534), 0x08)]
+        );
+    }
+}
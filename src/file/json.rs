@@ -1,7 +1,6 @@
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{self, BufRead, BufReader};
 use std::path::Path;
-use std::vec::IntoIter;
 
 use anyhow::Error;
 use regex::RegexSet;
@@ -10,52 +9,213 @@ pub use serde_json::Value as Json;
 use crate::search::Searchable;
 
 pub struct Parser {
-    pub inner: Option<Json>,
+    pub inner: Option<Elements<BufReader<File>>>,
+    first: Option<Json>,
 }
 
 impl Parser {
     pub fn load(path: &Path) -> crate::Result<Self> {
         let file = File::open(path)?;
-        let reader = BufReader::new(file);
-        let json = serde_json::from_reader(reader)?;
-        Ok(Self { inner: Some(json) })
+        let mut elements = Elements::new(BufReader::new(file));
+        // NOTE: Only the first document is read up front, which is enough for loading to fail for
+        // files that are not json (e.g. jsonl when guessing the kind), the rest is validated as it
+        // is read.
+        let first = elements.read()?;
+        Ok(Self {
+            inner: Some(elements),
+            first,
+        })
     }
 
     pub fn parse(&mut self) -> impl Iterator<Item = Result<Json, Error>> + '_ {
-        if let Some(json) = self.inner.take() {
-            return match json {
-                Json::Array(array) => ParserIter(Some(array.into_iter())),
-                _ => ParserIter(Some(vec![json].into_iter())),
-            };
-        }
-        ParserIter(None)
+        self.first
+            .take()
+            .map(Ok)
+            .into_iter()
+            .chain(self.inner.take().into_iter().flatten())
     }
 }
 
-struct ParserIter(Option<IntoIter<Json>>);
+enum State {
+    Start,
+    First,
+    Next,
+    Done,
+}
 
-impl Iterator for ParserIter {
-    type Item = Result<Json, Error>;
+/// Reads each element of a top level array as its own document, anything else is read whole.
+///
+/// NOTE: A top level array can be far larger than memory, so we only ever hold the bytes of the
+/// element being read, which are found by tracking the nesting rather than by parsing them twice.
+pub struct Elements<R> {
+    buffer: Vec<u8>,
+    reader: R,
+    state: State,
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match &mut self.0 {
-            Some(i) => i.next().map(Ok),
-            None => None,
+impl<R: BufRead> Elements<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            buffer: vec![],
+            reader,
+            state: State::Start,
+        }
+    }
+
+    fn read(&mut self) -> crate::Result<Option<Json>> {
+        let result = self.next_document();
+        if !matches!(result, Ok(Some(_))) {
+            self.state = State::Done;
         }
+        result
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        match &self.0 {
-            Some(i) => i.size_hint(),
-            None => (0, Some(0)),
+    fn next_document(&mut self) -> crate::Result<Option<Json>> {
+        loop {
+            match self.state {
+                State::Start => {
+                    if self.skip_whitespace()? == Some(b'[') {
+                        self.reader.consume(1);
+                        self.state = State::First;
+                        continue;
+                    }
+                    let json = self.value()?;
+                    self.end()?;
+                    self.state = State::Done;
+                    return Ok(Some(json));
+                }
+                State::First => {
+                    if self.skip_whitespace()? == Some(b']') {
+                        self.reader.consume(1);
+                        self.end()?;
+                        return Ok(None);
+                    }
+                    self.state = State::Next;
+                    return Ok(Some(self.value()?));
+                }
+                State::Next => match self.skip_whitespace()? {
+                    Some(b',') => {
+                        self.reader.consume(1);
+                        self.skip_whitespace()?;
+                        return Ok(Some(self.value()?));
+                    }
+                    Some(b']') => {
+                        self.reader.consume(1);
+                        self.end()?;
+                        return Ok(None);
+                    }
+                    Some(_) => anyhow::bail!("expected ',' or ']' after an element of the array"),
+                    None => anyhow::bail!("EOF while parsing the array"),
+                },
+                State::Done => return Ok(None),
+            }
+        }
+    }
+
+    /// Reads the value that starts at the current position, leaving the reader just after it.
+    fn value(&mut self) -> crate::Result<Json> {
+        self.buffer.clear();
+        let mut depth = 0usize;
+        let mut string = false;
+        let mut escape = false;
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                break;
+            }
+            let mut end = None;
+            for (i, byte) in available.iter().enumerate() {
+                if string {
+                    if escape {
+                        escape = false;
+                    } else if *byte == b'\\' {
+                        escape = true;
+                    } else if *byte == b'"' {
+                        string = false;
+                        if depth == 0 {
+                            end = Some(i + 1);
+                            break;
+                        }
+                    }
+                    continue;
+                }
+                match byte {
+                    b'"' => string = true,
+                    b'{' | b'[' => depth += 1,
+                    b'}' | b']' if depth > 0 => {
+                        depth -= 1;
+                        if depth == 0 {
+                            end = Some(i + 1);
+                            break;
+                        }
+                    }
+                    // NOTE: Anything else that ends a scalar is left for the caller to check.
+                    b'}' | b']' | b',' | b' ' | b'\t' | b'\n' | b'\r' if depth == 0 => {
+                        end = Some(i);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            let used = end.unwrap_or(available.len());
+            self.buffer.extend_from_slice(&available[..used]);
+            self.reader.consume(used);
+            if end.is_some() {
+                break;
+            }
+        }
+        // NOTE: The bytes of the value are only delimited above, this is what validates them.
+        Ok(serde_json::from_slice(&self.buffer)?)
+    }
+
+    fn end(&mut self) -> crate::Result<()> {
+        if self.skip_whitespace()?.is_some() {
+            anyhow::bail!("trailing characters after the json document");
+        }
+        Ok(())
+    }
+
+    /// Skips any whitespace, returning the next byte without consuming it.
+    fn skip_whitespace(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Ok(None);
+            }
+            match available
+                .iter()
+                .position(|b| !matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+            {
+                Some(i) => {
+                    let byte = available[i];
+                    self.reader.consume(i);
+                    return Ok(Some(byte));
+                }
+                None => {
+                    let len = available.len();
+                    self.reader.consume(len);
+                }
+            }
         }
     }
 }
 
+impl<R: BufRead> Iterator for Elements<R> {
+    type Item = Result<Json, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
 impl Searchable for Json {
     fn matches(&self, regex: &RegexSet) -> bool {
         regex.is_match(&self.to_string())
     }
+
+    fn patterns(&self, regex: &RegexSet) -> Vec<usize> {
+        regex.matches(&self.to_string()).into_iter().collect()
+    }
 }
 
 pub mod lines {
@@ -63,13 +223,16 @@ pub mod lines {
 
     use std::io::prelude::*;
     use std::io::Lines;
+    use std::path::PathBuf;
 
     pub struct Parser {
         pub inner: Option<BufReader<File>>,
+        pub path: PathBuf,
+        pub skip_errors: bool,
     }
 
     impl Parser {
-        pub fn load(path: &Path) -> crate::Result<Self> {
+        pub fn load(path: &Path, skip_errors: bool) -> crate::Result<Self> {
             let file = File::open(path)?;
             let mut reader = BufReader::new(file);
             // A crude check where we read the first line to see if its JSON, we should probably
@@ -80,37 +243,169 @@ pub mod lines {
             reader.rewind()?;
             Ok(Self {
                 inner: Some(reader),
+                path: path.to_path_buf(),
+                skip_errors,
             })
         }
 
         pub fn parse(&mut self) -> impl Iterator<Item = Result<Json, Error>> + '_ {
-            if let Some(file) = self.inner.take() {
-                return ParserIter(Some(file.lines()));
+            ParserIter {
+                lines: self.inner.take().map(|file| file.lines()),
+                path: &self.path,
+                skip_errors: self.skip_errors,
+                skipped: 0,
             }
-            ParserIter(None)
         }
     }
 
-    struct ParserIter(Option<Lines<BufReader<File>>>);
+    struct ParserIter<'a> {
+        lines: Option<Lines<BufReader<File>>>,
+        path: &'a Path,
+        skip_errors: bool,
+        skipped: usize,
+    }
 
-    impl Iterator for ParserIter {
+    impl<'a> Iterator for ParserIter<'a> {
         type Item = Result<Json, Error>;
 
         fn next(&mut self) -> Option<Self::Item> {
-            match &mut self.0 {
-                Some(i) => i.next().map(|l| match l {
+            let lines = self.lines.as_mut()?;
+            for line in lines.by_ref() {
+                let result = match line {
                     Ok(l) => serde_json::from_str(l.as_str()).map_err(Error::from),
                     Err(e) => Err(Error::from(e)),
-                }),
-                None => None,
+                };
+                match result {
+                    Ok(json) => return Some(Ok(json)),
+                    Err(e) => {
+                        // NOTE: Exports are often truncated or contain the odd malformed line, so
+                        // rather than warning for each one we tally them up and report once.
+                        if self.skip_errors {
+                            self.skipped += 1;
+                            continue;
+                        }
+                        return Some(Err(e));
+                    }
+                }
+            }
+            self.lines = None;
+            if self.skipped > 0 {
+                cs_eyellowln!(
+                    "[!] skipped {} malformed lines in '{}'\n",
+                    self.skipped,
+                    self.path.display()
+                );
             }
+            None
         }
 
         fn size_hint(&self) -> (usize, Option<usize>) {
-            match &self.0 {
+            match &self.lines {
                 Some(i) => i.size_hint(),
                 None => (0, Some(0)),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Read;
+
+    /// A top level array of documents that is generated as it is read, rather than held in memory.
+    struct Generated {
+        count: usize,
+        next: usize,
+        pending: Vec<u8>,
+        offset: usize,
+    }
+
+    impl Read for Generated {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.offset == self.pending.len() {
+                self.pending = match self.next {
+                    0 => format!(r#"[{{"id": 0, "message": "{}"}}"#, "a".repeat(64)),
+                    n if n < self.count => {
+                        format!(r#", {{"id": {}, "message": "{}"}}"#, n, "a".repeat(64))
+                    }
+                    n if n == self.count => "]".to_owned(),
+                    _ => return Ok(0),
+                }
+                .into_bytes();
+                self.next += 1;
+                self.offset = 0;
+            }
+            let len = buf.len().min(self.pending.len() - self.offset);
+            buf[..len].copy_from_slice(&self.pending[self.offset..self.offset + len]);
+            self.offset += len;
+            Ok(len)
+        }
+    }
+
+    fn documents(json: &str) -> crate::Result<Vec<Json>> {
+        Elements::new(json.as_bytes()).collect()
+    }
+
+    #[test]
+    fn test_elements() {
+        assert_eq!(
+            documents(r#" [1, -2.5e3 ,"a,]\"}", true, null, {"b": [{}]}, []] "#).unwrap(),
+            vec![
+                Json::from(1),
+                Json::from(-2500.0),
+                Json::from("a,]\"}"),
+                Json::from(true),
+                Json::Null,
+                serde_json::json!({"b": [{}]}),
+                serde_json::json!([]),
+            ]
+        );
+        assert_eq!(
+            documents(r#"{"a": 1}"#).unwrap(),
+            vec![serde_json::json!({"a": 1})]
+        );
+        assert_eq!(documents(" [ ] ").unwrap(), Vec::<Json>::new());
+    }
+
+    #[test]
+    fn test_elements_invalid() {
+        for json in [
+            "",
+            "{\"a\": 1}\n{\"a\": 2}\n",
+            "[1 2]",
+            "[1,]",
+            "[1, 2",
+            "[1] 2",
+            "[{\"a\": }]",
+        ] {
+            assert!(documents(json).is_err(), "{}", json);
+        }
+        // NOTE: The elements before the error are still read.
+        let mut elements = Elements::new(&b"[1, {]"[..]);
+        assert_eq!(elements.next().unwrap().unwrap(), Json::from(1));
+        assert!(elements.next().unwrap().is_err());
+        assert!(elements.next().is_none());
+    }
+
+    #[test]
+    fn test_elements_bounded() {
+        let count = 200_000;
+        let reader = Generated {
+            count,
+            next: 0,
+            pending: vec![],
+            offset: 0,
+        };
+        let mut elements = Elements::new(BufReader::new(reader));
+        let mut read = 0;
+        for (i, json) in elements.by_ref().enumerate() {
+            assert_eq!(json.unwrap()["id"], i);
+            read += 1;
+        }
+        assert_eq!(read, count);
+        // NOTE: Only a single element is ever held, rather than the ~18MB array.
+        assert!(elements.buffer.capacity() < 1024);
+    }
+}
@@ -3,6 +3,7 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
 
 use self::evtx::{Evtx, Parser as EvtxParser};
 use self::hve::{Hve, Parser as HveParser};
@@ -10,8 +11,11 @@ use self::json::{lines::Parser as JsonlParser, Json, Parser as JsonParser};
 use self::mft::{Mft, Parser as MftParser};
 use self::xml::{Parser as XmlParser, Xml};
 
+pub mod archive;
+pub mod cache;
 pub mod evtx;
 pub mod hve;
+pub mod image;
 pub mod json;
 pub mod mft;
 pub mod xml;
@@ -47,7 +51,7 @@ impl Kind {
             Kind::Evtx => Some(vec!["evt".to_string(), "evtx".to_string()]),
             Kind::Hve => Some(vec!["hve".to_string()]),
             Kind::Json => Some(vec!["json".to_string()]),
-            Kind::Jsonl => Some(vec!["jsonl".to_string()]),
+            Kind::Jsonl => Some(vec!["jsonl".to_string(), "ndjson".to_string()]),
             Kind::Mft => Some(vec!["mft".to_string(), "bin".to_string()]),
             Kind::Xml => Some(vec!["xml".to_string()]),
             Kind::Unknown => None,
@@ -73,6 +77,8 @@ impl Iterator for Unknown {
 }
 
 pub enum Parser {
+    Cached(cache::Parser),
+    Caching(Box<Parser>, cache::Writer),
     Evtx(EvtxParser),
     Hve(HveParser),
     Json(JsonParser),
@@ -84,10 +90,31 @@ pub enum Parser {
 
 pub struct Reader {
     parser: Parser,
+    // NOTE: Files within archives are extracted to disk for the parsers, and must outlive them.
+    extracted: Option<NamedTempFile>,
 }
 
 impl Reader {
     pub fn load(file: &Path, load_unknown: bool, skip_errors: bool) -> crate::Result<Self> {
+        if let Some((archive, name)) = archive::split(file) {
+            let extracted = match archive::extract(archive, &name) {
+                Ok(extracted) => extracted,
+                Err(e) => {
+                    if skip_errors {
+                        cs_eyellowln!("[!] failed to load file '{}' - {}\n", file.display(), e);
+                        return Ok(Self {
+                            parser: Parser::Unknown,
+                            extracted: None,
+                        });
+                    } else {
+                        anyhow::bail!(e);
+                    }
+                }
+            };
+            let mut reader = Self::load(extracted.path(), load_unknown, skip_errors)?;
+            reader.extracted = Some(extracted);
+            return Ok(reader);
+        }
         // NOTE: We don't want to use libmagic because then we have to include databases etc... So
         // for now we assume that the file extensions are correct!
         match file.extension().and_then(|e| e.to_str()) {
@@ -104,6 +131,7 @@ impl Reader {
                                 );
                                 return Ok(Self {
                                     parser: Parser::Unknown,
+                                    extracted: None,
                                 });
                             } else {
                                 anyhow::bail!(e);
@@ -112,6 +140,7 @@ impl Reader {
                     };
                     Ok(Self {
                         parser: Parser::Evtx(parser),
+                        extracted: None,
                     })
                 }
                 "json" => {
@@ -126,6 +155,7 @@ impl Reader {
                                 );
                                 return Ok(Self {
                                     parser: Parser::Unknown,
+                                    extracted: None,
                                 });
                             } else {
                                 anyhow::bail!(e);
@@ -134,10 +164,11 @@ impl Reader {
                     };
                     Ok(Self {
                         parser: Parser::Json(parser),
+                        extracted: None,
                     })
                 }
-                "jsonl" => {
-                    let parser = match JsonlParser::load(file) {
+                "jsonl" | "ndjson" => {
+                    let parser = match JsonlParser::load(file, skip_errors) {
                         Ok(parser) => parser,
                         Err(e) => {
                             if skip_errors {
@@ -148,6 +179,7 @@ impl Reader {
                                 );
                                 return Ok(Self {
                                     parser: Parser::Unknown,
+                                    extracted: None,
                                 });
                             } else {
                                 anyhow::bail!(e);
@@ -156,6 +188,7 @@ impl Reader {
                     };
                     Ok(Self {
                         parser: Parser::Jsonl(parser),
+                        extracted: None,
                     })
                 }
                 "bin" | "mft" => {
@@ -170,6 +203,7 @@ impl Reader {
                                 );
                                 return Ok(Self {
                                     parser: Parser::Unknown,
+                                    extracted: None,
                                 });
                             } else {
                                 anyhow::bail!(e);
@@ -178,6 +212,7 @@ impl Reader {
                     };
                     Ok(Self {
                         parser: Parser::Mft(parser),
+                        extracted: None,
                     })
                 }
                 "xml" => {
@@ -192,6 +227,7 @@ impl Reader {
                                 );
                                 return Ok(Self {
                                     parser: Parser::Unknown,
+                                    extracted: None,
                                 });
                             } else {
                                 anyhow::bail!(e);
@@ -200,6 +236,7 @@ impl Reader {
                     };
                     Ok(Self {
                         parser: Parser::Xml(parser),
+                        extracted: None,
                     })
                 }
                 "hve" => {
@@ -214,6 +251,7 @@ impl Reader {
                                 );
                                 return Ok(Self {
                                     parser: Parser::Unknown,
+                                    extracted: None,
                                 });
                             } else {
                                 anyhow::bail!(e);
@@ -222,6 +260,7 @@ impl Reader {
                     };
                     Ok(Self {
                         parser: Parser::Hve(parser),
+                        extracted: None,
                     })
                 }
                 _ => {
@@ -229,18 +268,27 @@ impl Reader {
                         if let Ok(parser) = EvtxParser::load(file) {
                             return Ok(Self {
                                 parser: Parser::Evtx(parser),
+                                extracted: None,
                             });
                         } else if let Ok(parser) = MftParser::load(file) {
                             return Ok(Self {
                                 parser: Parser::Mft(parser),
+                                extracted: None,
                             });
                         } else if let Ok(parser) = JsonParser::load(file) {
                             return Ok(Self {
                                 parser: Parser::Json(parser),
+                                extracted: None,
+                            });
+                        } else if let Ok(parser) = JsonlParser::load(file, skip_errors) {
+                            return Ok(Self {
+                                parser: Parser::Jsonl(parser),
+                                extracted: None,
                             });
                         } else if let Ok(parser) = XmlParser::load(file) {
                             return Ok(Self {
                                 parser: Parser::Xml(parser),
+                                extracted: None,
                             });
                         }
                         if skip_errors {
@@ -250,6 +298,7 @@ impl Reader {
                             );
                             Ok(Self {
                                 parser: Parser::Unknown,
+                                extracted: None,
                             })
                         } else {
                             anyhow::bail!(
@@ -260,6 +309,7 @@ impl Reader {
                     } else {
                         Ok(Self {
                             parser: Parser::Unknown,
+                            extracted: None,
                         })
                     }
                 }
@@ -269,30 +319,39 @@ impl Reader {
                     if let Ok(parser) = EvtxParser::load(file) {
                         return Ok(Self {
                             parser: Parser::Evtx(parser),
+                            extracted: None,
                         });
                     } else if let Ok(parser) = MftParser::load(file) {
                         return Ok(Self {
                             parser: Parser::Mft(parser),
+                            extracted: None,
                         });
                     } else if let Ok(parser) = JsonParser::load(file) {
                         return Ok(Self {
                             parser: Parser::Json(parser),
+                            extracted: None,
+                        });
+                    } else if let Ok(parser) = JsonlParser::load(file, skip_errors) {
+                        return Ok(Self {
+                            parser: Parser::Jsonl(parser),
+                            extracted: None,
                         });
                     } else if let Ok(parser) = XmlParser::load(file) {
                         return Ok(Self {
                             parser: Parser::Xml(parser),
+                            extracted: None,
                         });
                     } else if let Ok(parser) = HveParser::load(file) {
                         return Ok(Self {
                             parser: Parser::Hve(parser),
+                            extracted: None,
                         });
                     }
-                    // NOTE: We don't support the JSONL parser as it is too generic, maybe we are
-                    // happy to use it as the fallback...?
                     if skip_errors {
                         cs_eyellowln!("[!] file type is not known - {}\n", file.display());
                         Ok(Self {
                             parser: Parser::Unknown,
+                            extracted: None,
                         })
                     } else {
                         anyhow::bail!(
@@ -303,6 +362,7 @@ impl Reader {
                 } else {
                     Ok(Self {
                         parser: Parser::Unknown,
+                        extracted: None,
                     })
                 }
             }
@@ -310,7 +370,25 @@ impl Reader {
     }
 
     pub fn documents<'a>(&'a mut self) -> Documents<'a> {
-        let iterator = match &mut self.parser {
+        Documents {
+            iterator: self.parser.documents(),
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.parser.kind()
+    }
+}
+
+impl Parser {
+    fn documents<'a>(
+        &'a mut self,
+    ) -> Box<dyn Iterator<Item = crate::Result<Document>> + Send + Sync + 'a> {
+        match self {
+            Parser::Cached(parser) => Box::new(parser.parse())
+                as Box<dyn Iterator<Item = crate::Result<Document>> + Send + Sync + 'a>,
+            Parser::Caching(parser, writer) => Box::new(writer.tee(parser.documents()))
+                as Box<dyn Iterator<Item = crate::Result<Document>> + Send + Sync + 'a>,
             Parser::Evtx(parser) => Box::new(
                 parser
                     .parse()
@@ -329,12 +407,13 @@ impl Reader {
                 as Box<dyn Iterator<Item = crate::Result<Document>> + Send + Sync + 'a>,
             Parser::Unknown => Box::new(Unknown)
                 as Box<dyn Iterator<Item = crate::Result<Document>> + Send + Sync + 'a>,
-        };
-        Documents { iterator }
+        }
     }
 
-    pub fn kind(&self) -> Kind {
-        match self.parser {
+    fn kind(&self) -> Kind {
+        match self {
+            Parser::Cached(parser) => parser.kind.clone(),
+            Parser::Caching(parser, _) => parser.kind(),
             Parser::Evtx(_) => Kind::Evtx,
             Parser::Hve(_) => Kind::Hve,
             Parser::Json(_) => Kind::Json,
@@ -346,12 +425,63 @@ impl Reader {
     }
 }
 
+/// The size of a file, where files within archives are measured once decompressed.
+pub fn file_size(path: &Path) -> crate::Result<u64> {
+    match archive::split(path) {
+        Some((archive, name)) => archive::size(archive, &name),
+        None => Ok(path.metadata()?.len()),
+    }
+}
+
 pub fn get_files(
     path: &PathBuf,
     extensions: &Option<HashSet<String>>,
+    follow_symlinks: bool,
+    skip_errors: bool,
+    depth: Option<usize>,
+) -> crate::Result<Vec<PathBuf>> {
+    let mut visited = HashSet::new();
+    walk_files(
+        path,
+        extensions,
+        follow_symlinks,
+        skip_errors,
+        depth,
+        true,
+        &mut visited,
+    )
+}
+
+fn walk_files(
+    path: &PathBuf,
+    extensions: &Option<HashSet<String>>,
+    follow_symlinks: bool,
     skip_errors: bool,
+    depth: Option<usize>,
+    root: bool,
+    visited: &mut HashSet<PathBuf>,
 ) -> crate::Result<Vec<PathBuf>> {
     let mut files: Vec<PathBuf> = vec![];
+    // NOTE: Paths provided by the user are always resolved, but symlinks found while walking
+    // directories are only followed when asked to, as mounted images can contain links that
+    // point outside of the collection.
+    if !root && !follow_symlinks {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) => {
+                if metadata.file_type().is_symlink() {
+                    return Ok(files);
+                }
+            }
+            Err(e) => {
+                if skip_errors {
+                    cs_eyellowln!("[!] failed to get metadata for file - {}", e);
+                    return Ok(files);
+                } else {
+                    anyhow::bail!(e);
+                }
+            }
+        }
+    }
     if path.exists() {
         let metadata = match fs::metadata(path) {
             Ok(metadata) => metadata,
@@ -365,6 +495,27 @@ pub fn get_files(
             }
         };
         if metadata.is_dir() {
+            // NOTE: The depth is the number of directory levels below the provided path that we
+            // are allowed to read from, so a depth of 1 only loads the files directly within it.
+            if depth == Some(0) {
+                return Ok(files);
+            }
+            // Keep track of the directories that we have entered so that cyclic symlinks don't
+            // cause us to walk forever.
+            let canonical = match fs::canonicalize(path) {
+                Ok(canonical) => canonical,
+                Err(e) => {
+                    if skip_errors {
+                        cs_eyellowln!("[!] failed to resolve directory - {}", e);
+                        return Ok(files);
+                    } else {
+                        anyhow::bail!(e);
+                    }
+                }
+            };
+            if !visited.insert(canonical) {
+                return Ok(files);
+            }
             let directory = match path.read_dir() {
                 Ok(directory) => directory,
                 Err(e) => {
@@ -388,7 +539,26 @@ pub fn get_files(
                         }
                     }
                 };
-                files.extend(get_files(&dir.path(), extensions, skip_errors)?);
+                files.extend(walk_files(
+                    &dir.path(),
+                    extensions,
+                    follow_symlinks,
+                    skip_errors,
+                    depth.map(|d| d - 1),
+                    false,
+                    visited,
+                )?);
+            }
+        } else if archive::is_archive(path) {
+            match archive::entries(path, extensions) {
+                Ok(entries) => files.extend(entries),
+                Err(e) => {
+                    if skip_errors {
+                        cs_eyellowln!("[!] failed to read archive - {}", e);
+                    } else {
+                        anyhow::bail!(e);
+                    }
+                }
             }
         } else if let Some(e) = extensions {
             if let Some(ext) = path.extension() {
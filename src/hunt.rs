@@ -1,17 +1,20 @@
 use std::borrow::Cow;
 use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs;
 use std::hash::{BuildHasherDefault, Hash, Hasher};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Mutex;
 
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
 use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
-use chrono_tz::Tz;
 // https://github.com/rust-lang/rust/issues/74465
 use once_cell::sync::OnceCell;
 use rayon::prelude::*;
-use rustc_hash::{FxHashMap, FxHasher};
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
 use serde::{
     ser::{SerializeStruct, Serializer},
     Deserialize, Serialize,
@@ -24,10 +27,11 @@ use tau_engine::{
 };
 use uuid::Uuid;
 
-use crate::file::{Document as File, Kind as FileKind, Reader};
+use crate::ext::timezone::Timezone;
+use crate::file::{cache::Cache, Document as File, Kind as FileKind, Reader};
 use crate::rule::{
     chainsaw::{Container, Field, Format},
-    Aggregate, Filter, Kind as RuleKind, Rule,
+    Aggregate, Filter, Kind as RuleKind, Level, Rule,
 };
 use crate::value::Value;
 
@@ -39,7 +43,7 @@ pub struct Precondition {
     pub filter: Expression,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Default, Deserialize)]
 pub struct Extensions {
     #[serde(default)]
     preconditions: Option<Vec<Precondition>>,
@@ -52,10 +56,32 @@ pub struct Group {
     pub fields: Vec<Field>,
     #[serde(deserialize_with = "crate::ext::tau::deserialize_expression")]
     pub filter: Expression,
+    /// When set, the group only applies to the sigma rules whose logsource has these values.
+    #[serde(default)]
+    pub logsource: Option<HashMap<String, String>>,
     pub name: String,
     pub timestamp: String,
 }
 
+impl Group {
+    fn selects(&self, rule: &Rule) -> bool {
+        match (&self.logsource, rule) {
+            (Some(logsource), Rule::Sigma(sigma)) => logsource
+                .iter()
+                .all(|(k, v)| sigma_has(sigma, &format!("logsource.{}", k), v)),
+            _ => true,
+        }
+    }
+}
+
+/// Whether the sigma rule field holds the value.
+fn sigma_has(sigma: &crate::rule::sigma::Rule, field: &str, value: &str) -> bool {
+    match sigma.find(field) {
+        Some(v) => v.as_str() == Some(value),
+        None => false,
+    }
+}
+
 #[derive(Deserialize)]
 pub struct Mapping {
     #[serde(default)]
@@ -68,26 +94,220 @@ pub struct Mapping {
     pub rules: RuleKind,
 }
 
+impl Mapping {
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let mut file = match fs::File::open(path) {
+            Ok(a) => a,
+            Err(e) => anyhow::bail!("Error loading specified mapping file - {}", e),
+        };
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let mapping: Mapping = match serde_yaml::from_str(&content) {
+            Ok(a) => a,
+            Err(e) => anyhow::bail!("Provided mapping file is invalid - {}", e),
+        };
+        if let RuleKind::Chainsaw = mapping.rules {
+            anyhow::bail!("Chainsaw rules do not support mappings");
+        }
+        if let RuleKind::Keyword = mapping.rules {
+            anyhow::bail!("Keyword rules do not support mappings");
+        }
+        // NOTE: This also applies when hunting, where these mappings used to reach an
+        // `unreachable!` when building the hunts rather than failing with an error.
+        if let FileKind::Unknown = mapping.kind {
+            anyhow::bail!("Mappings must be for a known kind of file");
+        }
+        Ok(mapping)
+    }
+
+    /// Problems with the mapping that do not stop it from loading, but are likely to be mistakes.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        let mut groups = HashSet::new();
+        for group in &self.groups {
+            if !groups.insert(&group.name) {
+                warnings.push(format!("group '{}' is defined more than once", group.name));
+            }
+            let mut logsource = group.logsource.iter().flatten().collect::<Vec<_>>();
+            logsource.sort();
+            for (key, _) in logsource {
+                if !crate::rule::sigma::FIELDS.contains(&format!("logsource.{}", key).as_str()) {
+                    warnings.push(format!(
+                        "group '{}' selects on logsource '{}' which is not a sigma logsource field",
+                        group.name, key
+                    ));
+                }
+            }
+            let mut names = HashSet::new();
+            let mut froms = HashSet::new();
+            for field in &group.fields {
+                if !names.insert(&field.name) {
+                    warnings.push(format!(
+                        "field '{}' is defined more than once in group '{}'",
+                        field.name, group.name
+                    ));
+                }
+                if !froms.insert(&field.from) {
+                    warnings.push(format!(
+                        "rule field '{}' is mapped more than once in group '{}'",
+                        field.from, group.name
+                    ));
+                }
+            }
+        }
+        let preconditions = self
+            .extensions
+            .as_ref()
+            .and_then(|e| e.preconditions.as_ref());
+        for precondition in preconditions.into_iter().flatten() {
+            // NOTE: The hunter skips these rather than applying them to every rule.
+            if precondition.for_.is_empty() {
+                warnings.push("precondition has no 'for' fields so is never applied".to_owned());
+            }
+            let mut fields = precondition.for_.keys().collect::<Vec<_>>();
+            fields.sort();
+            for field in fields {
+                if !crate::rule::sigma::FIELDS.contains(&field.as_str()) {
+                    warnings.push(format!(
+                        "precondition references '{}' which is not a supported sigma rule field",
+                        field
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+
+    /// Merges another mapping into this one, with the other mapping winning on any collisions.
+    fn merge(&mut self, other: Mapping) {
+        self.exclusions.extend(other.exclusions);
+        if let Some(preconditions) = other.extensions.and_then(|e| e.preconditions) {
+            // NOTE: Later preconditions take precedence as they are applied in order.
+            self.extensions
+                .get_or_insert_with(Extensions::default)
+                .preconditions
+                .get_or_insert_with(Vec::new)
+                .extend(preconditions);
+        }
+        for group in other.groups {
+            match self.groups.iter_mut().find(|g| g.name == group.name) {
+                Some(existing) => {
+                    for field in group.fields {
+                        match existing.fields.iter_mut().find(|f| f.name == field.name) {
+                            Some(f) => *f = field,
+                            None => existing.fields.push(field),
+                        }
+                    }
+                    existing.filter = group.filter;
+                    existing.logsource = group.logsource;
+                    existing.timestamp = group.timestamp;
+                }
+                None => self.groups.push(group),
+            }
+        }
+    }
+}
+
+/// Loads a mapping file, returning any problems that are likely to be mistakes.
+pub fn lint(path: &Path) -> crate::Result<Vec<String>> {
+    Ok(Mapping::load(path)?.warnings())
+}
+
+/// A rule that matched, as part of a [`Detections`].
 pub struct Hit {
+    /// The id of the hunt that found the hit, see [`Hunter::hunts`].
     pub hunt: Uuid,
+    /// The id of the rule that matched, see [`Hunter::rules`].
     pub rule: Uuid,
+    /// The timestamp of the matched document, in UTC unless hunting with a timezone.
     pub timestamp: NaiveDateTime,
+    /// The field values that caused the rule to match, only set when hunting with
+    /// [`HunterBuilder::matches`] and always empty for keywords and aggregates.
+    pub matched: BTreeMap<String, String>,
+    /// The rule's logic annotated with which parts were true for the document, only set when
+    /// hunting with [`HunterBuilder::explain`].
+    pub explanation: Option<String>,
+}
+
+/// The metadata of the rule behind a hit, joined from the hunt and rule that it references, see
+/// [`Hunter::metadata`].
+#[derive(Debug, Serialize)]
+pub struct RuleMetadata<'a> {
+    /// The group of the hunt, which is the mapping group for Sigma rules.
+    pub group: &'a String,
+    /// The id of the rule, only Sigma rules have one.
+    pub id: Option<&'a String>,
+    pub level: &'a Level,
+    pub name: &'a String,
+    pub source: RuleKind,
+    pub tags: &'a [String],
+}
+
+impl<'a> RuleMetadata<'a> {
+    pub fn new(hunt: &'a Hunt, rule: &'a Rule) -> Self {
+        Self {
+            group: &hunt.group,
+            id: rule.id(),
+            level: rule.level(),
+            name: rule.name(),
+            source: rule.kind(),
+            tags: rule.tags(),
+        }
+    }
 }
 
+/// The hits for a single document, or a group of documents for aggregate rules, as returned by
+/// [`Hunter::hunt`].
+///
+/// ```no_run
+/// use std::path::Path;
+///
+/// use chainsaw::{load, DetectionKind, Hunter, RuleKind};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let rules = load(RuleKind::Chainsaw, Path::new("rule.yml"), &None, &None, &None)?;
+/// let hunter = Hunter::builder().rules(rules).build()?;
+/// for detections in hunter.hunt(Path::new("Security.evtx"), &None)? {
+///     for hit in &detections.hits {
+///         let metadata = hunter.metadata(hit).expect("could not get rule");
+///         println!("{} - {} ({})", hit.timestamp, metadata.name, metadata.level);
+///     }
+///     if let DetectionKind::Individual { document } = &detections.kind {
+///         println!("{}", document.to_json()?);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
 pub struct Detections<'a> {
+    /// The rules that matched.
     pub hits: SmallVec<[Hit; 1]>,
+    /// The documents that were matched.
     pub kind: Kind<'a>,
+    // NOTE: The timezone read from the document itself, when hunting with a timezone field.
+    pub timezone: Option<Timezone>,
 }
 
+/// A document that was matched by a hunt.
 //#[derive(Debug, Serialize)]
 #[derive(Debug)]
 pub struct Document<'a> {
+    /// The kind of artefact that the document was read from.
     pub kind: FileKind,
+    /// The path of the artefact that the document was read from.
     pub path: &'a Path,
     // NOTE: Serialised Value using bincode.
     pub data: Vec<u8>,
 }
 
+impl<'a> Document<'a> {
+    /// The matched document, decoded from its serialised form.
+    pub fn to_json(&self) -> crate::Result<Json> {
+        let value: Value = bincode::deserialize(&self.data)?;
+        Ok(Json::from(value))
+    }
+}
+
 impl<'a> Serialize for Document<'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -112,6 +332,7 @@ pub struct RawDocument<'a> {
     pub data: Option<&'a RawValue>,
 }
 
+/// How the matched documents are held, which depends on the rule and whether hunting with a cache.
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "snake_case", tag = "kind")]
 pub enum Kind<'a> {
@@ -130,17 +351,72 @@ pub enum Kind<'a> {
     },
 }
 
+impl<'a> Kind<'a> {
+    /// The path of the artefact that the documents were read from.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Aggregate { documents } => {
+                documents.first().map(|d| d.path).unwrap_or(Path::new(""))
+            }
+            Self::Individual { document } => document.path,
+            Self::Cached { document, .. } => document.path,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub enum Sort {
+    File,
+    Rule,
+    #[default]
+    Timestamp,
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File => write!(f, "file"),
+            Self::Rule => write!(f, "rule"),
+            Self::Timestamp => write!(f, "timestamp"),
+        }
+    }
+}
+
+impl FromStr for Sort {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let v = match s {
+            "file" => Self::File,
+            "rule" => Self::Rule,
+            "timestamp" => Self::Timestamp,
+            _ => anyhow::bail!("unknown sort, must be: file, rule or timestamp"),
+        };
+        Ok(v)
+    }
+}
+
 #[derive(Default)]
 pub struct HunterBuilder {
     mappings: Option<Vec<PathBuf>>,
     rules: Option<Vec<Rule>>,
 
+    cache_parsed: Option<PathBuf>,
+    channels: Option<Vec<String>>,
+    explain: Option<bool>,
+    ignore_records: Option<HashMap<String, HashSet<u64>>>,
+    keyword_timestamp: Option<String>,
     load_unknown: Option<bool>,
     local: Option<bool>,
+    matches: Option<bool>,
+    merge_repeated_data: Option<bool>,
     preprocess: Option<bool>,
     from: Option<NaiveDateTime>,
+    redact: Option<Vec<String>>,
+    redact_key: Option<String>,
     skip_errors: Option<bool>,
-    timezone: Option<Tz>,
+    timezone: Option<Timezone>,
+    timezone_field: Option<String>,
     to: Option<NaiveDateTime>,
 }
 
@@ -151,12 +427,16 @@ impl HunterBuilder {
 
     pub fn build(self) -> crate::Result<Hunter> {
         let mut hunts = vec![];
+        let mut keywords = vec![];
         let mut rules = match self.rules {
             Some(mut rules) => {
                 rules.sort_by(|x, y| x.name().cmp(y.name()));
                 let mut map = BTreeMap::new();
                 for rule in rules {
                     let uuid = Uuid::new_v4();
+                    if let Rule::Keyword(rule) = &rule {
+                        keywords.push((uuid, rule.keyword.clone()));
+                    }
                     if let Rule::Chainsaw(rule) = &rule {
                         let mapper = Mapper::from(rule.fields.clone());
                         hunts.push(Hunt {
@@ -179,22 +459,94 @@ impl HunterBuilder {
             }
             None => BTreeMap::new(),
         };
-        if let Some(mut mappings) = self.mappings {
-            mappings.sort();
-            for mapping in mappings {
-                let mut file = match fs::File::open(mapping) {
-                    Ok(a) => a,
-                    Err(e) => anyhow::bail!("Error loading specified mapping file - {}", e),
-                };
-                let mut content = String::new();
-                file.read_to_string(&mut content)?;
-                let mut mapping: Mapping = match serde_yaml::from_str(&content) {
-                    Ok(a) => a,
-                    Err(e) => anyhow::bail!("Provided mapping file is invalid - {}", e),
-                };
-                if let RuleKind::Chainsaw = mapping.rules {
-                    anyhow::bail!("Chainsaw rules do not support mappings");
+        if !keywords.is_empty() {
+            // NOTE: All keywords are searched for in a single pass over the document, where each
+            // matched pattern is then resolved back to its rule.
+            let matcher = AhoCorasickBuilder::new()
+                .ascii_case_insensitive(true)
+                .build(keywords.iter().map(|(_, k)| k))?;
+            let rules: Vec<Uuid> = keywords.into_iter().map(|(id, _)| id).collect();
+            // NOTE: Keywords match against any field so they are hunted for in every kind of
+            // document, but each hit still needs a timestamp. We only know where to find that in
+            // event logs and MFTs, so the other kinds are only hunted when given the field to use.
+            let mut kinds = vec![
+                (
+                    FileKind::Evtx,
+                    "Event.System.TimeCreated".to_owned(),
+                    vec![
+                        ("Event ID", "Event.System.EventID"),
+                        ("Record ID", "Event.System.EventRecordID"),
+                        ("Computer", "Event.System.Computer"),
+                    ],
+                ),
+                (
+                    FileKind::Mft,
+                    "FileNameCreated".to_owned(),
+                    vec![("Full Path", "FullPath")],
+                ),
+            ];
+            if let Some(timestamp) = &self.keyword_timestamp {
+                for kind in [FileKind::Hve, FileKind::Json, FileKind::Xml] {
+                    kinds.push((kind, timestamp.clone(), vec![]));
                 }
+            }
+            for (kind, timestamp, fields) in kinds {
+                let fields = fields
+                    .into_iter()
+                    .map(|(name, from)| Field {
+                        name: name.to_owned(),
+                        from: from.to_owned(),
+                        to: from.to_owned(),
+
+                        cast: None,
+                        container: None,
+                        visible: true,
+                    })
+                    .collect();
+                hunts.push(Hunt {
+                    id: Uuid::new_v4(),
+
+                    group: "Keywords".to_owned(),
+                    kind: HuntKind::Keywords {
+                        matcher: matcher.clone(),
+                        rules: rules.clone(),
+                    },
+                    timestamp,
+
+                    file: kind,
+                    mapper: Mapper::from(fields),
+                });
+            }
+        }
+        if let Some(paths) = self.mappings {
+            // NOTE: Mappings for the same kind of file and rules are merged in the order they were
+            // provided, so that the last mapping wins on any collision.
+            let count = paths.len();
+            let mut mappings: Vec<Mapping> = vec![];
+            for path in paths {
+                let mapping = Mapping::load(&path)?;
+                match mappings
+                    .iter_mut()
+                    .find(|m| m.kind == mapping.kind && m.rules == mapping.rules)
+                {
+                    Some(existing) => existing.merge(mapping),
+                    None => mappings.push(mapping),
+                }
+            }
+            if count > 1 {
+                let fields: usize = mappings
+                    .iter()
+                    .flat_map(|m| &m.groups)
+                    .map(|g| g.fields.len())
+                    .sum();
+                cs_eprintln!(
+                    "[+] Merged {} mapping files into {} mappings with {} fields",
+                    count,
+                    mappings.len(),
+                    fields
+                );
+            }
+            for mut mapping in mappings {
                 let mut preconds = FxHashMap::default();
                 if let Some(extensions) = &mapping.extensions {
                     if let Some(preconditions) = &extensions.preconditions {
@@ -206,21 +558,10 @@ impl HunterBuilder {
                                     if precondition.for_.is_empty() {
                                         continue;
                                     }
-                                    let mut matched = true;
-                                    for (f, v) in &precondition.for_ {
-                                        match sigma.find(f) {
-                                            Some(value) => {
-                                                if value.as_str() != Some(v.as_str()) {
-                                                    matched = false;
-                                                    break;
-                                                }
-                                            }
-                                            None => {
-                                                matched = false;
-                                                break;
-                                            }
-                                        }
-                                    }
+                                    let matched = precondition
+                                        .for_
+                                        .iter()
+                                        .all(|(f, v)| sigma_has(sigma, f, v));
                                     if matched {
                                         preconds.insert(*rid, precondition.filter.clone());
                                     }
@@ -233,7 +574,9 @@ impl HunterBuilder {
                 for group in mapping.groups {
                     let mut exclusions = HashSet::<Uuid, BuildHasherDefault<FxHasher>>::default();
                     for (rid, rule) in &rules {
-                        if mapping.exclusions.contains(rule.name()) {
+                        // NOTE: Rules outside of the group's logsource are excluded, so that one
+                        // mapping can hold a group per logsource.
+                        if mapping.exclusions.contains(rule.name()) || !group.selects(rule) {
                             exclusions.insert(*rid);
                         }
                     }
@@ -269,6 +612,18 @@ impl HunterBuilder {
             }
         }
 
+        // NOTE: Channels prefixed with '!' are excluded, the rest are the only ones included.
+        let mut channels = (FxHashSet::default(), FxHashSet::default());
+        for channel in self.channels.unwrap_or_default() {
+            match channel.strip_prefix('!') {
+                Some(channel) => channels.1.insert(channel.to_lowercase()),
+                None => channels.0.insert(channel.to_lowercase()),
+            };
+        }
+        let cache_parsed = match self.cache_parsed {
+            Some(directory) => Some(Cache::new(directory)?),
+            None => None,
+        };
         let load_unknown = self.load_unknown.unwrap_or_default();
         let local = self.local.unwrap_or_default();
         let preprocess = self.preprocess.unwrap_or_default();
@@ -303,6 +658,7 @@ impl HunterBuilder {
                             keys.extend(crate::ext::tau::extract_fields(precondition));
                         }
                     }
+                    HuntKind::Keywords { .. } => {}
                 }
             }
             for rule in rules.values() {
@@ -320,6 +676,7 @@ impl HunterBuilder {
                             }
                         }
                     }
+                    Rule::Keyword(_) => {}
                     Rule::Sigma(s) => {
                         if let Some(a) = &s.aggregate {
                             keys.extend(a.fields.iter().cloned());
@@ -388,6 +745,9 @@ impl HunterBuilder {
                                 .map(|(i, p)| (i, crate::ext::tau::update_fields(p, &lookup)))
                                 .collect(),
                         },
+                        HuntKind::Keywords { matcher, rules } => {
+                            HuntKind::Keywords { matcher, rules }
+                        }
                     };
                     h
                 })
@@ -417,6 +777,7 @@ impl HunterBuilder {
                             };
                             Rule::Chainsaw(c)
                         }
+                        Rule::Keyword(k) => Rule::Keyword(k),
                         Rule::Sigma(mut s) => {
                             if let Some(mut a) = s.aggregate.as_mut() {
                                 a.fields = a
@@ -442,22 +803,65 @@ impl HunterBuilder {
                 fields,
                 rules,
 
+                cache_parsed,
+                channels,
+                explain: self.explain.unwrap_or_default(),
                 from: self.from.map(|d| DateTime::from_utc(d, Utc)),
+                ignore_records: self.ignore_records.unwrap_or_default(),
                 load_unknown,
                 local,
+                matches: self.matches.unwrap_or_default(),
+                merge_repeated_data: self.merge_repeated_data.unwrap_or_default(),
                 preprocess,
+                redact: self.redact.unwrap_or_default(),
+                redact_key: self
+                    .redact_key
+                    .unwrap_or_else(crate::ext::redact::generate_key)
+                    .into_bytes(),
                 skip_errors,
                 timezone: self.timezone,
+                timezone_field: self.timezone_field,
                 to: self.to.map(|d| DateTime::from_utc(d, Utc)),
             },
         })
     }
 
+    /// A directory to reuse parsed documents from, and to write them to for artefacts that have not
+    /// been parsed before, see [`Cache`].
+    pub fn cache_parsed(mut self, directory: PathBuf) -> Self {
+        self.cache_parsed = Some(directory);
+        self
+    }
+
+    pub fn channels(mut self, channels: Vec<String>) -> Self {
+        self.channels = Some(channels);
+        self
+    }
+
+    /// Include a trace of why each rule matched in the hits, see [`Hit::explanation`].
+    pub fn explain(mut self, explain: bool) -> Self {
+        self.explain = Some(explain);
+        self
+    }
+
     pub fn from(mut self, datetime: NaiveDateTime) -> Self {
         self.from = Some(datetime);
         self
     }
 
+    /// Event log records to skip, keyed by either the path of the artefact or its file name.
+    pub fn ignore_records(mut self, records: HashMap<String, HashSet<u64>>) -> Self {
+        self.ignore_records = Some(records);
+        self
+    }
+
+    /// The field holding the timestamp of json, xml and registry hive documents, without which
+    /// keywords are only hunted for in event logs and MFTs.
+    pub fn keyword_timestamp(mut self, field: String) -> Self {
+        self.keyword_timestamp = Some(field);
+        self
+    }
+
     pub fn load_unknown(mut self, allow: bool) -> Self {
         self.load_unknown = Some(allow);
         self
@@ -468,11 +872,37 @@ impl HunterBuilder {
         self
     }
 
+    /// Include the field values that caused each rule to match in the hits, see [`Hit::matched`].
+    pub fn matches(mut self, matches: bool) -> Self {
+        self.matches = Some(matches);
+        self
+    }
+
+    /// Collect the event log data values that repeat a name into an array under that name, see
+    /// [`crate::evtx::merge_repeated_data`].
+    pub fn merge_repeated_data(mut self, merge: bool) -> Self {
+        self.merge_repeated_data = Some(merge);
+        self
+    }
+
     pub fn preprocess(mut self, preprocess: bool) -> Self {
         self.preprocess = Some(preprocess);
         self
     }
 
+    /// Fields to replace with a keyed hash in the matched documents, this is applied after
+    /// matching so does not affect the detections.
+    pub fn redact(mut self, fields: Vec<String>) -> Self {
+        self.redact = Some(fields);
+        self
+    }
+
+    /// The key to hash the redacted values with, a random one is used when this is not set.
+    pub fn redact_key(mut self, key: String) -> Self {
+        self.redact_key = Some(key);
+        self
+    }
+
     pub fn mappings(mut self, paths: Vec<PathBuf>) -> Self {
         self.mappings = Some(paths);
         self
@@ -488,11 +918,16 @@ impl HunterBuilder {
         self
     }
 
-    pub fn timezone(mut self, tz: Tz) -> Self {
+    pub fn timezone(mut self, tz: Timezone) -> Self {
         self.timezone = Some(tz);
         self
     }
 
+    pub fn timezone_field(mut self, field: String) -> Self {
+        self.timezone_field = Some(field);
+        self
+    }
+
     pub fn to(mut self, datetime: NaiveDateTime) -> Self {
         self.to = Some(datetime);
         self
@@ -506,6 +941,10 @@ pub enum HuntKind {
         kind: RuleKind,
         preconditions: FxHashMap<Uuid, Expression>,
     },
+    Keywords {
+        matcher: AhoCorasick,
+        rules: Vec<Uuid>,
+    },
     Rule {
         aggregate: Option<Aggregate>,
         filter: Filter,
@@ -643,6 +1082,8 @@ impl<'a> TauDocument for Mapped<'a> {
                         res => res,
                     },
                     ModSym::Str => match self.document.find(v) {
+                        // NOTE: Arrays are left alone so that tau can match them element-wise.
+                        Some(Tau::Array(a)) => Some(Tau::Array(a)),
                         Some(value) => value.to_string().map(|s| Tau::String(Cow::Owned(s))),
                         res => res,
                     },
@@ -685,6 +1126,7 @@ impl Hunt {
     pub fn is_aggregation(&self) -> bool {
         match &self.kind {
             HuntKind::Group { .. } => true,
+            HuntKind::Keywords { .. } => false,
             HuntKind::Rule { aggregate, .. } => aggregate.is_some(),
         }
     }
@@ -695,12 +1137,21 @@ pub struct HunterInner {
     fields: Vec<String>,
     rules: BTreeMap<Uuid, Rule>,
 
+    cache_parsed: Option<Cache>,
+    channels: (FxHashSet<String>, FxHashSet<String>),
+    explain: bool,
+    ignore_records: HashMap<String, HashSet<u64>>,
     load_unknown: bool,
     local: bool,
+    matches: bool,
+    merge_repeated_data: bool,
     preprocess: bool,
     from: Option<DateTime<Utc>>,
+    redact: Vec<String>,
+    redact_key: Vec<u8>,
     skip_errors: bool,
-    timezone: Option<Tz>,
+    timezone: Option<Timezone>,
+    timezone_field: Option<String>,
     to: Option<DateTime<Utc>>,
 }
 
@@ -718,17 +1169,57 @@ impl Hunter {
         file: &'a Path,
         cache: &Option<std::fs::File>,
     ) -> crate::Result<Vec<Detections>> {
-        let mut reader = Reader::load(file, self.inner.load_unknown, self.inner.skip_errors)?;
+        self.hunt_since(file, cache, &mut None)
+    }
+
+    /// Hunts through the file skipping any event log records at or below the record id provided,
+    /// which is then updated to the highest record id seen so that the next hunt can pick up from
+    /// where this one left off.
+    pub fn hunt_since<'a>(
+        &'a self,
+        file: &'a Path,
+        cache: &Option<std::fs::File>,
+        record: &mut Option<u64>,
+    ) -> crate::Result<Vec<Detections>> {
+        self.hunt_cancellable(file, cache, record, &AtomicBool::new(false))
+    }
+
+    /// Hunts through the file as [`Hunter::hunt_since`] does, but gives up with an error once the
+    /// flag is set, so that a hunt which is no longer wanted stops at the next document.
+    pub fn hunt_cancellable<'a>(
+        &'a self,
+        file: &'a Path,
+        cache: &Option<std::fs::File>,
+        record: &mut Option<u64>,
+        cancelled: &AtomicBool,
+    ) -> crate::Result<Vec<Detections>> {
+        let since = *record;
+        let ignored = self
+            .inner
+            .ignore_records
+            .get(file.to_string_lossy().as_ref())
+            .or_else(|| {
+                let name = file.file_name()?.to_string_lossy();
+                self.inner.ignore_records.get(name.as_ref())
+            });
+        let highest: Mutex<Option<u64>> = Mutex::new(since);
+        let mut reader = match &self.inner.cache_parsed {
+            Some(cache) => cache.load(file, self.inner.load_unknown, self.inner.skip_errors)?,
+            None => Reader::load(file, self.inner.load_unknown, self.inner.skip_errors)?,
+        };
         let kind = reader.kind();
         let aggregates: Mutex<FxHashMap<(Uuid, Uuid), (&Aggregate, FxHashMap<u64, Vec<Uuid>>)>> =
             Mutex::new(FxHashMap::default());
-        let files: Mutex<FxHashMap<Uuid, (Value, NaiveDateTime)>> =
+        let files: Mutex<FxHashMap<Uuid, (Value, NaiveDateTime, Option<Timezone>)>> =
             Mutex::new(FxHashMap::default());
         let offset = Mutex::new(0);
         let mut detections = reader
             .documents()
             .par_bridge()
             .filter_map(|document| {
+                if cancelled.load(Ordering::Relaxed) {
+                    return Some(Err(anyhow!("cancelled hunting through {}", file.display())));
+                }
                 let document_id = Uuid::new_v4();
                 let document = match document {
                     Ok(document) => document,
@@ -744,13 +1235,35 @@ impl Hunter {
                         return Some(Err(anyhow!(format!("{} in {}", e, file.display()))));
                     }
                 };
+                if let File::Evtx(evtx) = &document {
+                    let id = evtx.event_record_id;
+                    if since.map(|s| id <= s).unwrap_or_default() {
+                        return None;
+                    }
+                    {
+                        let mut highest = highest.lock().expect("could not lock highest");
+                        *highest = Some(highest.map(|h| h.max(id)).unwrap_or(id));
+                    }
+                    if ignored.map(|i| i.contains(&id)).unwrap_or_default() {
+                        return None;
+                    }
+                }
                 let (kind, value): (FileKind, Value) = match document {
-                    File::Evtx(evtx) => (FileKind::Evtx, evtx.data.into()),
+                    File::Evtx(mut evtx) => {
+                        if self.inner.merge_repeated_data {
+                            crate::evtx::merge_repeated_data(&mut evtx.data);
+                        }
+                        (FileKind::Evtx, evtx.data.into())
+                    }
                     File::Hve(hve) => (FileKind::Hve, hve.into()),
                     File::Json(json) => (FileKind::Json, json.into()),
                     File::Mft(mft) => (FileKind::Mft, mft.into()),
                     File::Xml(xml) => (FileKind::Xml, xml.into()),
                 };
+                if kind == FileKind::Evtx && !self.allowed_channel(&value) {
+                    return None;
+                }
+                let timezone = self.document_timezone(&kind, &value);
                 let mut hits = smallvec::smallvec![];
                 for hunt in &self.inner.hunts {
                     if hunt.file != kind {
@@ -812,7 +1325,7 @@ impl Hunter {
                         None => continue,
                     };
 
-                    if self.skip(timestamp).ok()? {
+                    if self.skip(timestamp, timezone).ok()? {
                         continue;
                     }
 
@@ -849,7 +1362,10 @@ impl Hunter {
                                 for (rid, rule) in matches {
                                     if let Some(aggregate) = &rule.aggregate() {
                                         let mut files = files.lock().expect("could not lock files");
-                                        files.insert(document_id, (value.clone(), timestamp));
+                                        files.insert(
+                                            document_id,
+                                            (value.clone(), timestamp, timezone),
+                                        );
                                         let mut hasher = FxHasher::default();
                                         let mut skip = false;
                                         for field in &aggregate.fields {
@@ -878,11 +1394,36 @@ impl Hunter {
                                             hunt: hunt.id,
                                             rule: *rid,
                                             timestamp,
+                                            matched: if self.inner.matches {
+                                                rule.matches(&mapped)
+                                            } else {
+                                                BTreeMap::new()
+                                            },
+                                            explanation: if self.inner.explain {
+                                                rule.explain(&mapped)
+                                            } else {
+                                                None
+                                            },
                                         });
                                     }
                                 }
                             }
                         }
+                        HuntKind::Keywords { matcher, rules } => {
+                            let mut matches = FxHashSet::default();
+                            find_keywords(matcher, &value, &mut matches);
+                            let mut matches = matches.into_iter().collect::<Vec<_>>();
+                            matches.sort();
+                            for i in matches {
+                                hits.push(Hit {
+                                    hunt: hunt.id,
+                                    rule: rules[i],
+                                    timestamp,
+                                    matched: BTreeMap::new(),
+                                    explanation: None,
+                                });
+                            }
+                        }
                         HuntKind::Rule { aggregate, filter } => {
                             let hit = match &filter {
                                 Filter::Detection(detection) => {
@@ -895,7 +1436,7 @@ impl Hunter {
                             if hit {
                                 if let Some(aggregate) = aggregate {
                                     let mut files = files.lock().expect("could not lock files");
-                                    files.insert(document_id, (value.clone(), timestamp));
+                                    files.insert(document_id, (value.clone(), timestamp, timezone));
                                     let mut hasher = FxHasher::default();
                                     let mut skip = false;
                                     for field in &aggregate.fields {
@@ -924,6 +1465,16 @@ impl Hunter {
                                         hunt: hunt.id,
                                         rule: hunt.id,
                                         timestamp,
+                                        matched: if self.inner.matches {
+                                            filter.matches(&mapped)
+                                        } else {
+                                            BTreeMap::new()
+                                        },
+                                        explanation: if self.inner.explain {
+                                            Some(filter.explain(&mapped))
+                                        } else {
+                                            None
+                                        },
                                     });
                                 }
                             }
@@ -931,6 +1482,7 @@ impl Hunter {
                     }
                 }
                 if !hits.is_empty() {
+                    let value = self.redact(value, &mut hits);
                     if let Some(mut cache) = cache.as_ref() {
                         let mut offset = offset.lock().expect("could not lock offset");
                         let json = serde_json::to_string(&Json::from(value))
@@ -950,6 +1502,7 @@ impl Hunter {
                                 offset: val,
                                 size,
                             },
+                            timezone,
                         }))
                     } else {
                         Some(Ok(Detections {
@@ -961,6 +1514,7 @@ impl Hunter {
                                     data: bincode::serialize(&value).ok()?,
                                 },
                             },
+                            timezone,
                         }))
                     }
                 } else {
@@ -968,6 +1522,7 @@ impl Hunter {
                 }
             })
             .collect::<crate::Result<Vec<Detections>>>()?;
+        *record = highest.into_inner().expect("could not lock highest");
         let aggregates = aggregates.into_inner().expect("could not lock aggregates");
         let files = files.into_inner().expect("could not lock aggregates");
         for ((hid, rid), (aggregate, docs)) in aggregates {
@@ -984,25 +1539,30 @@ impl Hunter {
                     let mut documents = Vec::with_capacity(ids.len());
                     let mut timestamps = Vec::with_capacity(ids.len());
                     for id in ids {
-                        let (value, timestamp) = files.get(id).expect("could not get document");
+                        let (value, timestamp, timezone) =
+                            files.get(id).expect("could not get document");
+                        let value = self.redact(value.clone(), &mut []);
                         documents.push(Document {
                             kind: kind.clone(),
                             path: file,
                             data: bincode::serialize(&value)?,
                         });
-                        timestamps.push(*timestamp);
+                        timestamps.push((*timestamp, *timezone));
                     }
-                    timestamps.sort();
+                    let (timestamp, timezone) = timestamps
+                        .into_iter()
+                        .min_by_key(|(timestamp, _)| *timestamp)
+                        .expect("failed to get timestamp");
                     detections.push(Detections {
                         hits: smallvec::smallvec![Hit {
                             hunt: hid,
                             rule: rid,
-                            timestamp: timestamps
-                                .into_iter()
-                                .next()
-                                .expect("failed to get timestamp"),
+                            timestamp,
+                            matched: BTreeMap::new(),
+                            explanation: None,
                         }],
                         kind: Kind::Aggregate { documents },
+                        timezone,
                     });
                 }
             }
@@ -1012,14 +1572,22 @@ impl Hunter {
 
     pub fn extensions(&self) -> HashSet<String> {
         let mut extensions = HashSet::new();
-        for rule in &self.inner.rules {
-            if let Some(e) = FileKind::extensions(rule.1.types()) {
+        let kinds = self
+            .inner
+            .rules
+            .values()
+            .map(|r| r.types())
+            .chain(self.inner.hunts.iter().map(|h| &h.file));
+        for kind in kinds {
+            if let Some(e) = FileKind::extensions(kind) {
                 extensions.extend(e.iter().cloned());
             }
-        }
-        for hunt in &self.inner.hunts {
-            if let Some(e) = FileKind::extensions(&hunt.file) {
-                extensions.extend(e.iter().cloned());
+            // NOTE: Line delimited JSON is hunted as JSON documents, so we need to make sure that
+            // we pick up those files too.
+            if let FileKind::Json = kind {
+                if let Some(e) = FileKind::extensions(&FileKind::Jsonl) {
+                    extensions.extend(e.iter().cloned());
+                }
             }
         }
         extensions
@@ -1033,10 +1601,86 @@ impl Hunter {
         &self.inner.rules
     }
 
-    fn skip(&self, timestamp: NaiveDateTime) -> crate::Result<bool> {
+    /// The metadata of the rule that produced the hit, or `None` when the hit is not from this
+    /// hunter.
+    pub fn metadata(&self, hit: &Hit) -> Option<RuleMetadata<'_>> {
+        let hunt = self.inner.hunts.iter().find(|h| h.id == hit.hunt)?;
+        let rule = self.inner.rules.get(&hit.rule)?;
+        Some(RuleMetadata::new(hunt, rule))
+    }
+
+    /// Sorts the detections in ascending order by the key provided, ties are broken by the
+    /// timestamp, then the file path and finally the rule name.
+    pub fn sort(&self, detections: &mut [Detections], by: &Sort) {
+        let rules = &self.inner.rules;
+        // NOTE: Detections can contain multiple hits, so we key on the earliest timestamp and the
+        // lowest rule name to keep the ordering stable between runs.
+        let timestamp = |d: &Detections| d.hits.iter().map(|h| h.timestamp).min();
+        let rule = |d: &Detections| {
+            d.hits
+                .iter()
+                .filter_map(|h| rules.get(&h.rule).map(|r| r.name().clone()))
+                .min()
+        };
+        // NOTE: The sorts are stable, so we sort by the tiebreaks first and then by the key.
+        detections.sort_by_cached_key(|d| (timestamp(d), d.kind.path().to_path_buf(), rule(d)));
+        match by {
+            Sort::File => detections.sort_by(|x, y| x.kind.path().cmp(y.kind.path())),
+            Sort::Rule => detections.sort_by_cached_key(rule),
+            Sort::Timestamp => {}
+        }
+    }
+
+    /// Redacts the configured fields in the document and in the values the hits matched on.
+    fn redact(&self, value: Value, hits: &mut [Hit]) -> Value {
+        if self.inner.redact.is_empty() {
+            return value;
+        }
+        for hit in hits {
+            for (field, matched) in hit.matched.iter_mut() {
+                if self.inner.redact.contains(field) {
+                    *matched = crate::ext::redact::redact_str(matched, &self.inner.redact_key);
+                }
+            }
+        }
+        let mut json = Json::from(value);
+        crate::ext::redact::redact(&mut json, &self.inner.redact, &self.inner.redact_key);
+        Value::from(json)
+    }
+
+    fn allowed_channel(&self, value: &Value) -> bool {
+        let (included, excluded) = &self.inner.channels;
+        if included.is_empty() && excluded.is_empty() {
+            return true;
+        }
+        let channel = match crate::evtx::Wrapper(value)
+            .find("Event.System.Channel")
+            .and_then(|v| v.as_str().map(|c| c.to_lowercase()))
+        {
+            Some(channel) => channel,
+            None => return included.is_empty(),
+        };
+        if excluded.contains(&channel) {
+            return false;
+        }
+        included.is_empty() || included.contains(&channel)
+    }
+
+    fn document_timezone(&self, kind: &FileKind, value: &Value) -> Option<Timezone> {
+        let field = self.inner.timezone_field.as_ref()?;
+        let found = match kind {
+            FileKind::Evtx => crate::evtx::Wrapper(value).find(field),
+            _ => value.find(field),
+        };
+        // NOTE: Documents without a valid timezone fall back to the hunter's timezone, and to UTC
+        // when that is not set either.
+        found.and_then(|v| v.as_str().and_then(|tz| tz.parse().ok()))
+    }
+
+    fn skip(&self, timestamp: NaiveDateTime, timezone: Option<Timezone>) -> crate::Result<bool> {
         if self.inner.from.is_some() || self.inner.to.is_some() {
             // TODO: Not sure if this is correct...
-            let localised = if let Some(timezone) = self.inner.timezone {
+            let localised = if let Some(timezone) = timezone.or(self.inner.timezone) {
                 let local = match timezone.from_local_datetime(&timestamp).single() {
                     Some(l) => l,
                     None => {
@@ -1080,3 +1724,27 @@ impl Hunter {
         Ok(false)
     }
 }
+
+fn find_keywords(matcher: &AhoCorasick, value: &Value, matches: &mut FxHashSet<usize>) {
+    match value {
+        Value::Null | Value::Bool(_) => {}
+        Value::Float(f) => find_keywords(matcher, &Value::String(f.to_string()), matches),
+        Value::Int(i) => find_keywords(matcher, &Value::String(i.to_string()), matches),
+        Value::UInt(u) => find_keywords(matcher, &Value::String(u.to_string()), matches),
+        Value::String(s) => {
+            for m in matcher.find_overlapping_iter(s) {
+                matches.insert(m.pattern().as_usize());
+            }
+        }
+        Value::Array(a) => {
+            for v in a {
+                find_keywords(matcher, v, matches);
+            }
+        }
+        Value::Object(o) => {
+            for v in o.values() {
+                find_keywords(matcher, v, matches);
+            }
+        }
+    }
+}
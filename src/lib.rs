@@ -3,14 +3,27 @@ extern crate anyhow;
 
 pub(crate) use anyhow::Result;
 
-pub use analyse::shimcache::ShimcacheAnalyzer;
-pub use file::{evtx, get_files, Document, Kind as FileKind, Reader};
-pub use hunt::{Hunter, HunterBuilder};
+pub use analyse::lnk::LnkAnalyzer;
+pub use analyse::recyclebin::RecycleBinAnalyzer;
+pub use analyse::shimcache::{
+    load_baseline as load_shimcache_baseline, sort_timeline as sort_shimcache_timeline,
+    ShimcacheAnalyzer, SortOrder as ShimcacheSort,
+};
+pub use analyse::userassist::UserAssistAnalyzer;
+pub use ext::redact::generate_key as generate_redact_key;
+pub use ext::timezone::Timezone;
+pub use file::hve::shimcache::AssumedVersion as ShimcacheAssumedVersion;
+pub use file::{evtx, file_size, get_files, Document, Kind as FileKind, Reader};
+pub use hunt::{
+    lint as lint_mapping, Detections, Document as DetectionDocument, Hit, Hunt, Hunter,
+    HunterBuilder, Kind as DetectionKind, RawDocument, RuleMetadata, Sort,
+};
 pub use rule::{
-    lint, load, sigma, Filter, Kind as RuleKind, Level as RuleLevel, Status as RuleStatus,
+    fix as fix_rule, infer_kind as infer_rule_kind, lint, load, sigma, Filter, Kind as RuleKind,
+    Level as RuleLevel, Parsed as ParsedRule, Rule, Status as RuleStatus,
 };
 pub use search::{Searcher, SearcherBuilder};
-pub use write::{set_writer, Format, Writer, WRITER};
+pub use write::{close_writer, set_writer, use_colour, Format, Output, Writer, WRITER};
 
 #[macro_use]
 mod write;
@@ -2,21 +2,37 @@
 extern crate chainsaw;
 extern crate term_size;
 
+use std::ffi::OsString;
 use std::fs::{self, File};
-use std::io::BufRead;
-use std::path::PathBuf;
-use std::{collections::HashSet, io::BufReader};
+use std::hash::{Hash, Hasher};
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    io::BufReader,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
 use bytesize::ByteSize;
 use chrono::NaiveDateTime;
-use chrono_tz::Tz;
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use notify::Watcher;
+use regex::{Regex, RegexBuilder};
+use sha2::{Digest, Sha256};
 
 use chainsaw::{
-    cli, get_files, lint as lint_rule, load as load_rule, set_writer, Document, Filter, Format,
-    Hunter, Reader, RuleKind, RuleLevel, RuleStatus, Searcher, ShimcacheAnalyzer, Writer,
+    cli, close_writer, file_size, fix_rule, get_files, infer_rule_kind, lint as lint_rule,
+    lint_mapping, load as load_rule, load_shimcache_baseline, set_writer, sort_shimcache_timeline,
+    use_colour, DetectionKind, Detections, Document, FileKind, Filter, Format, Hunter, LnkAnalyzer,
+    Output, Reader, RecycleBinAnalyzer, RuleKind, RuleLevel, RuleStatus, Searcher,
+    ShimcacheAnalyzer, ShimcacheAssumedVersion, ShimcacheSort, Sort, Timezone, UserAssistAnalyzer,
+    Writer,
 };
 
 #[derive(Parser)]
@@ -40,6 +56,12 @@ use chainsaw::{
     version
 )]
 struct Args {
+    /// Always show Chainsaw's banner, even when stderr is not a terminal or NO_COLOR is set.
+    #[arg(long, conflicts_with = "no_banner")]
+    banner: bool,
+    /// Print errors to stderr as a json object, so that they can be parsed.
+    #[arg(long)]
+    error_json: bool,
     /// Hide Chainsaw's banner.
     #[arg(long)]
     no_banner: bool,
@@ -79,7 +101,7 @@ enum Command {
 
     /// Hunt through artefacts using detection rules for threat detection.
     Hunt {
-        /// The path to a collection of rules to use for hunting.
+        /// The path to a collection of rules to use for hunting, or - to read them from stdin.
         rules: Option<PathBuf>,
 
         /// The paths containing files to load and hunt through.
@@ -91,7 +113,28 @@ enum Command {
         /// A path containing additional rules to hunt with.
         #[arg(short = 'r', long = "rule", number_of_values = 1)]
         rule: Option<Vec<PathBuf>>,
+        /// A path containing newline delimited keywords to hunt for in any field of the documents.
+        #[arg(short = 'k', long = "keywords", number_of_values = 1)]
+        keywords: Option<Vec<PathBuf>>,
 
+        /// Append the rows to the csv files in the output directory instead of overwriting them,
+        /// only writing the header to new files.
+        #[arg(
+            long = "append-csv",
+            requires_all = &["csv", "output"],
+            conflicts_with = "compress"
+        )]
+        append_csv: bool,
+        /// Merge the results into the JSON array in the output file, instead of overwriting it.
+        #[arg(
+            long = "append-json",
+            requires_all = &["json", "output"],
+            conflicts_with = "compress"
+        )]
+        append_json: bool,
+        /// Print a breakdown of the time spent in each stage of the hunt.
+        #[arg(long = "benchmark")]
+        benchmark: bool,
         /// Cache results to disk to reduce memory usage at the cost of performance.
         #[arg(
             short = 'c',
@@ -100,15 +143,69 @@ enum Command {
             conflicts_with = "json"
         )]
         cache: bool,
+        /// A directory of parsed documents to reuse between runs, artefacts that have not been
+        /// parsed before are added to it.
+        #[arg(long = "cache-parsed", value_name = "dir")]
+        cache_parsed: Option<PathBuf>,
+        /// Only hunt through event log channels with the provided name, prefix with '!' to exclude.
+        #[arg(long = "channel", number_of_values = 1)]
+        channel: Vec<String>,
+        /// A file to record completed artefacts in, so that an interrupted hunt can be resumed.
+        /// Requires jsonl output to a file, which will be appended to when resuming.
+        // NOTE: Resuming appends to the output, which would start a second array with json.
+        #[arg(
+            long = "checkpoint",
+            requires_all = &["jsonl", "output"],
+            conflicts_with = "append_json"
+        )]
+        checkpoint: Option<PathBuf>,
         /// Set the column width for the tabular output.
         #[arg(long = "column-width", conflicts_with = "json")]
         column_width: Option<u32>,
+        /// Compress the output files with gzip, appending .gz to their names.
+        #[arg(long = "compress", requires = "destination")]
+        compress: bool,
         /// Print the output in csv format.
-        #[arg(group = "format", long = "csv", requires = "output")]
+        #[arg(group = "format", long = "csv", requires = "destination")]
         csv: bool,
+        /// Scan the event logs for the event ids present and report the rules that only check for
+        /// event ids which were not seen, then exit without hunting.
+        #[arg(long = "coverage", conflicts_with = "dry_run")]
+        coverage: bool,
+        /// Only print the number of detections found, skipping the output of the detections.
+        #[arg(
+            long = "count-only",
+            conflicts_with_all = &["format", "group_by", "output_dir"]
+        )]
+        count_only: bool,
+        /// Load the rules, mappings and artefacts, report what was found and then exit without
+        /// hunting.
+        #[arg(long = "dry-run")]
+        dry_run: bool,
+        /// Do not hunt through files with the provided extension, this is applied after --extension.
+        #[arg(long = "exclude-extension", value_name = "ext", number_of_values = 1)]
+        exclude_extension: Vec<String>,
+        /// Exclude rules by name or id, where '*' and '?' can be used as wildcards.
+        #[arg(long = "exclude-rule", number_of_values = 1)]
+        exclude_rule: Vec<String>,
+        /// Show why each detection matched, by printing the rule's logic with each part marked as
+        /// true or false for the document.
+        #[arg(long = "explain", conflicts_with = "redact")]
+        explain: bool,
         /// Only hunt through files with the provided extension.
         #[arg(long = "extension", number_of_values = 1)]
         extension: Option<Vec<String>>,
+        /// Rename a field in the csv, json and jsonl output, in the form old=new. For csv this is a
+        /// column header and for json the key of each detection.
+        #[arg(long = "field-rename", value_name = "old=new", number_of_values = 1)]
+        field_rename: Vec<String>,
+        /// Abandon any file that takes longer than this many seconds to hunt through, so that a
+        /// malformed artefact cannot stall the hunt. An abandoned file stops at its next document.
+        #[arg(long = "file-timeout", value_name = "seconds")]
+        file_timeout: Option<u64>,
+        /// Follow symlinks found when walking directories, by default they are skipped.
+        #[arg(long = "follow-symlinks")]
+        follow_symlinks: bool,
         /// The timestamp to hunt from. Drops any documents older than the value provided.
         /// (YYYY-MM-ddTHH:mm:SS)
         #[arg(long = "from")]
@@ -116,6 +213,29 @@ enum Command {
         /// Print the full values for the tabular output.
         #[arg(long = "full", conflicts_with = "json")]
         full: bool,
+        /// Print a table of detection counts grouped by the values of the provided field.
+        #[arg(long = "group-by", conflicts_with_all = &["csv", "json", "jsonl", "output_dir"])]
+        group_by: Option<String>,
+        /// Only output the first N detections, in the order given by --sort.
+        #[arg(
+            long = "head",
+            value_name = "N",
+            group = "limit",
+            conflicts_with_all = &["count_only", "jsonl", "output_dir"]
+        )]
+        head: Option<usize>,
+        /// A file of event log records to skip, one filename:recordid per line, where the filename
+        /// is either the path of the artefact or just its name.
+        #[arg(long = "ignore-records", value_name = "FILE")]
+        ignore_records: Option<PathBuf>,
+        /// The field holding the timestamp of json, xml and registry hive documents, so that
+        /// keywords are hunted for in them as well as in event logs and MFTs.
+        #[arg(
+            long = "keyword-timestamp",
+            value_name = "field",
+            requires = "keywords"
+        )]
+        keyword_timestamp: Option<String>,
         /// Print the output in json format.
         #[arg(group = "format", short = 'j', long = "json")]
         json: bool,
@@ -125,6 +245,14 @@ enum Command {
         /// Restrict loaded rules to specified kinds.
         #[arg(long = "kind", number_of_values = 1)]
         kind: Vec<RuleKind>,
+        /// Only hunt through documents from the last duration, e.g. 30m, 24h, 7d or 1d12h.
+        #[arg(
+            long = "last",
+            value_name = "duration",
+            value_parser = parse_duration,
+            conflicts_with = "from"
+        )]
+        last: Option<chrono::Duration>,
         /// Restrict loaded rules to specified levels.
         #[arg(long = "level", number_of_values = 1)]
         level: Vec<RuleLevel>,
@@ -134,21 +262,71 @@ enum Command {
         /// Output the timestamp using the local machine's timestamp.
         #[arg(long = "local", group = "tz")]
         local: bool,
+        /// Skip any files larger than the size provided (e.g. 500MB).
+        #[arg(long = "max-filesize")]
+        max_filesize: Option<ByteSize>,
+        /// Stop recording hits for a rule once it has reached this many hits.
+        #[arg(long = "max-hits-per-rule")]
+        max_hits_per_rule: Option<usize>,
+        /// Collect the event log data values that repeat a name into an array under that name, so
+        /// that rules see all of them rather than only the first.
+        #[arg(long = "merge-data")]
+        merge_data: bool,
+        /// Only output documents that at least this many rules matched.
+        #[arg(long = "min-hits", value_name = "N")]
+        min_hits: Option<usize>,
+        /// Restrict loaded rules to those with at least the specified status.
+        #[arg(long = "min-status")]
+        min_status: Option<RuleStatus>,
         /// Display additional metadata in the tablar output.
         #[arg(long = "metadata", conflicts_with = "json")]
         metadata: bool,
         /// A path to output results to.
-        #[arg(short = 'o', long = "output")]
+        #[arg(short = 'o', long = "output", group = "destination")]
         output: Option<PathBuf>,
+        /// A directory to output results to, writing one file per artefact with detections.
+        #[arg(long = "output-dir", group = "destination")]
+        output_dir: Option<PathBuf>,
         /// Print the output in log like format.
         #[arg(group = "format", long = "log")]
         log: bool,
+        /// A template for each line of the log format, interpolating {timestamp}, {rule}, {level},
+        /// {source_path} and {field.path} tokens.
+        #[arg(long = "output-template", requires = "log")]
+        output_template: Option<String>,
         /// (BETA) Enable preprocessing, which can result in increased performance.
         #[arg(long = "preprocess")]
         preprocess: bool,
+        /// Replace the value of the provided field in the output with a keyed hash (e.g.
+        /// Event.EventData.TargetUserName).
+        #[arg(long = "redact", value_name = "field", number_of_values = 1)]
+        redact: Vec<String>,
+        /// The key to hash the redacted values with, so that they can be correlated across runs. A
+        /// random key is generated and printed when this is not provided.
+        #[arg(long = "redact-key", value_name = "key", requires = "redact")]
+        redact_key: Option<String>,
         /// Supress informational output.
         #[arg(short = 'q')]
         quiet: bool,
+        /// A file of rule names or ids to hunt with, one per line, where '*' and '?' can be used as
+        /// wildcards. All other loaded rules are dropped.
+        #[arg(long = "rule-allowlist", value_name = "FILE")]
+        rule_allowlist: Option<PathBuf>,
+        /// Limit how many directories deep rule paths are walked, a depth of 1 only loads the rules
+        /// directly within them.
+        #[arg(long = "rule-depth")]
+        rule_depth: Option<usize>,
+        /// Infer the kind of each rule from its contents rather than the flag its path was provided
+        /// with, falling back to that kind when it cannot be inferred.
+        #[arg(long = "rule-kind-auto")]
+        rule_kind_auto: bool,
+        /// Display the field values that caused each rule to match in the tabular output.
+        #[arg(long = "show-matches", conflicts_with_all = &["json", "jsonl", "log"])]
+        show_matches: bool,
+        /// A file to record the highest event log record id hunted in each artefact, so that the next
+        /// hunt only processes the records that have been added since.
+        #[arg(long = "since-record", value_name = "FILE")]
+        since_record: Option<PathBuf>,
         /// A path containing Sigma rules to hunt with.
         #[arg(
             short = 's',
@@ -160,35 +338,107 @@ enum Command {
         /// Continue to hunt when an error is encountered.
         #[arg(long = "skip-errors")]
         skip_errors: bool,
+        /// Sort the detections by file, rule or timestamp (ties broken by timestamp, file then rule).
+        #[arg(long = "sort", default_value = "timestamp")]
+        sort: Sort,
         /// Restrict loaded rules to specified statuses.
         #[arg(long = "status", number_of_values = 1)]
         status: Vec<RuleStatus>,
-        /// Output the timestamp using the timezone provided.
+        /// Print the output as a STIX 2.1 bundle, with an indicator for each rule that matched and
+        /// sightings of it for each artefact.
+        #[arg(group = "format", long = "stix", conflicts_with = "output_dir")]
+        stix: bool,
+        /// Suppress informational output, but still print the final summary.
+        #[arg(long = "summary-only")]
+        summary_only: bool,
+        /// Write a json summary of the detections, with counts per rule and level and the time
+        /// span they cover, to this path.
+        #[arg(long = "summary-output", value_name = "path")]
+        summary_output: Option<PathBuf>,
+        /// Restrict loaded rules to those with the specified tag, where '*' and '?' can be used as
+        /// wildcards (e.g. attack.t1059*).
+        #[arg(long = "tag", number_of_values = 1)]
+        tag: Vec<String>,
+        /// Only output the newest N detections by timestamp, which are then ordered by --sort.
+        #[arg(
+            long = "tail",
+            value_name = "N",
+            group = "limit",
+            conflicts_with_all = &["count_only", "jsonl", "output_dir"]
+        )]
+        tail: Option<usize>,
+        /// Also print the detections table to the terminal when writing the results to --output.
+        #[arg(
+            long = "tee",
+            requires = "output",
+            conflicts_with_all = &["count_only", "output_dir"]
+        )]
+        tee: bool,
+        /// Output the timestamp using the timezone provided (e.g. Europe/London or +05:30).
         #[arg(long = "timezone", group = "tz")]
-        timezone: Option<Tz>,
+        timezone: Option<Timezone>,
         /// The timestamp to hunt up to. Drops any documents newer than the value provided.
         /// (YYYY-MM-ddTHH:mm:SS)
         #[arg(long = "to")]
         to: Option<NaiveDateTime>,
+        /// Output the timestamp using the timezone held in the provided document field, falling
+        /// back to UTC when it is missing (e.g. Event.TimeZone holding Europe/London or +05:30).
+        #[arg(long = "tz-from-event", value_name = "FIELD", group = "tz")]
+        tz_from_event: Option<String>,
+        /// Keep running after the hunt, hunting through artefacts as they are created or modified.
+        /// Requires jsonl output, which the new detections are appended to.
+        #[arg(
+            long = "watch",
+            requires = "jsonl",
+            conflicts_with_all = &["compress", "output_dir", "tee"]
+        )]
+        watch: bool,
+        /// Print the reason that each rule failed to load.
+        #[arg(short = 'v', long = "verbose")]
+        verbose: bool,
     },
 
     /// Lint provided rules to ensure that they load correctly
     Lint {
         /// The path to a collection of rules.
         path: PathBuf,
-        /// The kind of rule to lint: chainsaw, sigma or stalker
+        /// The kind of rule to lint: chainsaw, keyword or sigma, or mapping to lint mapping files.
         #[arg(long = "kind")]
-        kind: RuleKind,
+        kind: LintKind,
+        /// Print the distinct fields referenced by the rules.
+        #[arg(long = "fields")]
+        fields: bool,
+        /// Rewrite regex values that the optimiser turns into plain string matches using the
+        /// equivalent tau syntax, keeping a .bak copy of each chainsaw rule that is changed.
+        #[arg(long = "fix")]
+        fix: bool,
+        /// Print each rule that loads as json, including its metadata and logic.
+        #[arg(long = "json", conflicts_with_all = &["fields", "tau", "test_event"])]
+        json: bool,
+        /// Write a json report of which files loaded and which failed, along with why, to this path.
+        #[arg(long = "report-json", value_name = "path")]
+        report_json: Option<PathBuf>,
+        /// Also flag rules that load but are likely to be mistakes, such as unused identifiers or
+        /// conditions that are always true or false, and exit with an error if any are found.
+        #[arg(long = "strict")]
+        strict: bool,
+        /// Output the tau logic as parsed, without running the optimiser over it.
+        #[arg(long = "no-optimise", requires = "tau")]
+        no_optimise: bool,
         /// Output tau logic.
         #[arg(short = 't', long = "tau")]
         tau: bool,
+        /// Evaluate the rules against a single json event and print whether each one matched,
+        /// along with the fields that caused it to match.
+        #[arg(long = "test-event", value_name = "path")]
+        test_event: Option<PathBuf>,
     },
 
     /// Search through forensic artefacts for keywords.
     Search {
         /// A string or regular expression pattern to search for.
-        /// Not used when -e or -t is specified.
-        #[arg(required_unless_present_any=&["additional_pattern", "tau"])]
+        /// Not used when -e, -t or --pattern-file is specified.
+        #[arg(required_unless_present_any=&["additional_pattern", "pattern_file", "tau"])]
         pattern: Option<String>,
 
         /// The paths containing files to load and hunt through.
@@ -203,50 +453,170 @@ enum Command {
         )]
         additional_pattern: Option<Vec<String>>,
 
+        /// A directory of parsed documents to reuse between runs, artefacts that have not been
+        /// parsed before are added to it.
+        #[arg(long = "cache-parsed", value_name = "dir")]
+        cache_parsed: Option<PathBuf>,
+        /// Compress the output file with gzip, appending .gz to its name.
+        #[arg(long = "compress", requires = "output")]
+        compress: bool,
+        /// Only print the number of hits for each file, instead of the hits themselves.
+        #[arg(short = 'c', long = "count", conflicts_with_all = &["json", "jsonl"])]
+        count: bool,
+        /// Do not search through files with the provided extension, this is applied after
+        /// --extension.
+        #[arg(long = "exclude-extension", value_name = "ext", number_of_values = 1)]
+        exclude_extension: Vec<String>,
         /// Only search through files with the provided extension.
         #[arg(long = "extension", number_of_values = 1)]
         extension: Option<Vec<String>>,
+        /// Only match the patterns against the value of this field, which can be repeated (e.g.
+        /// Event.EventData.CommandLine). Documents without any of the fields do not match.
+        #[arg(long = "field", value_name = "path", number_of_values = 1)]
+        field: Vec<String>,
+        /// Only print the paths of the files that contain a hit, instead of the hits themselves.
+        #[arg(
+            short = 'l',
+            long = "files-with-matches",
+            conflicts_with_all = &["count", "json", "jsonl"]
+        )]
+        files_with_matches: bool,
+        /// Follow symlinks found when walking directories, by default they are skipped.
+        #[arg(long = "follow-symlinks")]
+        follow_symlinks: bool,
         /// The timestamp to search from. Drops any documents older than the value provided.
         /// (YYYY-MM-ddTHH:mm:SS)
         #[arg(long = "from", requires = "timestamp")]
         from: Option<NaiveDateTime>,
-        /// Ignore the case when searching patterns
+        /// Only output the first N hits, the search stops once they have been found.
+        #[arg(
+            long = "head",
+            value_name = "N",
+            group = "limit",
+            conflicts_with_all = &["count", "files_with_matches"]
+        )]
+        head: Option<usize>,
+        /// Colour the parts of each hit that matched the patterns, when printing to a terminal.
+        #[arg(
+            long = "highlight",
+            conflicts_with_all = &["count", "files_with_matches", "json", "jsonl"]
+        )]
+        highlight: bool,
+        /// Ignore the case when searching patterns and tau expression values.
+        /// Tau field names are always matched case-sensitively.
         #[arg(short = 'i', long = "ignore-case")]
         ignore_case: bool,
+        /// Only print the documents that do not match, with tau expressions this inverts the match
+        /// as a whole so documents that fail either the patterns or the expressions are printed.
+        #[arg(short = 'v', long = "invert-match")]
+        invert_match: bool,
         /// Print the output in json format.
         #[arg(short = 'j', long = "json")]
         json: bool,
         /// Print the output in jsonl format.
         #[arg(group = "format", long = "jsonl")]
         jsonl: bool,
+        /// Only search through documents from the last duration, e.g. 30m, 24h, 7d or 1d12h.
+        #[arg(
+            long = "last",
+            value_name = "duration",
+            value_parser = parse_duration,
+            conflicts_with = "from",
+            requires = "timestamp"
+        )]
+        last: Option<chrono::Duration>,
         /// Allow chainsaw to try and load files it cannot identify.
         #[arg(long = "load-unknown")]
         load_unknown: bool,
         /// Output the timestamp using the local machine's timestamp.
         #[arg(long = "local", group = "tz")]
         local: bool,
+        /// Record where each hit was found (the event record id, line, key path or index) under a
+        /// `chainsaw_location` field.
+        #[arg(long = "location")]
+        location: bool,
+        /// Skip any files larger than the size provided (e.g. 500MB).
+        #[arg(long = "max-filesize")]
+        max_filesize: Option<ByteSize>,
+        /// Collect the event log data values that repeat a name into an array under that name, so
+        /// that patterns and expressions see all of them rather than only the first.
+        #[arg(long = "merge-data")]
+        merge_data: bool,
+        /// Match patterns across line boundaries, searching plain text files that are not a known
+        /// format as a whole. Each of these files is held in memory while it is searched.
+        #[arg(long = "multiline", requires = "load_unknown")]
+        multiline: bool,
         /// The path to output results to.
         #[arg(short = 'o', long = "output")]
         output: Option<PathBuf>,
+        /// A file of regular expression patterns to search for, one per line. Blank lines and lines
+        /// starting with '#' are skipped.
+        #[arg(long = "pattern-file")]
+        pattern_file: Option<PathBuf>,
+        /// Print how many hits each pattern had once the search is done, to find the patterns
+        /// that never match.
+        #[arg(long = "pattern-stats")]
+        pattern_stats: bool,
+        /// Write how many hits each pattern had to this path as json, instead of printing them.
+        #[arg(long = "pattern-stats-json", value_name = "path")]
+        pattern_stats_json: Option<PathBuf>,
         /// Supress informational output.
         #[arg(short = 'q')]
         quiet: bool,
+        /// Replace the value of the provided field in the output with a keyed hash (e.g.
+        /// Event.EventData.TargetUserName).
+        #[arg(long = "redact", value_name = "field", number_of_values = 1)]
+        redact: Vec<String>,
+        /// The key to hash the redacted values with, so that they can be correlated across runs. A
+        /// random key is generated and printed when this is not provided.
+        #[arg(long = "redact-key", value_name = "key", requires = "redact")]
+        redact_key: Option<String>,
         /// Continue to search when an error is encountered.
         #[arg(long = "skip-errors")]
         skip_errors: bool,
+        /// Sort the hits by their timestamp, ties are broken by file then position within it. All
+        /// hits are held in memory until the search completes.
+        #[arg(
+            long = "sort",
+            requires = "timestamp",
+            conflicts_with_all = &["count", "files_with_matches"]
+        )]
+        sort: bool,
+        /// Suppress informational output, but still print the final summary.
+        #[arg(long = "summary-only")]
+        summary_only: bool,
+        /// Only output the last N hits, the newest by timestamp when --timestamp is provided. All
+        /// hits are held in memory until the search completes.
+        #[arg(
+            long = "tail",
+            value_name = "N",
+            group = "limit",
+            conflicts_with_all = &["count", "files_with_matches"]
+        )]
+        tail: Option<usize>,
         /// Tau expressions to search with. e.g. 'Event.System.EventID: =4104'
         #[arg(short = 't', long = "tau", number_of_values = 1)]
         tau: Option<Vec<String>>,
         /// The field that contains the timestamp.
         #[arg(long = "timestamp")]
         timestamp: Option<String>,
-        /// Output the timestamp using the timezone provided.
+        /// Output the timestamp using the timezone provided (e.g. Europe/London or +05:30).
         #[arg(long = "timezone", group = "tz")]
-        timezone: Option<Tz>,
+        timezone: Option<Timezone>,
         /// The timestamp to search up to. Drops any documents newer than the value provided.
         /// (YYYY-MM-ddTHH:mm:SS)
         #[arg(long = "to", requires = "timestamp")]
         to: Option<NaiveDateTime>,
+        /// A variable to substitute for ${key} in patterns and tau expressions, in the form
+        /// key=value. Unset variables are resolved from the environment.
+        #[arg(long = "var", number_of_values = 1)]
+        var: Vec<String>,
+    },
+
+    /// List the named timezones accepted by --timezone.
+    Tz {
+        /// Only list the timezones containing this text, ignoring case.
+        filter: Option<String>,
     },
 
     /// Perform various analyses on artifacts
@@ -282,9 +652,163 @@ enum AnalyseCommand {
         /// Enable near timestamp pair detection between shimcache and amcache for finding additional insertion timestamps for shimcache entries
         #[arg(short = 'p', long = "tspair", requires = "amcache")]
         ts_near_pair_matching: bool,
+        /// How far apart the shimcache and amcache timestamps of a near pair can be with --tspair, e.g. 30s or 5m. A wider window pairs more entries at the cost of more false pairings
+        #[arg(
+            long = "near-window",
+            value_name = "duration",
+            value_parser = parse_duration,
+            default_value = "1m"
+        )]
+        near_window: chrono::Duration,
+        /// The path to a transaction log (SYSTEM.LOG1/SYSTEM.LOG2) to replay into the shimcache artifact, logs next to the hive are found automatically
+        #[arg(short = 'l', long = "transaction-log", number_of_values = 1)]
+        transaction_log: Vec<PathBuf>,
+        /// For entries that only have a timestamp range, add the amcache file entry with the most similar path and its timestamp to the output
+        #[arg(short = 'v', long = "verbose", requires = "amcache")]
+        verbose: bool,
+        /// Parse the shimcache with the layout of this Windows version (win7, win8 or win10) instead of detecting it, for damaged or carved hives
+        #[arg(long = "assume-version", value_name = "version")]
+        assume_version: Option<ShimcacheAssumedVersion>,
+        /// The path to a timeline csv previously exported from a known-good host, entries whose path is not in it are marked as new
+        #[arg(long = "baseline", value_name = "csv")]
+        baseline: Option<PathBuf>,
+        /// Sort the entries by their resolved timestamp (asc or desc), using the start of a range when it is known. Entries without a timestamp go last
+        #[arg(long = "sort", value_name = "order")]
+        sort: Option<ShimcacheSort>,
+    },
+    /// Extract the original path, size and deletion time of files from recycle bin metadata
+    #[command(name = "recyclebin")]
+    RecycleBin {
+        /// The path to a directory containing recycle bin metadata files ($I files), e.g. $Recycle.Bin
+        path: PathBuf,
+        /// The path to output the result csv file
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+    },
+    /// Extract GUI program executions from the UserAssist keys of user hives
+    #[command(name = "userassist")]
+    UserAssist {
+        /// The paths to the UserAssist artifacts (NTUSER.DAT registry files)
+        #[arg(required = true)]
+        hives: Vec<PathBuf>,
+        /// The path to output the result csv file
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+    },
+    /// Extract the target path, arguments and target timestamps from shortcut (.lnk) files
+    #[command(name = "lnk")]
+    Lnk {
+        /// The path to a directory containing shortcut files, e.g. AppData\Roaming\Microsoft\Windows\Recent
+        path: PathBuf,
+        /// The path to output the result csv file
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
     },
 }
 
+/// The kinds of file that can be linted, which are the rule kinds plus mappings.
+#[derive(Clone)]
+enum LintKind {
+    Mapping,
+    Rule(RuleKind),
+}
+
+impl std::fmt::Display for LintKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Mapping => write!(f, "mapping"),
+            Self::Rule(kind) => write!(f, "{}", kind),
+        }
+    }
+}
+
+impl std::str::FromStr for LintKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mapping" => Ok(Self::Mapping),
+            _ => s.parse().map(Self::Rule).map_err(|_| {
+                anyhow::anyhow!("unknown kind, must be: chainsaw, keyword, mapping or sigma")
+            }),
+        }
+    }
+}
+
+fn print_hit(
+    hit: &serde_json::Value,
+    json: bool,
+    jsonl: bool,
+    first: bool,
+    highlights: &[Regex],
+) -> Result<()> {
+    if json {
+        if !first {
+            cs_print!(",");
+        }
+        cs_print_json!(hit)?;
+    } else if jsonl {
+        cs_print_json!(hit)?;
+        cs_println!();
+    } else if !highlights.is_empty() {
+        cs_println!("---");
+        cs_println!(
+            "{}",
+            cli::highlight(&serde_yaml::to_string(hit)?, highlights)
+        );
+    } else {
+        cs_println!("---");
+        cs_print_yaml!(hit)?;
+    }
+    Ok(())
+}
+
+/// The distinct event ids found in the event logs, all other artefacts are skipped.
+fn observed_event_ids(
+    files: &[PathBuf],
+    load_unknown: bool,
+    skip_errors: bool,
+) -> Result<HashSet<i64>> {
+    let extensions = FileKind::Evtx.extensions().unwrap_or_default();
+    let mut ids = HashSet::new();
+    for file in files {
+        let known = file
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| extensions.iter().any(|x| x == e))
+            .unwrap_or(false);
+        if !known && !load_unknown {
+            continue;
+        }
+        let mut reader = Reader::load(file, load_unknown, skip_errors)?;
+        if reader.kind() != FileKind::Evtx {
+            continue;
+        }
+        for result in reader.documents() {
+            let document = match result {
+                Ok(document) => document,
+                Err(e) => {
+                    if skip_errors {
+                        continue;
+                    }
+                    return Err(e);
+                }
+            };
+            if let Document::Evtx(evtx) = document {
+                let id = &evtx.data["Event"]["System"]["EventID"];
+                // NOTE: When the event id has qualifiers its value is held in the text node.
+                let id = id.get("#text").unwrap_or(id);
+                if let Some(id) = id.as_i64() {
+                    ids.insert(id);
+                } else if let Some(Ok(id)) = id.as_str().map(|i| i.parse::<i64>()) {
+                    ids.insert(id);
+                }
+            }
+        }
+    }
+    Ok(ids)
+}
+
 fn print_title() {
     cs_eprintln!(
         "
@@ -315,13 +839,24 @@ fn resolve_col_width() -> Option<u32> {
     }
 }
 
-fn init_writer(output: Option<PathBuf>, csv: bool, json: bool, quiet: bool) -> crate::Result<()> {
+fn init_writer(
+    output: Option<PathBuf>,
+    csv: bool,
+    json: bool,
+    quiet: bool,
+    compress: bool,
+) -> crate::Result<()> {
     let (path, output) = match &output {
         Some(path) => {
             if csv {
                 (Some(path.to_path_buf()), None)
             } else {
-                let file = match File::create(path) {
+                let path = if compress {
+                    compressed_path(path)
+                } else {
+                    path.to_path_buf()
+                };
+                let file = match Output::create(&path, compress) {
                     Ok(f) => f,
                     Err(e) => {
                         return Err(anyhow::anyhow!(
@@ -344,6 +879,7 @@ fn init_writer(output: Option<PathBuf>, csv: bool, json: bool, quiet: bool) -> c
         Format::Std
     };
     let writer = Writer {
+        compress,
         format,
         output,
         path,
@@ -353,13 +889,500 @@ fn init_writer(output: Option<PathBuf>, csv: bool, json: bool, quiet: bool) -> c
     Ok(())
 }
 
-fn run() -> Result<()> {
-    let args = Args::parse();
+/// Infers the output format from the extension of the output file when no format was given. The
+/// original arguments are then checked again with the format added, so that clap rejects it when it
+/// conflicts with the other arguments just as it would have if it had been given.
+fn infer_format(mut args: Args, argv: &[OsString]) -> Result<Args> {
+    let (output, formats) = match &args.cmd {
+        Command::Dump {
+            json,
+            jsonl,
+            output,
+            ..
+        } => (output, vec![("json", *json), ("jsonl", *jsonl)]),
+        // NOTE: Csv output is written to a directory rather than the output file, so it is never
+        // inferred. The other modes have their own output, which the formats conflict with, and a
+        // STIX bundle is json so it would otherwise be taken for the json format.
+        Command::Hunt {
+            count_only,
+            csv,
+            group_by,
+            json,
+            jsonl,
+            log,
+            output,
+            stix,
+            ..
+        } => {
+            if *count_only || *csv || group_by.is_some() || *stix {
+                return Ok(args);
+            }
+            (
+                output,
+                vec![("json", *json), ("jsonl", *jsonl), ("log", *log)],
+            )
+        }
+        Command::Search {
+            count,
+            files_with_matches,
+            json,
+            jsonl,
+            output,
+            ..
+        } => {
+            if *count || *files_with_matches {
+                return Ok(args);
+            }
+            (output, vec![("json", *json), ("jsonl", *jsonl)])
+        }
+        _ => return Ok(args),
+    };
+    let extension = match output.as_ref().and_then(|p| p.extension()) {
+        Some(extension) => extension.to_string_lossy().to_lowercase(),
+        None => return Ok(args),
+    };
+    let inferred = match extension.as_str() {
+        "json" => "json",
+        "jsonl" | "ndjson" => "jsonl",
+        _ => return Ok(args),
+    };
+    if let Some((flag, _)) = formats.iter().find(|(_, set)| *set) {
+        if *flag != inferred {
+            anyhow::bail!(
+                "The output file has a .{} extension but --{} was specified",
+                extension,
+                flag
+            );
+        }
+        return Ok(args);
+    }
+    // NOTE: Anything after a '--' is positional, so the flag has to go before it.
+    let mut argv = argv.to_vec();
+    let index = argv.iter().position(|a| a == "--").unwrap_or(argv.len());
+    argv.insert(index, format!("--{}", inferred).into());
+    Args::command().try_get_matches_from(argv)?;
+    match &mut args.cmd {
+        Command::Dump { json, jsonl, .. }
+        | Command::Hunt { json, jsonl, .. }
+        | Command::Search { json, jsonl, .. } => {
+            if inferred == "json" {
+                *json = true;
+            } else {
+                *jsonl = true;
+            }
+        }
+        _ => unreachable!(),
+    }
+    Ok(args)
+}
+
+/// The key to redact with, which is generated and printed when it was not provided so that the
+/// redacted values can still be correlated with those of a later run.
+fn redact_key_or_generate(redact: &[String], key: Option<String>) -> String {
+    match key {
+        Some(key) => key,
+        None => {
+            let key = chainsaw::generate_redact_key();
+            if !redact.is_empty() {
+                cs_eprintln!(
+                    "[+] Redacting with the generated key: {} (pass it with --redact-key to \
+                     correlate with other runs)",
+                    key
+                );
+            }
+            key
+        }
+    }
+}
+
+/// Parse a relative duration such as 30m, 24h or 7d, where units can be combined e.g. 1d12h.
+fn parse_duration(value: &str) -> std::result::Result<chrono::Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid duration '{}', expected numbers followed by one of s, m, h, d or w e.g. 24h",
+            value
+        )
+    };
+    let too_large = || format!("duration '{}' is too large", value);
+    let mut total = chrono::Duration::zero();
+    let mut digits = String::new();
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let n: i64 = digits.parse().map_err(|_| invalid())?;
+        digits.clear();
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        // NOTE: The chrono constructors panic on overflow, so the bounds are checked first.
+        total = n
+            .checked_mul(unit)
+            .filter(|s| *s <= chrono::Duration::max_value().num_seconds())
+            .and_then(|s| total.checked_add(&chrono::Duration::seconds(s)))
+            .ok_or_else(too_large)?;
+    }
+    if !digits.is_empty() || total <= chrono::Duration::zero() {
+        return Err(invalid());
+    }
+    if chrono::Utc::now()
+        .naive_utc()
+        .checked_sub_signed(total)
+        .is_none()
+    {
+        return Err(too_large());
+    }
+    Ok(total)
+}
+
+fn compressed_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".gz");
+    PathBuf::from(path)
+}
+
+fn file_stamp(path: &Path) -> Result<(u64, u64)> {
+    // NOTE: Files within archives take the modification time of the archive itself.
+    let on_disk = path.ancestors().find(|p| p.exists()).unwrap_or(path);
+    let modified = on_disk
+        .metadata()?
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    Ok((file_size(path)?, modified))
+}
+
+/// Walks the paths for the artefacts to hunt through, returning them alongside their sizes.
+fn enumerate_artefacts(
+    paths: &[PathBuf],
+    extensions: &Option<HashSet<String>>,
+    exclude_extension: &[String],
+    follow_symlinks: bool,
+    skip_errors: bool,
+    max_filesize: Option<ByteSize>,
+) -> Result<(Vec<PathBuf>, Vec<u64>)> {
+    let mut files = vec![];
+    let mut sizes = vec![];
+    for path in paths {
+        for file in get_files(path, extensions, follow_symlinks, skip_errors, None)? {
+            if has_extension(&file, exclude_extension) {
+                continue;
+            }
+            let len = file_size(&file)?;
+            if let Some(max) = max_filesize {
+                if len > max.as_u64() {
+                    cs_eyellowln!(
+                        "[!] Skipping '{}' as it exceeds the maximum file size ({} > {})",
+                        file.display(),
+                        ByteSize(len),
+                        max
+                    );
+                    continue;
+                }
+            }
+            files.push(file);
+            sizes.push(len);
+        }
+    }
+    Ok((files, sizes))
+}
+
+/// Blocks until files are created or modified, returning them once the events have settled so
+/// that artefacts which are still being written are not picked up straight away.
+fn wait_for_changes(
+    rx: &std::sync::mpsc::Receiver<notify::Result<notify::Event>>,
+) -> Result<Vec<PathBuf>> {
+    let mut changed = BTreeSet::new();
+    let mut event = rx.recv()?;
+    loop {
+        match event {
+            Ok(e) => {
+                if let notify::EventKind::Create(_) | notify::EventKind::Modify(_) = e.kind {
+                    changed.extend(e.paths.into_iter().filter(|p| p.is_file()));
+                }
+            }
+            Err(e) => cs_eyellowln!("[!] Failed to watch for changes - {}", e),
+        }
+        event = match rx.recv_timeout(Duration::from_secs(2)) {
+            Ok(event) => event,
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                if !changed.is_empty() {
+                    return Ok(changed.into_iter().collect());
+                }
+                rx.recv()?
+            }
+            Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                anyhow::bail!("The file watcher stopped unexpectedly")
+            }
+        };
+    }
+}
+
+fn load_checkpoint(path: &Path) -> Result<HashMap<PathBuf, (u64, u64)>> {
+    let mut completed = HashMap::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let mut parts = line.splitn(3, '\t');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(size), Some(modified), Some(file)) => {
+                completed.insert(PathBuf::from(file), (size.parse()?, modified.parse()?));
+            }
+            _ => anyhow::bail!("Invalid checkpoint file - {}", path.display()),
+        }
+    }
+    Ok(completed)
+}
+
+fn load_records(path: &Path) -> Result<HashMap<PathBuf, u64>> {
+    let mut records = HashMap::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        match line.split_once('\t') {
+            Some((record, file)) => {
+                records.insert(PathBuf::from(file), record.parse()?);
+            }
+            None => anyhow::bail!("Invalid record file - {}", path.display()),
+        }
+    }
+    Ok(records)
+}
+
+fn save_records(path: &Path, records: &HashMap<PathBuf, u64>) -> Result<()> {
+    let mut f = File::create(path)
+        .with_context(|| format!("Unable to write record file - {}", path.display()))?;
+    for (file, record) in records {
+        writeln!(f, "{}\t{}", record, file.display())?;
+    }
+    Ok(())
+}
+
+/// A fingerprint of the rules that matched and the documents that they matched, so that the same
+/// detection can be recognised when an artefact is hunted through again.
+fn detection_key(detection: &Detections, cache: &Option<File>) -> Result<u64> {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    for hit in &detection.hits {
+        hit.rule.hash(&mut hasher);
+    }
+    match &detection.kind {
+        DetectionKind::Aggregate { documents } => {
+            for document in documents {
+                document.data.hash(&mut hasher);
+            }
+        }
+        DetectionKind::Individual { document } => document.data.hash(&mut hasher),
+        DetectionKind::Cached { offset, size, .. } => {
+            let mut data = vec![0; *size];
+            if let Some(mut f) = cache.as_ref() {
+                f.seek(SeekFrom::Start(*offset as u64))?;
+                f.read_exact(&mut data)?;
+            }
+            data.hash(&mut hasher);
+        }
+    }
+    Ok(hasher.finish())
+}
+
+fn load_ignored_records(path: &Path) -> Result<HashMap<String, HashSet<u64>>> {
+    let file = File::open(path)
+        .with_context(|| format!("Unable to open ignore records file - {}", path.display()))?;
+    let mut ignored: HashMap<String, HashSet<u64>> = HashMap::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // NOTE: Windows paths contain colons, so the record id is taken from after the last one.
+        match line.rsplit_once(':') {
+            Some((file, record)) => match record.trim().parse() {
+                Ok(record) => {
+                    ignored.entry(file.to_owned()).or_default().insert(record);
+                }
+                Err(_) => anyhow::bail!(
+                    "Invalid record id '{}' in ignore records file - {}",
+                    record,
+                    path.display()
+                ),
+            },
+            None => anyhow::bail!(
+                "Invalid line '{}' in ignore records file, expected filename:recordid - {}",
+                line,
+                path.display()
+            ),
+        }
+    }
+    Ok(ignored)
+}
+
+fn load_rule_allowlist(path: &Path) -> Result<Vec<String>> {
+    let file = File::open(path)
+        .with_context(|| format!("Unable to open rule allowlist file - {}", path.display()))?;
+    let mut allowlist = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        allowlist.push(line.to_owned());
+    }
+    if allowlist.is_empty() {
+        anyhow::bail!("Rule allowlist file is empty - {}", path.display());
+    }
+    Ok(allowlist)
+}
+
+/// Whether the file has one of the extensions, used to drop excluded files when no extension set
+/// is passed to `get_files` (e.g. with --load-unknown).
+fn has_extension(file: &Path, extensions: &[String]) -> bool {
+    match file.extension().and_then(|e| e.to_str()) {
+        Some(ext) => extensions.iter().any(|e| e == ext),
+        None => false,
+    }
+}
+
+fn lint_report_entry(file: &Path, kind: &str, error: Option<&anyhow::Error>) -> serde_json::Value {
+    serde_json::json!({
+        "file": file.display().to_string(),
+        "kind": kind,
+        "status": if error.is_some() { "failed" } else { "loaded" },
+        "error": error.map(|e| e.to_string()),
+    })
+}
+
+/// Write the lint report, this happens before any strict failure so that CI always gets a report.
+fn write_lint_report(path: &Path, report: &[serde_json::Value]) -> Result<()> {
+    let file = File::create(path)
+        .with_context(|| format!("Unable to write lint report - {}", path.display()))?;
+    serde_json::to_writer_pretty(file, report)?;
+    cs_eprintln!("[+] Lint report written to: {}", path.display());
+    Ok(())
+}
+
+fn read_stdin_rules() -> Result<Vec<tempfile::TempPath>> {
+    let mut contents = String::new();
+    std::io::stdin().read_to_string(&mut contents)?;
+    let mut documents = vec![String::new()];
+    for line in contents.lines() {
+        if line.trim_end() == "---" {
+            documents.push(String::new());
+            continue;
+        }
+        let document = documents.last_mut().expect("could not get document");
+        document.push_str(line);
+        document.push('\n');
+    }
+    let mut files = vec![];
+    for document in documents.iter().filter(|d| !d.trim().is_empty()) {
+        let mut file = tempfile::Builder::new().suffix(".yml").tempfile()?;
+        file.write_all(document.as_bytes())?;
+        files.push(file.into_temp_path());
+    }
+    if files.is_empty() {
+        anyhow::bail!("No rules were provided on stdin");
+    }
+    Ok(files)
+}
+
+/// Matches the value against a pattern where '*' matches any run of characters and '?' matches
+/// exactly one, this is how rules are selected by --exclude-rule, --rule-allowlist and --tag.
+fn glob_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+    let (mut p, mut v) = (0, 0);
+    // The position of the last '*' seen and the value position it is currently matched up to
+    let mut star: Option<(usize, usize)> = None;
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, v));
+            p += 1;
+        } else if let Some((s, m)) = star {
+            p = s + 1;
+            v = m + 1;
+            star = Some((s, m + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn substitute(value: &str, vars: &HashMap<String, String>) -> Result<String> {
+    let mut substituted = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        substituted.push_str(&rest[..start]);
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => anyhow::bail!("Unterminated variable in '{}'", value),
+        };
+        let name = &rest[start + 2..end];
+        // NOTE: Variables passed on the command line take precedence over the environment.
+        match vars.get(name) {
+            Some(v) => substituted.push_str(v),
+            None => match std::env::var(name) {
+                Ok(v) => substituted.push_str(&v),
+                Err(_) => anyhow::bail!("Unresolved variable '${{{}}}' in '{}'", name, value),
+            },
+        }
+        rest = &rest[end + 1..];
+    }
+    substituted.push_str(rest);
+    Ok(substituted)
+}
+
+/// The longest artefact name written beneath the output directory, which leaves room for the hash
+/// and extension.
+const MAX_ARTEFACT_NAME: usize = 200;
+
+fn artefact_file_name(path: &Path) -> String {
+    // NOTE: We use the whole path rather than just the file name, as collections from multiple
+    // hosts will often contain artefacts with the same name (i.e. Security.evtx).
+    let name: String = path
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    // NOTE: Replacing the separators is lossy (i.e. 'a/b_c' and 'a_b/c'), so a short hash of the
+    // whole path keeps the names unique, and deep paths are cut from the front to keep the name
+    // within the 255 byte limit that most file systems have.
+    let hash: String = Sha256::digest(path.to_string_lossy().as_bytes())[..8]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let mut start = name.len().saturating_sub(MAX_ARTEFACT_NAME);
+    while !name.is_char_boundary(start) {
+        start += 1;
+    }
+    let name = name[start..].trim_start_matches(|c: char| !c.is_alphanumeric());
+    format!("{}-{}", name, hash)
+}
+
+fn run(args: Args) -> Result<()> {
     if let Some(num_threads) = args.num_threads {
         rayon::ThreadPoolBuilder::new()
             .num_threads(num_threads)
             .build_global()?;
     }
+    // NOTE: The banner is just noise when piped into another tool, so we only show it by default
+    // when we are writing to a terminal.
+    let banner = args.banner || (!args.no_banner && use_colour(&std::io::stderr()));
     match args.cmd {
         Command::Dump {
             path,
@@ -371,8 +1394,8 @@ fn run() -> Result<()> {
             quiet,
             skip_errors,
         } => {
-            init_writer(output, false, json, quiet)?;
-            if !args.no_banner {
+            init_writer(output, false, json, quiet, false)?;
+            if banner {
                 print_title();
             }
             let mut reader = Reader::load(&path, load_unknown, skip_errors)?;
@@ -432,33 +1455,99 @@ fn run() -> Result<()> {
 
             mapping,
             rule,
+            keywords,
 
             load_unknown,
+            append_csv,
+            append_json,
+            benchmark,
             cache,
+            cache_parsed,
+            channel,
+            checkpoint,
             mut column_width,
+            compress,
+            count_only,
+            coverage,
             csv,
+            dry_run,
+            exclude_extension,
+            exclude_rule,
+            explain,
             extension,
+            field_rename,
+            file_timeout,
+            follow_symlinks,
             from,
             full,
+            group_by,
+            head,
+            ignore_records,
+            keyword_timestamp,
             json,
             jsonl,
             kind,
+            last,
             level,
             local,
+            max_filesize,
+            max_hits_per_rule,
+            merge_data,
             metadata,
+            min_hits,
+            min_status,
             output,
+            output_dir,
             log,
+            output_template,
             preprocess,
             quiet,
+            redact,
+            redact_key,
+            rule_allowlist,
+            rule_depth,
+            rule_kind_auto,
+            show_matches,
+            since_record,
             sigma,
             skip_errors,
+            sort,
             status,
+            stix,
+            summary_only,
+            summary_output,
+            tag,
+            tail,
+            tee,
             timezone,
             to,
+            tz_from_event,
+            verbose,
+            watch,
         } => {
             if column_width.is_none() {
                 column_width = resolve_col_width();
             }
+            if explain && (csv || log || stix) {
+                anyhow::bail!("--explain is not supported with csv, log or stix output");
+            }
+            let mut renames = HashMap::new();
+            for rename in &field_rename {
+                match rename.split_once('=') {
+                    Some((old, new)) if !old.is_empty() && !new.is_empty() => {
+                        if renames.insert(old.to_owned(), new.to_owned()).is_some() {
+                            anyhow::bail!("The field '{}' is renamed more than once", old);
+                        }
+                    }
+                    _ => anyhow::bail!(
+                        "Invalid field rename '{}', must be in the form old=new",
+                        rename
+                    ),
+                }
+            }
+            if !renames.is_empty() && !(csv || json || jsonl) {
+                anyhow::bail!("--field-rename is only supported with csv, json or jsonl output");
+            }
             // CSV must be a folder when hunting due to the complexity of the output
             if csv {
                 if let Some(path) = &output {
@@ -468,19 +1557,79 @@ fn run() -> Result<()> {
                             ..Default::default()
                         };
                         set_writer(writer).expect("could not set writer");
-                        if !args.no_banner {
+                        if banner {
                             print_title();
                         }
                         anyhow::bail!("Unable to create output directory");
                     }
                 }
             }
-            init_writer(output.clone(), csv, json, quiet)?;
-            if !args.no_banner {
+            if let Some(dir) = &output_dir {
+                if dir.is_file() {
+                    let writer = Writer {
+                        quiet,
+                        ..Default::default()
+                    };
+                    set_writer(writer).expect("could not set writer");
+                    if banner {
+                        print_title();
+                    }
+                    anyhow::bail!("Unable to create output directory");
+                }
+            }
+            let quiet = quiet || summary_only;
+            // NOTE: The previous results must be read before the writer truncates the file.
+            let previous: Vec<serde_json::Value> = match &output {
+                Some(path) if append_json && path.exists() && path.metadata()?.len() > 0 => {
+                    serde_json::from_reader(BufReader::new(File::open(path)?)).with_context(
+                        || {
+                            format!(
+                                "Unable to append to output file as it is not a JSON array - {}",
+                                path.display()
+                            )
+                        },
+                    )?
+                }
+                _ => vec![],
+            };
+            let mut completed = match &checkpoint {
+                Some(path) if path.exists() => load_checkpoint(path)?,
+                _ => HashMap::new(),
+            };
+            if completed.is_empty() {
+                init_writer(output.clone(), csv, json || stix, quiet, compress)?;
+            } else {
+                // NOTE: When resuming we must not truncate the results of the previous run.
+                let path = output.as_ref().expect("could not get output");
+                let path = if compress {
+                    compressed_path(path)
+                } else {
+                    path.to_path_buf()
+                };
+                let file = match Output::append(&path, compress) {
+                    Ok(f) => f,
+                    Err(e) => {
+                        return Err(anyhow::anyhow!(
+                            "Unable to append to specified output file - {} - {}",
+                            path.display(),
+                            e
+                        ));
+                    }
+                };
+                set_writer(Writer {
+                    compress,
+                    format: Format::Std,
+                    output: Some(file),
+                    path: None,
+                    quiet,
+                })?;
+            }
+            if banner {
                 print_title();
             }
+            let timer = Instant::now();
             let mut rs = vec![];
-            if rule.is_some() || sigma.is_some() {
+            if rule.is_some() || sigma.is_some() || keywords.is_some() {
                 if let Some(rules) = rules {
                     let mut paths = vec![rules];
                     paths.extend(path);
@@ -494,12 +1643,14 @@ fn run() -> Result<()> {
                 rules.extend(rule)
             };
             let sigma = sigma.unwrap_or_default();
+            let keywords = keywords.unwrap_or_default();
 
             cs_eprintln!(
                 "[+] Loading detection rules from: {}",
                 rules
                     .iter()
                     .chain(sigma.iter())
+                    .chain(keywords.iter())
                     .map(|r| r.display().to_string())
                     .collect::<Vec<_>>()
                     .join(", ")
@@ -514,80 +1665,207 @@ fn run() -> Result<()> {
             } else {
                 Some(HashSet::from_iter(level.into_iter()))
             };
-            let statuses: Option<HashSet<RuleStatus>> = if status.is_empty() {
+            let mut statuses: Option<HashSet<RuleStatus>> = if status.is_empty() {
                 None
             } else {
                 Some(HashSet::from_iter(status.into_iter()))
             };
-            let mut failed = 0;
+            if let Some(min) = min_status {
+                let mut scratch = statuses.unwrap_or_else(|| {
+                    HashSet::from_iter([RuleStatus::Experimental, RuleStatus::Stable])
+                });
+                scratch.retain(|s| s >= &min);
+                statuses = Some(scratch);
+            }
+            let mut failed = vec![];
             let mut count = 0;
             let mut rs = vec![];
-            for path in &rules {
-                for file in get_files(path, &None, skip_errors)? {
-                    match load_rule(RuleKind::Chainsaw, &file, &kinds, &levels, &statuses) {
-                        Ok(r) => {
-                            if !r.is_empty() {
-                                count += 1;
-                                rs.extend(r)
+            // NOTE: Each path is loaded as the kind given, unless we are inferring it from each
+            // file. Keyword files are plain text so they are never inferred.
+            let mut load_rules = |paths: &[PathBuf], default: RuleKind| -> Result<()> {
+                for path in paths {
+                    // NOTE: Rules read from stdin are written out to temporary files so that they
+                    // go through the exact same loading logic, we keep them around until loading
+                    // is done.
+                    let mut stdin = vec![];
+                    let files = if path == Path::new("-") {
+                        stdin = read_stdin_rules()?;
+                        stdin.iter().map(|p| p.to_path_buf()).collect()
+                    } else {
+                        get_files(path, &None, true, skip_errors, rule_depth)?
+                    };
+                    for file in files {
+                        let kind = if rule_kind_auto && default != RuleKind::Keyword {
+                            infer_rule_kind(&file).unwrap_or_else(|| default.clone())
+                        } else {
+                            default.clone()
+                        };
+                        match load_rule(kind, &file, &kinds, &levels, &statuses) {
+                            Ok(r) => {
+                                if !r.is_empty() {
+                                    count += 1;
+                                    rs.extend(r)
+                                }
                             }
-                        }
-                        Err(_) => {
-                            failed += 1;
-                        }
-                    }
-                }
-            }
-            for path in &sigma {
-                for file in get_files(path, &None, skip_errors)? {
-                    match load_rule(RuleKind::Sigma, &file, &kinds, &levels, &statuses) {
-                        Ok(r) => {
-                            if !r.is_empty() {
-                                count += 1;
-                                rs.extend(r)
+                            Err(e) => {
+                                failed.push((file, e));
                             }
                         }
-                        Err(_) => {
-                            failed += 1;
-                        }
                     }
+                    drop(stdin);
+                }
+                Ok(())
+            };
+            load_rules(&rules, RuleKind::Chainsaw)?;
+            load_rules(&sigma, RuleKind::Sigma)?;
+            load_rules(&keywords, RuleKind::Keyword)?;
+            if verbose {
+                for (file, e) in &failed {
+                    cs_eyellowln!("[!] Failed to load rule '{}' - {}", file.display(), e);
                 }
             }
-            if failed > 500 && sigma.is_empty() {
-                cs_eyellowln!("[!] {} rules failed to load, ensure Sigma rule paths are specified with the '-s' flag", failed);
+            if failed.len() > 500 && sigma.is_empty() {
+                cs_eyellowln!("[!] {} rules failed to load, ensure Sigma rule paths are specified with the '-s' flag", failed.len());
             }
             if count == 0 {
                 return Err(anyhow::anyhow!(
                     "No valid detection rules were found in the provided paths",
                 ));
             }
-            if failed > 0 {
-                cs_eyellowln!(
-                    "[!] Loaded {} detection rules ({} not loaded)",
-                    count,
-                    failed
-                );
+            if !failed.is_empty() {
+                if verbose {
+                    cs_eyellowln!(
+                        "[!] Loaded {} detection rules ({} not loaded)",
+                        count,
+                        failed.len()
+                    );
+                } else {
+                    cs_eyellowln!(
+                        "[!] Loaded {} detection rules ({} not loaded, use -v to see why)",
+                        count,
+                        failed.len()
+                    );
+                }
             } else {
                 cs_eprintln!("[+] Loaded {} detection rules", count);
             }
+            if let Some(path) = &rule_allowlist {
+                let allowlist = load_rule_allowlist(path)?;
+                let mut found = vec![false; allowlist.len()];
+                rs.retain(|r| {
+                    let mut keep = false;
+                    for (i, pattern) in allowlist.iter().enumerate() {
+                        if glob_match(pattern, r.name())
+                            || r.id().map(|id| glob_match(pattern, id)).unwrap_or(false)
+                        {
+                            found[i] = true;
+                            keep = true;
+                        }
+                    }
+                    keep
+                });
+                let missing: Vec<&String> = allowlist
+                    .iter()
+                    .zip(&found)
+                    .filter(|(_, found)| !**found)
+                    .map(|(pattern, _)| pattern)
+                    .collect();
+                cs_eprintln!(
+                    "[+] {} detection rules remaining after filtering by allowlist ({} of {} entries found)",
+                    rs.len(),
+                    allowlist.len() - missing.len(),
+                    allowlist.len()
+                );
+                for pattern in missing {
+                    cs_eyellowln!(
+                        "[!] No detection rules matched allowlist entry '{}'",
+                        pattern
+                    );
+                }
+            }
+            if !exclude_rule.is_empty() {
+                let loaded = rs.len();
+                rs.retain(|r| {
+                    !exclude_rule.iter().any(|e| {
+                        glob_match(e, r.name()) || r.id().map(|i| glob_match(e, i)).unwrap_or(false)
+                    })
+                });
+                cs_eprintln!("[+] Excluded {} detection rules", loaded - rs.len());
+            }
+            if !tag.is_empty() {
+                rs.retain(|r| {
+                    r.tags()
+                        .iter()
+                        .any(|t| tag.iter().any(|pattern| glob_match(pattern, t)))
+                });
+                cs_eprintln!(
+                    "[+] {} detection rules remaining after filtering by tag",
+                    rs.len()
+                );
+            }
 
             let rules = rs;
+            let redact_key = redact_key_or_generate(&redact, redact_key);
             let mut hunter = Hunter::builder()
                 .rules(rules)
                 .mappings(mapping.unwrap_or_default())
+                .channels(channel)
+                .explain(explain)
                 .load_unknown(load_unknown)
                 .local(local)
+                // NOTE: Only these outputs show the matched values, which are costly to find.
+                .matches(json || jsonl || show_matches)
+                .merge_repeated_data(merge_data)
                 .preprocess(preprocess)
+                .redact(redact)
+                .redact_key(redact_key)
                 .skip_errors(skip_errors);
+            // NOTE: The builder holds `from` in UTC and localises the document timestamps against
+            // it, so the relative window is taken from the current UTC time.
+            let from = match last {
+                Some(last) => Some(
+                    chrono::Utc::now()
+                        .naive_utc()
+                        .checked_sub_signed(last)
+                        .ok_or_else(|| anyhow::anyhow!("--last is too large"))?,
+                ),
+                None => from,
+            };
+            if let Some(directory) = cache_parsed {
+                hunter = hunter.cache_parsed(directory);
+            }
             if let Some(from) = from {
                 hunter = hunter.from(from);
             }
+            if let Some(path) = &ignore_records {
+                let ignored = load_ignored_records(path)?;
+                cs_eprintln!(
+                    "[+] Ignoring {} event log records listed in: {}",
+                    ignored.values().map(|r| r.len()).sum::<usize>(),
+                    path.display()
+                );
+                hunter = hunter.ignore_records(ignored);
+            }
+            match keyword_timestamp {
+                Some(field) => hunter = hunter.keyword_timestamp(field),
+                None if !keywords.is_empty() => cs_eyellowln!(
+                    "[!] Keywords are only hunted for in event logs and MFTs, use --keyword-timestamp to also hunt through other documents"
+                ),
+                None => {}
+            }
             if let Some(timezone) = timezone {
                 hunter = hunter.timezone(timezone);
             }
             if let Some(to) = to {
                 hunter = hunter.to(to);
             }
-            let hunter = hunter.build()?;
+            if let Some(field) = tz_from_event {
+                hunter = hunter.timezone_field(field);
+            }
+            // NOTE: Files that time out are left running on their worker thread, so it shares
+            // ownership of the hunter rather than borrowing it.
+            let hunter = Arc::new(hunter.build()?);
+            let loading = timer.elapsed();
 
             /* if no user-defined extensions are specified, then we parse rules and
             mappings to build a list of file extensions that should be loaded */
@@ -615,6 +1893,12 @@ fn run() -> Result<()> {
                     ));
                     }
                 };
+                for e in &exclude_extension {
+                    scratch.remove(e);
+                }
+                if scratch.is_empty() {
+                    anyhow::bail!("All of the supported file extensions have been excluded");
+                }
                 message = scratch
                     .iter()
                     .map(|x| format!(".{}", x))
@@ -623,6 +1907,7 @@ fn run() -> Result<()> {
                 Some(scratch)
             };
 
+            let timer = Instant::now();
             cs_eprintln!(
                 "[+] Loading forensic artefacts from: {} (extensions: {})",
                 path.iter()
@@ -632,15 +1917,16 @@ fn run() -> Result<()> {
                 message
             );
 
-            let mut files = vec![];
-            let mut size = ByteSize::mb(0);
-            for path in &path {
-                let res = get_files(path, &exts, skip_errors)?;
-                for i in &res {
-                    size += i.metadata()?.len();
-                }
-                files.extend(res);
-            }
+            // NOTE: The sizes are kept alongside the files so the progress bar can track bytes.
+            let (files, sizes) = enumerate_artefacts(
+                &path,
+                &exts,
+                &exclude_extension,
+                follow_symlinks,
+                skip_errors,
+                max_filesize,
+            )?;
+            let size = ByteSize(sizes.iter().sum());
             if files.is_empty() {
                 return Err(anyhow::anyhow!(
                     "No compatible files were found in the provided paths",
@@ -648,13 +1934,128 @@ fn run() -> Result<()> {
             } else {
                 cs_eprintln!("[+] Loaded {} forensic artefacts ({})", files.len(), size);
             }
+            let enumeration = timer.elapsed();
+            if dry_run {
+                cs_eprintln!(
+                    "[+] Dry run complete, would hunt through {} forensic artefacts using {} detection rules",
+                    files.len(),
+                    hunter.rules().len(),
+                );
+                return Ok(());
+            }
+            if coverage {
+                let observed = observed_event_ids(&files, load_unknown, skip_errors)?;
+                cs_eprintln!(
+                    "[+] Found {} distinct event ids in the forensic artefacts",
+                    observed.len()
+                );
+                let mut dead = 0;
+                for rule in hunter.rules().values() {
+                    let ids = rule.event_ids();
+                    if ids.is_empty() || !ids.is_disjoint(&observed) {
+                        continue;
+                    }
+                    let mut ids = ids.into_iter().collect::<Vec<_>>();
+                    ids.sort();
+                    cs_println!(
+                        "{} (event ids: {})",
+                        rule.name(),
+                        ids.iter()
+                            .map(|i| i.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                    dead += 1;
+                }
+                cs_eprintln!(
+                    "[+] {} of {} detection rules only check for event ids not present in the forensic artefacts",
+                    dead,
+                    hunter.rules().len()
+                );
+                return Ok(());
+            }
+            if let Some(dir) = &output_dir {
+                fs::create_dir_all(dir)?;
+            }
             let mut hits = 0;
             let mut documents = 0;
+            let mut panicked = 0;
+            let mut timed_out = 0;
+            let mut below_min_hits = 0;
             let mut detections = vec![];
-            let pb = cli::init_progress_bar(files.len() as u64, "Hunting".to_string());
-            for file in &files {
+            let mut span: Option<(NaiveDateTime, NaiveDateTime)> = None;
+            let mut counts = HashMap::new();
+            let mut rule_hits = HashMap::new();
+            let timer = Instant::now();
+            // NOTE: The watcher is started before hunting so that changes made while the initial
+            // pass is running are not missed.
+            let watcher = if watch {
+                let (tx, rx) = std::sync::mpsc::channel();
+                let mut watcher = notify::recommended_watcher(tx)?;
+                for path in &path {
+                    watcher.watch(path, notify::RecursiveMode::Recursive)?;
+                }
+                Some((watcher, rx))
+            } else {
+                None
+            };
+            let mut pb = cli::init_progress_bar(size.as_u64(), "Hunting".to_string());
+            let mut checkpoint = match &checkpoint {
+                Some(path) => Some(
+                    fs::OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path)
+                        .with_context(|| {
+                            format!("Unable to open checkpoint file - {}", path.display())
+                        })?,
+                ),
+                None => None,
+            };
+            if !completed.is_empty() {
+                cs_eprintln!(
+                    "[+] Resuming hunt, skipping artefacts already completed in the checkpoint..."
+                );
+            }
+            let mut records = match &since_record {
+                Some(path) if path.exists() => {
+                    cs_eprintln!(
+                        "[+] Skipping event log records already hunted according to: {}",
+                        path.display()
+                    );
+                    load_records(path)?
+                }
+                _ => HashMap::new(),
+            };
+            let mut emitted: HashMap<PathBuf, HashSet<u64>> = HashMap::new();
+            // NOTE: Files hunted with a timeout share a pool of their own, so that any work left
+            // over from an abandoned file cannot hold on to the threads of the global pool. Panics
+            // are caught so that they are reported through the channel rather than aborting.
+            let timeout_pool = match file_timeout {
+                Some(_) => Some(
+                    rayon::ThreadPoolBuilder::new()
+                        .num_threads(rayon::current_num_threads())
+                        .panic_handler(|_| {})
+                        .build()?,
+                ),
+                None => None,
+            };
+            let mut hunt_file = |file: &PathBuf,
+                                 len: u64,
+                                 pb: &mut indicatif::ProgressBar,
+                                 records: &mut HashMap<PathBuf, u64>|
+             -> Result<()> {
                 pb.tick();
-                let cache = if cache {
+                let stamp = if checkpoint.is_some() || watch {
+                    Some(file_stamp(file)?)
+                } else {
+                    None
+                };
+                if stamp.is_some() && completed.get(file) == stamp.as_ref() {
+                    pb.inc(len);
+                    return Ok(());
+                }
+                let mut cache = if cache {
                     match tempfile::tempfile() {
                         Ok(f) => Some(f),
                         Err(e) => {
@@ -664,12 +2065,202 @@ fn run() -> Result<()> {
                 } else {
                     None
                 };
-                let scratch = hunter.hunt(file, &cache).with_context(|| {
+                let mut record = records.get(file).copied();
+                let result = if let Some((timeout, pool)) = file_timeout.zip(timeout_pool.as_ref())
+                {
+                    // NOTE: A file that times out is cancelled, which stops it at the next document
+                    // it reads, and its results are dropped.
+                    let cancelled = Arc::new(AtomicBool::new(false));
+                    let (tx, rx) = std::sync::mpsc::channel();
+                    let hunter = hunter.clone();
+                    let path = file.clone();
+                    let flag = cancelled.clone();
+                    pool.spawn(move || {
+                        let result = hunter.hunt_cancellable(&path, &cache, &mut record, &flag);
+                        let _ = tx.send((result, record, cache));
+                    });
+                    match rx.recv_timeout(Duration::from_secs(timeout)) {
+                        Ok((result, r, c)) => {
+                            record = r;
+                            cache = c;
+                            result
+                        }
+                        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
+                            cancelled.store(true, Ordering::Relaxed);
+                            cs_eyellowln!(
+                                "[!] Skipping '{}' as it took longer than {}s to hunt through",
+                                file.display(),
+                                timeout
+                            );
+                            timed_out += 1;
+                            pb.inc(len);
+                            return Ok(());
+                        }
+                        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+                            if !skip_errors {
+                                anyhow::bail!(
+                                    "The parser panicked while hunting through file '{}'",
+                                    file.display()
+                                );
+                            }
+                            cs_eyellowln!(
+                                "[!] Skipping '{}' as it caused the parser to panic",
+                                file.display()
+                            );
+                            panicked += 1;
+                            pb.inc(len);
+                            return Ok(());
+                        }
+                    }
+                } else if skip_errors {
+                    // NOTE: The parsers can panic on malformed artefacts, so when skipping
+                    // errors we isolate each file to stop one bad artefact from ending the
+                    // hunt.
+                    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                        hunter.hunt_since(file, &cache, &mut record)
+                    })) {
+                        Ok(result) => result,
+                        Err(_) => {
+                            cs_eyellowln!(
+                                "[!] Skipping '{}' as it caused the parser to panic",
+                                file.display()
+                            );
+                            panicked += 1;
+                            pb.inc(len);
+                            return Ok(());
+                        }
+                    }
+                } else {
+                    hunter.hunt_since(file, &cache, &mut record)
+                };
+                let mut scratch = result.with_context(|| {
                     format!("Failed to hunt through file '{}'", file.to_string_lossy())
                 })?;
+                if let Some(record) = record {
+                    records.insert(file.clone(), record);
+                }
+                if let (true, Some(stamp)) = (watch, stamp) {
+                    completed.insert(file.clone(), stamp);
+                }
+                hunter.sort(&mut scratch, &sort);
+                // NOTE: Event log records already hunted are skipped using the highest record id
+                // seen, but other artefacts are hunted in full again when they change, so we drop
+                // the detections that were already output for them.
+                if watch && record.is_none() {
+                    let seen = emitted.entry(file.clone()).or_default();
+                    let mut keep = Vec::with_capacity(scratch.len());
+                    for detection in scratch {
+                        if seen.insert(detection_key(&detection, &cache)?) {
+                            keep.push(detection);
+                        }
+                    }
+                    scratch = keep;
+                }
+                // NOTE: This comes first so that documents we drop do not use up the hits
+                // allowed for each rule.
+                if let Some(min) = min_hits {
+                    let before = scratch.len();
+                    scratch.retain(|d| d.hits.len() >= min);
+                    below_min_hits += before - scratch.len();
+                }
+                if let Some(max) = max_hits_per_rule {
+                    for detection in scratch.iter_mut() {
+                        detection.hits.retain(|hit| {
+                            let count = counts.entry(hit.rule).or_insert(0);
+                            *count += 1;
+                            *count <= max
+                        });
+                    }
+                    scratch.retain(|d| !d.hits.is_empty());
+                }
                 hits += scratch.iter().map(|d| d.hits.len()).sum::<usize>();
                 documents += scratch.len();
-                if jsonl {
+                for hit in scratch.iter().flat_map(|d| d.hits.iter()) {
+                    span = match span {
+                        Some((earliest, latest)) => {
+                            Some((earliest.min(hit.timestamp), latest.max(hit.timestamp)))
+                        }
+                        None => Some((hit.timestamp, hit.timestamp)),
+                    };
+                    *rule_hits.entry(hit.rule).or_insert(0) += 1;
+                }
+                if let Some(dir) = &output_dir {
+                    // Each artefact with detections gets its own output file, so we point the
+                    // writer at it for the duration of the print and then put it back.
+                    if scratch.is_empty() {
+                        pb.inc(len);
+                        return Ok(());
+                    }
+                    let name = artefact_file_name(file);
+                    let target = if csv {
+                        dir.join(name)
+                    } else if json {
+                        dir.join(format!("{}.json", name))
+                    } else if jsonl {
+                        dir.join(format!("{}.jsonl", name))
+                    } else if log {
+                        dir.join(format!("{}.log", name))
+                    } else {
+                        dir.join(format!("{}.txt", name))
+                    };
+                    init_writer(Some(target), csv, json, quiet, compress)?;
+                    if csv {
+                        cli::print_csv(
+                            &scratch,
+                            hunter.hunts(),
+                            hunter.rules(),
+                            local,
+                            timezone,
+                            append_csv,
+                            &renames,
+                        )?;
+                    } else if json {
+                        cli::print_json(
+                            &scratch,
+                            hunter.hunts(),
+                            hunter.rules(),
+                            local,
+                            timezone,
+                            &[],
+                            &renames,
+                        )?;
+                    } else if jsonl {
+                        cli::print_jsonl(
+                            &scratch,
+                            hunter.hunts(),
+                            hunter.rules(),
+                            local,
+                            timezone,
+                            cache,
+                            &renames,
+                        )?;
+                    } else if log {
+                        cli::print_log(
+                            &scratch,
+                            hunter.hunts(),
+                            hunter.rules(),
+                            local,
+                            timezone,
+                            output_template.as_deref(),
+                        )?;
+                    } else {
+                        cli::print_detections(
+                            &scratch,
+                            hunter.hunts(),
+                            hunter.rules(),
+                            column_width.unwrap_or(40),
+                            full,
+                            local,
+                            show_matches,
+                            metadata,
+                            timezone,
+                        );
+                        if explain {
+                            cli::print_explanations(&scratch, hunter.rules(), local, timezone);
+                        }
+                    }
+                    init_writer(None, false, json, quiet, compress)?;
+                } else if jsonl {
                     cli::print_jsonl(
                         &scratch,
                         hunter.hunts(),
@@ -677,24 +2268,137 @@ fn run() -> Result<()> {
                         local,
                         timezone,
                         cache,
+                        &renames,
                     )?;
-                } else {
+                    if tee {
+                        detections.extend(scratch);
+                    }
+                } else if !count_only {
                     detections.extend(scratch);
                 }
-                pb.inc(1);
+                if let (Some(checkpoint), Some((size, modified))) = (&mut checkpoint, stamp) {
+                    writeln!(checkpoint, "{}\t{}\t{}", size, modified, file.display())?;
+                    checkpoint.flush()?;
+                }
+                pb.inc(len);
+                Ok(())
+            };
+            for (file, len) in files.iter().zip(sizes.iter().copied()) {
+                hunt_file(file, len, &mut pb, &mut records)?;
             }
             pb.finish();
-            if csv {
-                cli::print_csv(&detections, hunter.hunts(), hunter.rules(), local, timezone)?;
+            if let Some(path) = &since_record {
+                save_records(path, &records)?;
+            }
+            if let Some((_, rx)) = &watcher {
+                cs_eprintln!("[+] Watching for new or modified forensic artefacts...");
+                loop {
+                    let changed = wait_for_changes(rx)?;
+                    let (files, sizes) = match enumerate_artefacts(
+                        &changed,
+                        &exts,
+                        &exclude_extension,
+                        follow_symlinks,
+                        skip_errors,
+                        max_filesize,
+                    ) {
+                        Ok(artefacts) => artefacts,
+                        Err(e) => {
+                            cs_eyellowln!(
+                                "[!] Failed to load the changed forensic artefacts - {}",
+                                e
+                            );
+                            continue;
+                        }
+                    };
+                    if files.is_empty() {
+                        continue;
+                    }
+                    cs_eprintln!(
+                        "[+] Hunting through {} changed forensic artefacts",
+                        files.len()
+                    );
+                    let mut pb = cli::init_progress_bar(sizes.iter().sum(), "Hunting".to_string());
+                    for (file, len) in files.iter().zip(sizes.iter().copied()) {
+                        // NOTE: An artefact that fails to parse is not marked as completed, so it
+                        // is hunted again the next time that it changes, e.g. once fully written.
+                        if let Err(e) = hunt_file(file, len, &mut pb, &mut records) {
+                            cs_eyellowln!(
+                                "[!] Skipping '{}' until it next changes - {:#}",
+                                file.display(),
+                                e
+                            );
+                            pb.inc(len);
+                        }
+                    }
+                    pb.finish();
+                    if let Some(path) = &since_record {
+                        if let Err(e) = save_records(path, &records) {
+                            cs_eyellowln!("[!] Unable to write record file - {}", e);
+                        }
+                    }
+                }
+            }
+            let hunting = timer.elapsed();
+            let timer = Instant::now();
+            if let Some(n) = tail {
+                hunter.sort(&mut detections, &Sort::Timestamp);
+                detections.drain(..detections.len().saturating_sub(n));
+            }
+            hunter.sort(&mut detections, &sort);
+            if let Some(n) = head {
+                detections.truncate(n);
+            }
+            if head.is_some() || tail.is_some() {
+                cs_eprintln!(
+                    "[+] Output limited to {} of {} documents with detections",
+                    detections.len(),
+                    documents
+                );
+            }
+            if count_only {
+                cs_println!("{} Detections found on {} documents", hits, documents);
+            } else if let Some(dir) = &output_dir {
+                cs_eprintln!("[+] Results written to output directory: {}", dir.display());
+            } else if csv {
+                cli::print_csv(
+                    &detections,
+                    hunter.hunts(),
+                    hunter.rules(),
+                    local,
+                    timezone,
+                    append_csv,
+                    &renames,
+                )?;
             } else if json {
                 if output.is_some() {
                     cs_eprintln!("[+] Writing results to output file...");
                 }
-                cli::print_json(&detections, hunter.hunts(), hunter.rules(), local, timezone)?;
+                cli::print_json(
+                    &detections,
+                    hunter.hunts(),
+                    hunter.rules(),
+                    local,
+                    timezone,
+                    &previous,
+                    &renames,
+                )?;
             } else if jsonl {
                 // Work already done
+            } else if stix {
+                if output.is_some() {
+                    cs_eprintln!("[+] Writing results to output file...");
+                }
+                cli::print_stix(&detections, hunter.rules(), timezone)?;
             } else if log {
-                cli::print_log(&detections, hunter.hunts(), hunter.rules(), local, timezone)?;
+                cli::print_log(
+                    &detections,
+                    hunter.hunts(),
+                    hunter.rules(),
+                    local,
+                    timezone,
+                    output_template.as_deref(),
+                )?;
             } else {
                 cli::print_detections(
                     &detections,
@@ -703,27 +2407,300 @@ fn run() -> Result<()> {
                     column_width.unwrap_or(40),
                     full,
                     local,
+                    show_matches,
+                    metadata,
+                    timezone,
+                );
+                if explain {
+                    cli::print_explanations(&detections, hunter.rules(), local, timezone);
+                }
+            }
+            if let Some(field) = &group_by {
+                cli::print_group_by(&detections, hunter.hunts(), field)?;
+            }
+            if tee {
+                // NOTE: Swapping the writer finishes the output file, so the table can only go to
+                // the terminal once the results have been written.
+                set_writer(Writer {
+                    quiet,
+                    ..Default::default()
+                })?;
+                cli::print_detections(
+                    &detections,
+                    hunter.hunts(),
+                    hunter.rules(),
+                    column_width.unwrap_or(40),
+                    full,
+                    local,
+                    show_matches,
                     metadata,
                     timezone,
                 );
             }
-            cs_eprintln!("\n[+] {} Detections found on {} documents", hits, documents,);
+            let printing = timer.elapsed();
+            // NOTE: The summary bypasses the quiet gate so that it can be relied upon in scripts.
+            // NOTE: With count only the summary has already been written to the output.
+            if !count_only {
+                if summary_only {
+                    eprintln!("[+] {} Detections found on {} documents", hits, documents);
+                } else {
+                    cs_eprintln!("\n[+] {} Detections found on {} documents", hits, documents,);
+                }
+            }
+            if let Some(max) = max_hits_per_rule {
+                for (rule, count) in &counts {
+                    if *count > max {
+                        cs_eyellowln!(
+                            "[!] Rule '{}' was truncated to {} of its {} hits",
+                            hunter.rules().get(rule).expect("could not get rule").name(),
+                            max,
+                            count
+                        );
+                    }
+                }
+            }
+            if panicked > 0 {
+                cs_eyellowln!(
+                    "[!] Skipped {} files that caused the parser to panic",
+                    panicked
+                );
+            }
+            if let Some(min) = min_hits {
+                cs_eprintln!(
+                    "[+] Filtered out {} documents with fewer than {} hits",
+                    below_min_hits,
+                    min
+                );
+            }
+            if timed_out > 0 {
+                cs_eyellowln!(
+                    "[!] Skipped {} files that exceeded the file timeout",
+                    timed_out
+                );
+            }
+            cli::print_time_span(span, local, timezone);
+            if let Some(path) = &summary_output {
+                let file = File::create(path).with_context(|| {
+                    format!("Unable to write summary file - {}", path.display())
+                })?;
+                let summary = cli::summary(
+                    hits,
+                    documents,
+                    &rule_hits,
+                    hunter.rules(),
+                    span,
+                    local,
+                    timezone,
+                );
+                serde_json::to_writer_pretty(file, &summary)?;
+                cs_eprintln!("[+] Summary written to: {}", path.display());
+            }
+            if benchmark {
+                // NOTE: When writing jsonl or to an output directory the results are written as we
+                // go, so that time is included in the hunting stage rather than the output stage.
+                cs_eprintln!("[+] Benchmark:");
+                cs_eprintln!(
+                    "    Rule loading:     {:>10.2?} ({} rules)",
+                    loading,
+                    hunter.rules().len()
+                );
+                cs_eprintln!(
+                    "    File enumeration: {:>10.2?} ({} artefacts)",
+                    enumeration,
+                    files.len()
+                );
+                cs_eprintln!(
+                    "    Hunting:          {:>10.2?} ({} documents)",
+                    hunting,
+                    documents
+                );
+                cs_eprintln!(
+                    "    Output:           {:>10.2?} ({} detections)",
+                    printing,
+                    hits
+                );
+                cs_eprintln!(
+                    "    Total:            {:>10.2?}",
+                    [loading, enumeration, hunting, printing]
+                        .iter()
+                        .sum::<Duration>()
+                );
+            }
         }
-        Command::Lint { path, kind, tau } => {
-            init_writer(None, false, false, false)?;
-            if !args.no_banner {
+        Command::Lint {
+            path,
+            kind,
+            fields,
+            fix,
+            json,
+            no_optimise,
+            report_json,
+            strict,
+            tau,
+            test_event,
+        } => {
+            init_writer(None, false, false, false, false)?;
+            if banner {
                 print_title();
             }
+            let relative = |file: &Path| match file
+                .display()
+                .to_string()
+                .strip_prefix(&path.display().to_string())
+            {
+                Some(e) => e.to_string(),
+                None => file.display().to_string(),
+            };
+            let kind = match kind {
+                LintKind::Mapping => {
+                    if fields || fix || json || tau || test_event.is_some() {
+                        anyhow::bail!(
+                            "--fields, --fix, --json, --tau and --test-event are only supported when linting rules"
+                        );
+                    }
+                    cs_eprintln!("[+] Validating supplied mappings...");
+                    let mut count = 0;
+                    let mut failed = 0;
+                    let mut warned = 0;
+                    let mut report = vec![];
+                    for file in get_files(&path, &None, true, false, None)? {
+                        let file_name = relative(&file);
+                        let result = lint_mapping(&file);
+                        report.push(lint_report_entry(&file, "mapping", result.as_ref().err()));
+                        match result {
+                            Ok(warnings) => {
+                                for warning in &warnings {
+                                    cs_eyellowln!("[!] {}: {}", file_name, warning);
+                                }
+                                if !warnings.is_empty() {
+                                    warned += 1;
+                                }
+                            }
+                            Err(e) => {
+                                failed += 1;
+                                cs_eprintln!("[!] {}: {}", file_name, e);
+                                continue;
+                            }
+                        }
+                        count += 1;
+                    }
+                    cs_eprintln!("[+] Validated {} mappings out of {}", count, count + failed);
+                    if let Some(report_path) = &report_json {
+                        write_lint_report(report_path, &report)?;
+                    }
+                    if strict && (failed > 0 || warned > 0) {
+                        anyhow::bail!(
+                            "{} mappings failed to load and {} have warnings",
+                            failed,
+                            warned
+                        );
+                    }
+                    return Ok(());
+                }
+                LintKind::Rule(kind) => kind,
+            };
+            if kind == RuleKind::Keyword && test_event.is_some() {
+                anyhow::bail!("--test-event is not supported for keyword rules");
+            }
+            if kind != RuleKind::Chainsaw && fix {
+                anyhow::bail!("--fix is only supported for chainsaw rules");
+            }
+            let event: Option<serde_json::Value> = match &test_event {
+                Some(path) => {
+                    let file = File::open(path).with_context(|| {
+                        format!("Unable to open test event - {}", path.display())
+                    })?;
+                    Some(serde_json::from_reader(file).with_context(|| {
+                        format!("Test event is not valid json - {}", path.display())
+                    })?)
+                }
+                None => None,
+            };
             cs_eprintln!("[+] Validating as {} for supplied detection rules...", kind);
             let mut count = 0;
             let mut failed = 0;
-            for file in get_files(&path, &None, false)? {
-                match lint_rule(&kind, &file) {
+            let mut warned = 0;
+            let mut modified = 0;
+            let mut referenced = BTreeSet::new();
+            let mut report = vec![];
+            for file in get_files(&path, &None, true, false, None)? {
+                // NOTE: These are the backups left by --fix, which are not rules to validate.
+                if has_extension(&file, &["bak".to_owned()]) {
+                    continue;
+                }
+                let file_name = relative(&file);
+                let result = lint_rule(&kind, &file);
+                report.push(lint_report_entry(
+                    &file,
+                    &kind.to_string(),
+                    result.as_ref().err(),
+                ));
+                match result {
                     Ok(filters) => {
+                        if strict {
+                            let warnings = filters
+                                .iter()
+                                .flat_map(|f| f.warnings())
+                                .collect::<Vec<_>>();
+                            for warning in &warnings {
+                                cs_eyellowln!("[!] {}: {}", file_name, warning);
+                            }
+                            if !warnings.is_empty() {
+                                warned += 1;
+                            }
+                        }
+                        if fields {
+                            for filter in &filters {
+                                referenced.extend(filter.fields());
+                            }
+                        }
+                        if fix {
+                            match fix_rule(&kind, &file) {
+                                Ok(Some((contents, count))) => {
+                                    let mut backup = file.clone().into_os_string();
+                                    backup.push(".bak");
+                                    fs::copy(&file, &backup)?;
+                                    fs::write(&file, contents)?;
+                                    cs_eprintln!("[+] {}: fixed {} values", file_name, count);
+                                    modified += 1;
+                                }
+                                Ok(None) => {}
+                                Err(e) => {
+                                    cs_eyellowln!("[!] {}: not fixed - {}", file_name, e);
+                                }
+                            }
+                        }
+                        // NOTE: No mapping is applied here, so the event must use the field names
+                        // that the rule expects.
+                        if let Some(event) = &event {
+                            for filter in &filters {
+                                if filter.solve(event) {
+                                    cs_println!("[+] {}: matched", file.display());
+                                    for (field, value) in filter.matches(event) {
+                                        cs_println!("    {}: {}", field, value);
+                                    }
+                                } else {
+                                    cs_println!("[-] {}: not matched", file.display());
+                                }
+                            }
+                        }
+                        if json {
+                            // NOTE: Filters are stripped of the metadata, so the rule is loaded
+                            // again to get hold of it.
+                            for rule in load_rule(kind.clone(), &file, &None, &None, &None)? {
+                                let mut value = serde_json::to_value(rule.parsed())?;
+                                value["path"] = serde_json::Value::String(file_name.clone());
+                                cs_print_json!(&value)?;
+                                cs_println!();
+                            }
+                        }
                         if tau {
                             cs_eprintln!("[+] Rule {}:", file.to_string_lossy());
                             for filter in filters {
                                 let yaml = match filter {
+                                    Filter::Detection(d) if no_optimise => {
+                                        serde_yaml::to_string(&d)?
+                                    }
                                     Filter::Detection(mut d) => {
                                         d.expression = tau_engine::core::optimiser::coalesce(
                                             d.expression,
@@ -749,14 +2726,6 @@ fn run() -> Result<()> {
                     }
                     Err(e) => {
                         failed += 1;
-                        let file_name = match file
-                            .display()
-                            .to_string()
-                            .strip_prefix(&path.display().to_string())
-                        {
-                            Some(e) => e.to_string(),
-                            None => file.display().to_string(),
-                        };
                         cs_eprintln!("[!] {}: {}", file_name, e);
                         continue;
                     }
@@ -768,6 +2737,25 @@ fn run() -> Result<()> {
                 count,
                 count + failed
             );
+            if fix {
+                cs_eprintln!("[+] Fixed {} detection rules", modified);
+            }
+            if let Some(report_path) = &report_json {
+                write_lint_report(report_path, &report)?;
+            }
+            if fields {
+                cs_eprintln!("[+] Fields referenced by {} detection rules:", kind);
+                for field in referenced {
+                    cs_println!("{}", field);
+                }
+            }
+            if strict && (failed > 0 || warned > 0) {
+                anyhow::bail!(
+                    "{} detection rules failed to load and {} have warnings",
+                    failed,
+                    warned
+                );
+            }
         }
         Command::Search {
             path,
@@ -775,35 +2763,74 @@ fn run() -> Result<()> {
             mut pattern,
             additional_pattern,
 
+            cache_parsed,
+            compress,
+            count,
+            exclude_extension,
             extension,
+            field,
+            files_with_matches,
+            follow_symlinks,
             from,
+            head,
+            highlight,
             ignore_case,
+            invert_match,
             json,
             jsonl,
+            last,
             load_unknown,
             local,
+            location,
+            max_filesize,
+            merge_data,
+            multiline,
             output,
+            pattern_file,
+            pattern_stats,
+            pattern_stats_json,
             quiet,
+            redact,
+            redact_key,
             skip_errors,
+            sort,
+            summary_only,
+            tail,
             tau,
             timestamp,
             timezone,
             to,
+            var,
         } => {
-            init_writer(output, false, json, quiet)?;
-            if !args.no_banner {
+            let quiet = quiet || summary_only;
+            // NOTE: Highlighting is only applied when writing to a terminal.
+            let highlight = highlight && output.is_none() && use_colour(&std::io::stdout());
+            init_writer(output, false, json, quiet, compress)?;
+            let mut vars = HashMap::new();
+            for v in var {
+                match v.split_once('=') {
+                    Some((key, value)) => {
+                        vars.insert(key.to_owned(), value.to_owned());
+                    }
+                    None => {
+                        anyhow::bail!("Invalid variable '{}', must be in the form key=value", v)
+                    }
+                }
+            }
+            if banner {
                 print_title();
             }
-            let mut paths = if additional_pattern.is_some() || tau.is_some() {
-                let mut scratch = pattern
-                    .take()
-                    .map(|p| vec![PathBuf::from(p)])
-                    .unwrap_or_default();
-                scratch.extend(path);
-                scratch
-            } else {
-                path
-            };
+            let mut paths =
+                if additional_pattern.is_some() || pattern_file.is_some() || tau.is_some() {
+                    let mut scratch = pattern
+                        .take()
+                        .map(|p| vec![PathBuf::from(p)])
+                        .unwrap_or_default();
+                    scratch.extend(path);
+                    scratch
+                } else {
+                    path
+                };
             if paths.is_empty() {
                 paths.push(
                     std::env::current_dir().expect("could not get current working directory"),
@@ -814,11 +2841,25 @@ fn run() -> Result<()> {
             let mut files = vec![];
             let mut size = ByteSize::mb(0);
             for path in &paths {
-                let res = get_files(path, &types, skip_errors)?;
-                for i in &res {
-                    size += i.metadata()?.len();
+                for file in get_files(path, &types, follow_symlinks, skip_errors, None)? {
+                    if has_extension(&file, &exclude_extension) {
+                        continue;
+                    }
+                    let len = file_size(&file)?;
+                    if let Some(max) = max_filesize {
+                        if len > max.as_u64() {
+                            cs_eyellowln!(
+                                "[!] Skipping '{}' as it exceeds the maximum file size ({} > {})",
+                                file.display(),
+                                ByteSize(len),
+                                max
+                            );
+                            continue;
+                        }
+                    }
+                    size += len;
+                    files.push(file);
                 }
-                files.extend(res);
             }
             if let Some(ext) = &extension {
                 cs_eprintln!(
@@ -851,21 +2892,85 @@ fn run() -> Result<()> {
             } else {
                 cs_eprintln!("[+] Loaded {} forensic files ({})", files.len(), size);
             }
+            let redact_key = redact_key_or_generate(&redact, redact_key);
             let mut searcher = Searcher::builder()
+                .fields(field)
                 .ignore_case(ignore_case)
+                .invert_match(invert_match)
                 .load_unknown(load_unknown)
                 .local(local)
+                .location(location)
+                .merge_repeated_data(merge_data)
+                .multiline(multiline)
+                .pattern_stats(pattern_stats || pattern_stats_json.is_some())
+                .redact(redact)
+                .redact_key(redact_key)
                 .skip_errors(skip_errors);
-            if let Some(patterns) = additional_pattern {
-                searcher = searcher.patterns(patterns);
+            if let Some(directory) = cache_parsed {
+                searcher = searcher.cache_parsed(directory);
+            }
+            let mut patterns = vec![];
+            if let Some(path) = &pattern_file {
+                let file = File::open(path)
+                    .with_context(|| format!("Unable to open pattern file - {}", path.display()))?;
+                let mut file_patterns = vec![];
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    let pattern = line.trim();
+                    if pattern.is_empty() || pattern.starts_with('#') {
+                        continue;
+                    }
+                    file_patterns.push(substitute(pattern, &vars)?);
+                }
+                if file_patterns.is_empty() {
+                    anyhow::bail!("Pattern file does not contain any patterns");
+                }
+                cs_eprintln!(
+                    "[+] Pattern file with {} pattern(s) loaded from {:?}",
+                    file_patterns.len(),
+                    fs::canonicalize(path).expect("could not get absolute path")
+                );
+                patterns.extend(file_patterns);
+            }
+            if let Some(additional) = additional_pattern {
+                for pattern in &additional {
+                    patterns.push(substitute(pattern, &vars)?);
+                }
             } else if let Some(pattern) = pattern {
-                searcher = searcher.patterns(vec![pattern]);
+                patterns.push(substitute(&pattern, &vars)?);
+            }
+            let mut highlights = vec![];
+            if highlight {
+                for pattern in &patterns {
+                    highlights.push(
+                        RegexBuilder::new(pattern)
+                            .case_insensitive(ignore_case)
+                            .dot_matches_new_line(multiline)
+                            .build()?,
+                    );
+                }
+            }
+            if !patterns.is_empty() {
+                searcher = searcher.patterns(patterns);
             }
+            let from = match last {
+                Some(last) => Some(
+                    chrono::Utc::now()
+                        .naive_utc()
+                        .checked_sub_signed(last)
+                        .ok_or_else(|| anyhow::anyhow!("--last is too large"))?,
+                ),
+                None => from,
+            };
             if let Some(from) = from {
                 searcher = searcher.from(from);
             }
             if let Some(tau) = tau {
-                searcher = searcher.tau(tau);
+                searcher = searcher.tau(
+                    tau.iter()
+                        .map(|t| substitute(t, &vars))
+                        .collect::<Result<Vec<_>>>()?,
+                );
             }
             if let Some(timestamp) = timestamp {
                 searcher = searcher.timestamp(timestamp);
@@ -882,8 +2987,12 @@ fn run() -> Result<()> {
                 cs_print!("[");
             }
             let mut hits = 0;
-            for file in &files {
-                for res in searcher.search(file)?.iter() {
+            let mut sorted = vec![];
+            // NOTE: Tail needs every hit before it knows which are last, so it buffers like sort.
+            let buffer = sort || tail.is_some();
+            'files: for (i, file) in files.iter().enumerate() {
+                let mut matches = 0;
+                for (j, res) in searcher.search(file)?.iter().enumerate() {
                     let hit = match res {
                         Ok(hit) => hit,
                         Err(e) => {
@@ -893,41 +3002,113 @@ fn run() -> Result<()> {
                             anyhow::bail!("Failed to search file... - {}", e);
                         }
                     };
-                    if json {
-                        if hits != 0 {
-                            cs_print!(",");
-                        }
-                        cs_print_json!(&hit)?;
-                    } else if jsonl {
-                        cs_print_json!(&hit)?;
-                        println!();
-                    } else {
-                        cs_println!("---");
-                        cs_print_yaml!(&hit)?;
+                    if count {
+                        matches += 1;
+                        hits += 1;
+                        continue;
+                    }
+                    if files_with_matches {
+                        // NOTE: The search is lazy, so we can stop at the first hit in the file.
+                        cs_println!("{}", file.display());
+                        hits += 1;
+                        break;
+                    }
+                    if buffer {
+                        sorted.push((searcher.timestamp(&hit), i, j, hit));
+                        continue;
+                    }
+                    if head == Some(hits) {
+                        break 'files;
                     }
+                    print_hit(&hit, json, jsonl, hits == 0, &highlights)?;
+                    hits += 1;
+                }
+                if count {
+                    cs_println!("{}:{}", file.display(), matches);
+                }
+            }
+            if buffer {
+                // NOTE: Hits without a timestamp are printed last.
+                sorted.sort_by_key(|x| (x.0.is_none(), x.0, x.1, x.2));
+                if let Some(n) = head {
+                    sorted.truncate(n);
+                }
+                if let Some(n) = tail {
+                    sorted.drain(..sorted.len().saturating_sub(n));
+                }
+                for (_, _, _, hit) in &sorted {
+                    print_hit(hit, json, jsonl, hits == 0, &highlights)?;
                     hits += 1;
                 }
             }
             if json {
                 cs_println!("]");
             }
-            cs_eprintln!("[+] Found {} hits", hits);
+            if summary_only {
+                eprintln!("[+] Found {} hits", hits);
+            } else {
+                cs_eprintln!("[+] Found {} hits", hits);
+            }
+            if let Some(stats) = searcher.pattern_stats() {
+                if let Some(path) = &pattern_stats_json {
+                    let stats = stats
+                        .iter()
+                        .map(|(pattern, hits)| {
+                            serde_json::json!({ "pattern": pattern, "hits": hits })
+                        })
+                        .collect::<Vec<_>>();
+                    let file = File::create(path).with_context(|| {
+                        format!("Unable to write pattern stats - {}", path.display())
+                    })?;
+                    serde_json::to_writer_pretty(file, &stats)?;
+                    cs_eprintln!("[+] Pattern stats written to: {}", path.display());
+                } else {
+                    cs_eprintln!("[+] Hits per pattern:");
+                    for (pattern, hits) in &stats {
+                        cs_eprintln!("{:>10}  {}", hits, pattern);
+                    }
+                    cs_eprintln!(
+                        "[+] {} of {} patterns had no hits",
+                        stats.iter().filter(|(_, hits)| *hits == 0).count(),
+                        stats.len()
+                    );
+                }
+            }
+        }
+        Command::Tz { filter } => {
+            init_writer(None, false, false, false, false)?;
+            let filter = filter.map(|f| f.to_lowercase());
+            for name in Timezone::names() {
+                if let Some(filter) = &filter {
+                    if !name.to_lowercase().contains(filter) {
+                        continue;
+                    }
+                }
+                cs_println!("{}", name);
+            }
         }
         Command::Analyse { cmd } => {
             match cmd {
                 AnalyseCommand::Shimcache {
                     additional_pattern,
                     amcache,
+                    assume_version,
+                    baseline,
+                    near_window,
                     output,
                     regex_file,
                     shimcache,
+                    sort,
+                    transaction_log,
                     ts_near_pair_matching,
+                    verbose,
                 } => {
-                    if !args.no_banner {
+                    if banner {
                         print_title();
                     }
-                    init_writer(output.clone(), true, false, false)?;
-                    let shimcache_analyzer = ShimcacheAnalyzer::new(shimcache, amcache);
+                    init_writer(output.clone(), true, false, false, false)?;
+                    let shimcache_analyzer =
+                        ShimcacheAnalyzer::new(shimcache, amcache, transaction_log, assume_version);
 
                     // Load regex
                     let mut regex_patterns: Vec<String> = Vec::new();
@@ -946,10 +3127,83 @@ fn run() -> Result<()> {
                         regex_patterns.append(&mut additional_patterns);
                     }
 
+                    let baseline = match &baseline {
+                        Some(path) => {
+                            let baseline = load_shimcache_baseline(path).with_context(|| {
+                                format!("Unable to load baseline - {}", path.display())
+                            })?;
+                            cs_eprintln!(
+                                "[+] Baseline with {} entries loaded from {:?}",
+                                baseline.len(),
+                                fs::canonicalize(path).expect("could not get absolute path")
+                            );
+                            Some(baseline)
+                        }
+                        None => None,
+                    };
+
                     // Do analysis
-                    let timeline = shimcache_analyzer
-                        .amcache_shimcache_timeline(&regex_patterns, ts_near_pair_matching)?;
-                    cli::print_shimcache_analysis_csv(&timeline)?;
+                    let mut timeline = shimcache_analyzer.amcache_shimcache_timeline(
+                        &regex_patterns,
+                        ts_near_pair_matching,
+                        near_window,
+                        verbose,
+                    )?;
+                    if let Some(order) = sort {
+                        sort_shimcache_timeline(&mut timeline, order);
+                    }
+                    cli::print_shimcache_analysis_csv(&timeline, verbose, baseline.as_ref())?;
+
+                    if let Some(output_path) = output {
+                        cs_eprintln!(
+                            "[+] Saved output to {:?}",
+                            std::fs::canonicalize(output_path)
+                                .expect("could not get absolute path")
+                        );
+                    }
+                }
+                AnalyseCommand::RecycleBin { path, output } => {
+                    if banner {
+                        print_title();
+                    }
+                    init_writer(output.clone(), true, false, false, false)?;
+                    let recyclebin_analyzer = RecycleBinAnalyzer::new(path);
+                    let entries = recyclebin_analyzer.recyclebin_entries()?;
+                    cli::print_recyclebin_analysis_csv(&entries)?;
+
+                    if let Some(output_path) = output {
+                        cs_eprintln!(
+                            "[+] Saved output to {:?}",
+                            std::fs::canonicalize(output_path)
+                                .expect("could not get absolute path")
+                        );
+                    }
+                }
+                AnalyseCommand::UserAssist { hives, output } => {
+                    if banner {
+                        print_title();
+                    }
+                    init_writer(output.clone(), true, false, false, false)?;
+                    let userassist_analyzer = UserAssistAnalyzer::new(hives);
+                    let entries = userassist_analyzer.userassist_entries()?;
+                    cli::print_userassist_analysis_csv(&entries)?;
+
+                    if let Some(output_path) = output {
+                        cs_eprintln!(
+                            "[+] Saved output to {:?}",
+                            std::fs::canonicalize(output_path)
+                                .expect("could not get absolute path")
+                        );
+                    }
+                }
+                AnalyseCommand::Lnk { path, output } => {
+                    if banner {
+                        print_title();
+                    }
+                    init_writer(output.clone(), true, false, false, false)?;
+                    let lnk_analyzer = LnkAnalyzer::new(path);
+                    let entries = lnk_analyzer.lnk_entries()?;
+                    cli::print_lnk_analysis_csv(&entries)?;
 
                     if let Some(output_path) = output {
                         cs_eprintln!(
@@ -966,8 +3220,19 @@ fn run() -> Result<()> {
 }
 
 fn main() {
-    if let Err(e) = run() {
-        if let Some(cause) = e.chain().nth(1) {
+    let argv = std::env::args_os().collect::<Vec<_>>();
+    let args = Args::parse_from(&argv);
+    let error_json = args.error_json;
+    // NOTE: The writer is closed even when the run fails, so that compressed output is finished.
+    let result = infer_format(args, &argv).and_then(run);
+    if let Err(e) = result.and(close_writer()) {
+        if error_json {
+            let error = serde_json::json!({
+                "error": e.to_string(),
+                "causes": e.chain().skip(1).map(|c| c.to_string()).collect::<Vec<_>>(),
+            });
+            eprintln!("{}", error);
+        } else if let Some(cause) = e.chain().nth(1) {
             cs_eredln!("[x] {} - {}", e, cause);
         } else {
             cs_eredln!("[x] {}", e);
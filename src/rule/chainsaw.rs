@@ -1,5 +1,6 @@
+use std::collections::HashSet;
 use std::fmt;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Read;
 use std::path::Path;
 
@@ -7,9 +8,10 @@ use serde::{
     de::{self, MapAccess, Visitor},
     Deserialize,
 };
+use serde_yaml::Value as Yaml;
 use tau_engine::core::{
     optimiser,
-    parser::{Expression, ModSym},
+    parser::{Expression, IdentifierParser, MatchType, ModSym, Pattern, Search},
 };
 
 use crate::file::Kind;
@@ -150,6 +152,10 @@ pub struct Rule {
     pub group: String,
     pub description: String,
     pub authors: Vec<String>,
+    #[serde(default)]
+    pub references: Option<Vec<String>>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
 
     pub kind: Kind,
     pub level: Level,
@@ -164,13 +170,23 @@ pub struct Rule {
     pub aggregate: Option<Aggregate>,
 }
 
-pub fn load(rule: &Path) -> crate::Result<Rule> {
+/// Parses the rule without running the optimiser over its filter.
+pub fn parse(rule: &Path) -> crate::Result<Rule> {
     let mut file = File::open(rule)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
 
-    let mut rule: Rule = serde_yaml::from_str(&contents)?;
-    rule.filter = match rule.filter {
+    Ok(serde_yaml::from_str(&contents)?)
+}
+
+pub fn load(rule: &Path) -> crate::Result<Rule> {
+    let mut rule = parse(rule)?;
+    rule.filter = optimise(rule.filter);
+    Ok(rule)
+}
+
+fn optimise(filter: Filter) -> Filter {
+    match filter {
         Filter::Detection(mut detection) => {
             detection.expression =
                 optimiser::coalesce(detection.expression, &detection.identifiers);
@@ -185,6 +201,178 @@ pub fn load(rule: &Path) -> crate::Result<Rule> {
             let expression = optimiser::rewrite(expression);
             optimiser::matrix(expression)
         }),
+    }
+}
+
+/// Rewrites the regex values in the rule's filter that the optimiser turns into plain string
+/// matches using the equivalent tau syntax, returning the fixed rule and the number of values that
+/// were changed, or `None` when there is nothing to fix.
+///
+/// NOTE: We work on the text of the rule so that its comments and formatting are kept. To stay on
+/// the safe side we bail if a value cannot be found in the text, or if the optimised logic of the
+/// fixed rule is not the same as that of the original.
+pub fn fix(rule: &Path) -> crate::Result<Option<(String, usize)>> {
+    let contents = fs::read_to_string(rule)?;
+    let yaml: Yaml = serde_yaml::from_str(&contents)?;
+    let mut values = vec![];
+    if let Some(filter) = yaml.get("filter") {
+        collect_values(filter, &mut values);
+    }
+
+    let mut fixed = contents.clone();
+    let mut count = 0;
+    let mut seen = HashSet::new();
+    for value in &values {
+        if !seen.insert(value) {
+            continue;
+        }
+        let replacement = match modernise(value) {
+            Some(replacement) => replacement,
+            None => continue,
+        };
+        let occurrences = values.iter().filter(|v| *v == value).count();
+        let (text, replaced) = replace_scalar(&fixed, value, &replacement);
+        if replaced != occurrences {
+            anyhow::bail!("could not safely locate '{}' in the rule", value);
+        }
+        fixed = text;
+        count += replaced;
+    }
+    if count == 0 {
+        return Ok(None);
+    }
+
+    let original = optimise(serde_yaml::from_str::<Rule>(&contents)?.filter);
+    let modernised = optimise(serde_yaml::from_str::<Rule>(&fixed)?.filter);
+    if logic(&original) != logic(&modernised) {
+        anyhow::bail!("the fixed rule would not be equivalent to the original");
+    }
+    Ok(Some((fixed, count)))
+}
+
+fn logic(filter: &Filter) -> String {
+    match filter {
+        Filter::Detection(detection) => detection.expression.to_string(),
+        Filter::Expression(expression) => expression.to_string(),
+    }
+}
+
+fn collect_values(yaml: &Yaml, values: &mut Vec<String>) {
+    match yaml {
+        Yaml::Mapping(mapping) => {
+            for value in mapping.values() {
+                collect_values(value, values);
+            }
+        }
+        Yaml::Sequence(sequence) => {
+            for value in sequence {
+                collect_values(value, values);
+            }
+        }
+        Yaml::String(value) => values.push(value.to_owned()),
+        _ => {}
+    }
+}
+
+/// The tau syntax for a regex value that the optimiser rewrites into a plain string match.
+fn modernise(value: &str) -> Option<String> {
+    let identifier = value.to_owned().into_identifier().ok()?;
+    let regex = match identifier.pattern {
+        Pattern::Regex(regex) => regex,
+        _ => return None,
     };
-    Ok(rule)
+    let search = Expression::Search(
+        Search::Regex(regex, identifier.ignore_case),
+        String::new(),
+        false,
+    );
+    let (pattern, ignore_case) = match optimiser::rewrite(search) {
+        Expression::Search(Search::AhoCorasick(_, patterns, ignore_case), _, _) => {
+            let pattern = match patterns.as_slice() {
+                [MatchType::Contains(s)] => Pattern::Contains(s.to_owned()),
+                [MatchType::EndsWith(s)] => Pattern::EndsWith(s.to_owned()),
+                [MatchType::Exact(s)] => Pattern::Exact(s.to_owned()),
+                [MatchType::StartsWith(s)] => Pattern::StartsWith(s.to_owned()),
+                _ => return None,
+            };
+            (pattern, ignore_case)
+        }
+        Expression::Search(Search::Contains(s), _, _) => (Pattern::Contains(s), false),
+        Expression::Search(Search::EndsWith(s), _, _) => (Pattern::EndsWith(s), false),
+        Expression::Search(Search::Exact(s), _, _) => (Pattern::Exact(s), false),
+        Expression::Search(Search::StartsWith(s), _, _) => (Pattern::StartsWith(s), false),
+        _ => return None,
+    };
+    let prefix = if ignore_case { "i" } else { "" };
+    let modernised = match &pattern {
+        Pattern::Contains(s) => format!("{}*{}*", prefix, s),
+        Pattern::EndsWith(s) => format!("{}*{}", prefix, s),
+        Pattern::Exact(s) => format!("{}{}", prefix, s),
+        Pattern::StartsWith(s) => format!("{}{}*", prefix, s),
+        _ => return None,
+    };
+    // NOTE: A literal that itself looks like tau syntax would be read back differently, so we
+    // only accept values that parse back into the same search.
+    let identifier = modernised.clone().into_identifier().ok()?;
+    let same = match (&identifier.pattern, &pattern) {
+        (Pattern::Contains(x), Pattern::Contains(y))
+        | (Pattern::EndsWith(x), Pattern::EndsWith(y))
+        | (Pattern::Exact(x), Pattern::Exact(y))
+        | (Pattern::StartsWith(x), Pattern::StartsWith(y)) => x == y,
+        _ => false,
+    };
+    if same && identifier.ignore_case == ignore_case {
+        Some(modernised)
+    } else {
+        None
+    }
+}
+
+/// Replaces the scalar values that are equal to `old`, when they are the only value on a line.
+fn replace_scalar(contents: &str, old: &str, new: &str) -> (String, usize) {
+    let mut forms = vec![format!("'{}'", old.replace('\'', "''")), old.to_owned()];
+    if !old.contains(['\\', '"']) {
+        forms.push(format!("\"{}\"", old));
+    }
+    let quoted = format!("'{}'", new.replace('\'', "''"));
+    let mut count = 0;
+    let mut fixed = String::with_capacity(contents.len());
+    for line in contents.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        let head = forms.iter().find_map(|form| {
+            let head = trimmed.strip_suffix(form.as_str())?;
+            let marker = head.trim_end();
+            if head.len() > marker.len() && (marker.ends_with(':') || marker.trim_start() == "-") {
+                Some(head)
+            } else {
+                None
+            }
+        });
+        match head {
+            Some(head) => {
+                fixed.push_str(head);
+                fixed.push_str(&quoted);
+                fixed.push_str(&line[trimmed.len()..]);
+                count += 1;
+            }
+            None => fixed.push_str(line),
+        }
+    }
+    (fixed, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_scalar() {
+        let contents = "filter:\n  a:\n    Image: '?foo$' # comment\n    CommandLine:\n      - '?foo$'\n      - ?foo$\n    Path: ?foo$\r\n";
+        let (fixed, count) = replace_scalar(contents, "?foo$", "*foo");
+        assert_eq!(count, 3);
+        assert_eq!(
+            fixed,
+            "filter:\n  a:\n    Image: '?foo$' # comment\n    CommandLine:\n      - '*foo'\n      - '*foo'\n    Path: '*foo'\r\n"
+        );
+    }
 }
@@ -0,0 +1,46 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use crate::rule::{Level, Status};
+
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub name: String,
+    pub keyword: String,
+
+    pub authors: Vec<String>,
+    pub level: Level,
+    pub status: Status,
+}
+
+impl Rule {
+    pub fn new(keyword: String) -> Self {
+        Self {
+            name: format!("Keyword: {}", keyword),
+            keyword,
+
+            authors: vec![],
+            level: Level::Medium,
+            status: Status::Stable,
+        }
+    }
+}
+
+pub fn load(path: &Path) -> crate::Result<Vec<Rule>> {
+    let file = File::open(path)?;
+    let mut rules = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let keyword = line.trim();
+        // NOTE: We allow comments so that indicators can be annotated with their source.
+        if keyword.is_empty() || keyword.starts_with('#') {
+            continue;
+        }
+        rules.push(Rule::new(keyword.to_owned()));
+    }
+    if rules.is_empty() {
+        anyhow::bail!("keyword file does not contain any keywords");
+    }
+    Ok(rules)
+}
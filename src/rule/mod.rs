@@ -1,5 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fmt;
+use std::fs;
 use std::path::Path;
 use std::str::FromStr;
 
@@ -16,14 +17,17 @@ use tau_engine::{
 use crate::file::Kind as FileKind;
 
 pub use self::chainsaw::Rule as Chainsaw;
+pub use self::keyword::Rule as Keyword;
 pub use self::sigma::Rule as Sigma;
 
 pub mod chainsaw;
+pub mod keyword;
 pub mod sigma;
 
 #[derive(Clone, Debug)]
 pub enum Rule {
     Chainsaw(Chainsaw),
+    Keyword(Keyword),
     Sigma(Sigma),
 }
 
@@ -32,22 +36,63 @@ impl Rule {
     pub fn aggregate(&self) -> &Option<Aggregate> {
         match self {
             Self::Chainsaw(c) => &c.aggregate,
+            Self::Keyword(_) => &None,
             Self::Sigma(s) => &s.aggregate,
         }
     }
 
+    #[inline]
+    pub fn kind(&self) -> Kind {
+        match self {
+            Self::Chainsaw(_) => Kind::Chainsaw,
+            Self::Keyword(_) => Kind::Keyword,
+            Self::Sigma(_) => Kind::Sigma,
+        }
+    }
+
     #[inline]
     pub fn is_kind(&self, kind: &Kind) -> bool {
         match self {
             Self::Chainsaw(_) => kind == &Kind::Chainsaw,
+            Self::Keyword(_) => kind == &Kind::Keyword,
             Self::Sigma(_) => kind == &Kind::Sigma,
         }
     }
 
+    /// The event ids that the rule checks for, where empty means it could apply to any event.
+    pub fn event_ids(&self) -> HashSet<i64> {
+        // NOTE: Sigma rules reference the event id by its Sigma field name rather than its path.
+        const FIELDS: [&str; 2] = ["Event.System.EventID", "EventID"];
+        let (expression, identifiers) = match self {
+            Self::Chainsaw(c) => match &c.filter {
+                Filter::Detection(detection) => {
+                    (&detection.expression, Some(&detection.identifiers))
+                }
+                Filter::Expression(expression) => (expression, None),
+            },
+            Self::Keyword(_) => return HashSet::new(),
+            Self::Sigma(s) => (
+                &s.tau.detection.expression,
+                Some(&s.tau.detection.identifiers),
+            ),
+        };
+        crate::ext::tau::extract_equalities(expression, identifiers, &FIELDS).unwrap_or_default()
+    }
+
+    #[inline]
+    pub fn id(&self) -> Option<&String> {
+        match self {
+            Self::Chainsaw(_) => None,
+            Self::Keyword(_) => None,
+            Self::Sigma(s) => s.id.as_ref(),
+        }
+    }
+
     #[inline]
     pub fn level(&self) -> &Level {
         match self {
             Self::Chainsaw(c) => &c.level,
+            Self::Keyword(k) => &k.level,
             Self::Sigma(s) => &s.level,
         }
     }
@@ -56,6 +101,8 @@ impl Rule {
     pub fn types(&self) -> &FileKind {
         match self {
             Self::Chainsaw(c) => &c.kind,
+            // NOTE: Keywords are hunted for in several kinds of document, see the keyword hunts.
+            Self::Keyword(_) => &FileKind::Unknown,
             Self::Sigma(_) => &FileKind::Unknown,
         }
     }
@@ -64,28 +111,107 @@ impl Rule {
     pub fn name(&self) -> &String {
         match self {
             Self::Chainsaw(c) => &c.name,
+            Self::Keyword(k) => &k.name,
             Self::Sigma(s) => &s.name,
         }
     }
 
+    #[inline]
+    pub fn tags(&self) -> &[String] {
+        match self {
+            Self::Chainsaw(c) => c.tags.as_deref().unwrap_or_default(),
+            Self::Keyword(_) => &[],
+            Self::Sigma(s) => s.tags.as_deref().unwrap_or_default(),
+        }
+    }
+
     #[inline]
     pub fn solve(&self, document: &dyn Document) -> bool {
         match self {
-            Self::Chainsaw(c) => match &c.filter {
-                Filter::Detection(detection) => tau_engine::solve(detection, document),
-                Filter::Expression(expression) => tau_engine::core::solve(expression, document),
-            },
+            Self::Chainsaw(c) => c.filter.solve(document),
+            // NOTE: Keywords match on the whole document which is not possible through the
+            // `Document` trait, so they are solved by the hunter directly.
+            Self::Keyword(_) => false,
             Self::Sigma(s) => tau_engine::solve(&s.tau.detection, document),
         }
     }
 
+    /// The field values in the document that caused the rule to match, see [`Filter::matches`].
+    pub fn matches(&self, document: &dyn Document) -> BTreeMap<String, String> {
+        match self {
+            Self::Chainsaw(c) => c.filter.matches(document),
+            Self::Keyword(_) => BTreeMap::new(),
+            Self::Sigma(s) => crate::ext::tau::matched_fields(
+                &s.tau.detection.expression,
+                Some(&s.tau.detection.identifiers),
+                document,
+            ),
+        }
+    }
+
+    /// The rule's logic annotated against the document, see [`Filter::explain`].
+    pub fn explain(&self, document: &dyn Document) -> Option<String> {
+        match self {
+            Self::Chainsaw(c) => Some(c.filter.explain(document)),
+            Self::Keyword(_) => None,
+            Self::Sigma(s) => Some(crate::ext::tau::explain(
+                &s.tau.detection.expression,
+                Some(&s.tau.detection.identifiers),
+                document,
+            )),
+        }
+    }
+
     #[inline]
     pub fn status(&self) -> &Status {
         match self {
             Self::Chainsaw(c) => &c.status,
+            Self::Keyword(k) => &k.status,
             Self::Sigma(s) => &s.status,
         }
     }
+
+    /// The rule as it was loaded, in a form that can be serialised.
+    pub fn parsed(&self) -> Parsed {
+        let logic = match self {
+            Self::Chainsaw(c) => match &c.filter {
+                Filter::Detection(detection) => Logic::Detection(detection),
+                Filter::Expression(expression) => Logic::Expression(expression.to_string()),
+            },
+            Self::Keyword(k) => Logic::Keyword(&k.keyword),
+            Self::Sigma(s) => Logic::Detection(&s.tau.detection),
+        };
+        Parsed {
+            kind: self.kind(),
+            name: self.name(),
+            id: self.id(),
+            level: self.level(),
+            status: self.status(),
+            tags: self.tags(),
+            logic,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Parsed<'a> {
+    pub kind: Kind,
+    pub name: &'a String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<&'a String>,
+    pub level: &'a Level,
+    pub status: &'a Status,
+    pub tags: &'a [String],
+    pub logic: Logic<'a>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Logic<'a> {
+    Detection(&'a Detection),
+    // NOTE: Tau cannot serialise expressions, so they are given in their text form.
+    Expression(String),
+    Keyword(&'a String),
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -103,10 +229,93 @@ pub enum Filter {
     Expression(Expression),
 }
 
+impl Filter {
+    /// The distinct field paths referenced by the filter.
+    pub fn fields(&self) -> HashSet<String> {
+        match self {
+            Self::Detection(detection) => {
+                let mut fields = crate::ext::tau::extract_fields(&detection.expression);
+                for expression in detection.identifiers.values() {
+                    fields.extend(crate::ext::tau::extract_fields(expression));
+                }
+                fields
+            }
+            Self::Expression(expression) => crate::ext::tau::extract_fields(expression),
+        }
+    }
+
+    #[inline]
+    pub fn solve(&self, document: &dyn Document) -> bool {
+        match self {
+            Self::Detection(detection) => tau_engine::solve(detection, document),
+            Self::Expression(expression) => tau_engine::core::solve(expression, document),
+        }
+    }
+
+    /// The field values in the document that caused the filter to match.
+    pub fn matches(&self, document: &dyn Document) -> BTreeMap<String, String> {
+        match self {
+            Self::Detection(detection) => crate::ext::tau::matched_fields(
+                &detection.expression,
+                Some(&detection.identifiers),
+                document,
+            ),
+            Self::Expression(expression) => {
+                crate::ext::tau::matched_fields(expression, None, document)
+            }
+        }
+    }
+
+    /// The filter's logic as an indented tree, with each part marked by whether it was true for
+    /// the document.
+    pub fn explain(&self, document: &dyn Document) -> String {
+        match self {
+            Self::Detection(detection) => crate::ext::tau::explain(
+                &detection.expression,
+                Some(&detection.identifiers),
+                document,
+            ),
+            Self::Expression(expression) => crate::ext::tau::explain(expression, None, document),
+        }
+    }
+
+    /// Problems with the filter that do not stop it from loading, but are likely to be mistakes.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        let expression = match self {
+            Self::Detection(detection) => {
+                let used = crate::ext::tau::extract_identifiers(&detection.expression);
+                let mut unused = detection
+                    .identifiers
+                    .keys()
+                    .filter(|i| !used.contains(*i))
+                    .collect::<Vec<_>>();
+                unused.sort();
+                for identifier in unused {
+                    warnings.push(format!(
+                        "identifier '{}' is not used by the condition",
+                        identifier
+                    ));
+                }
+                optimiser::coalesce(detection.expression.clone(), &detection.identifiers)
+            }
+            Self::Expression(expression) => expression.clone(),
+        };
+        let expression = optimiser::shake(expression);
+        let expression = optimiser::rewrite(expression);
+        let expression = optimiser::matrix(expression);
+        if let Expression::Boolean(b) = expression {
+            warnings.push(format!("condition is always {}", b));
+        }
+        warnings
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Kind {
     Chainsaw,
+    Keyword,
     Sigma,
 }
 
@@ -120,6 +329,7 @@ impl fmt::Display for Kind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Chainsaw => write!(f, "chainsaw"),
+            Self::Keyword => write!(f, "keyword"),
             Self::Sigma => write!(f, "sigma"),
         }
     }
@@ -131,8 +341,9 @@ impl FromStr for Kind {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let v = match s {
             "chainsaw" => Self::Chainsaw,
+            "keyword" => Self::Keyword,
             "sigma" => Self::Sigma,
-            _ => anyhow::bail!("unknown kind, must be: chainsaw, or sigma"),
+            _ => anyhow::bail!("unknown kind, must be: chainsaw, keyword or sigma"),
         };
         Ok(v)
     }
@@ -176,11 +387,12 @@ impl FromStr for Level {
     }
 }
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq, Deserialize, Serialize)]
+// NOTE: The variants are ordered by maturity, so that statuses can be compared.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum Status {
-    Stable,
     Experimental,
+    Stable,
 }
 
 impl fmt::Display for Status {
@@ -204,6 +416,25 @@ impl FromStr for Status {
         Ok(v)
     }
 }
+
+/// Infers the kind of a rule from the shape of its first document, returning `None` when it does
+/// not look like any of them. A document with both a `kind` and `filter` is treated as a Chainsaw
+/// rule, and only then is one with a `detection` treated as a Sigma rule, so Chainsaw wins when a
+/// document is ambiguous.
+pub fn infer_kind(path: &Path) -> Option<Kind> {
+    let contents = fs::read_to_string(path).ok()?;
+    let document = serde_yaml::Deserializer::from_str(&contents).next()?;
+    let value = serde_yaml::Value::deserialize(document).ok()?;
+    let mapping = value.as_mapping()?;
+    if mapping.contains_key("kind") && mapping.contains_key("filter") {
+        Some(Kind::Chainsaw)
+    } else if mapping.contains_key("detection") {
+        Some(Kind::Sigma)
+    } else {
+        None
+    }
+}
+
 pub fn load(
     kind: Kind,
     path: &Path,
@@ -211,9 +442,11 @@ pub fn load(
     levels: &Option<HashSet<Level>>,
     statuses: &Option<HashSet<Status>>,
 ) -> crate::Result<Vec<Rule>> {
-    if let Some(x) = path.extension() {
-        if x != "yml" && x != "yaml" {
-            anyhow::bail!("rule must have a yaml file extension");
+    if kind != Kind::Keyword {
+        if let Some(x) = path.extension() {
+            if x != "yml" && x != "yaml" {
+                anyhow::bail!("rule must have a yaml file extension");
+            }
         }
     }
     let mut rules = match kind {
@@ -226,6 +459,17 @@ pub fn load(
             let rule = chainsaw::load(path)?;
             vec![Rule::Chainsaw(rule)]
         }
+        Kind::Keyword => {
+            if let Some(kinds) = kinds.as_ref() {
+                if !kinds.contains(&Kind::Keyword) {
+                    return Ok(vec![]);
+                }
+            }
+            keyword::load(path)?
+                .into_iter()
+                .map(Rule::Keyword)
+                .collect()
+        }
         Kind::Sigma => {
             if let Some(kinds) = kinds.as_ref() {
                 if !kinds.contains(&Kind::Sigma) {
@@ -238,8 +482,11 @@ pub fn load(
                 .collect::<Result<Vec<_>, _>>()
             {
                 Ok(rules) => rules,
-                Err(_) => {
-                    anyhow::bail!("failed to load rule, run the linter for more information");
+                Err(e) => {
+                    anyhow::bail!(
+                        "failed to load rule, run the linter for more information - {}",
+                        e
+                    );
                 }
             };
             sigma
@@ -267,14 +514,27 @@ pub fn load(
     Ok(rules)
 }
 
+/// Modernises the syntax of the rule, see [`chainsaw::fix`].
+pub fn fix(kind: &Kind, path: &Path) -> crate::Result<Option<(String, usize)>> {
+    match kind {
+        Kind::Chainsaw => chainsaw::fix(path),
+        Kind::Keyword | Kind::Sigma => {
+            anyhow::bail!("fixing is only supported for chainsaw rules")
+        }
+    }
+}
+
 pub fn lint(kind: &Kind, path: &Path) -> crate::Result<Vec<Filter>> {
-    if let Some(x) = path.extension() {
-        if x != "yml" && x != "yaml" {
-            anyhow::bail!("rule must have a yaml file extension");
+    if kind != &Kind::Keyword {
+        if let Some(x) = path.extension() {
+            if x != "yml" && x != "yaml" {
+                anyhow::bail!("rule must have a yaml file extension");
+            }
         }
     }
     let detections = match kind {
-        Kind::Chainsaw => match chainsaw::load(path) {
+        // NOTE: The filter is returned as parsed, so that the caller can decide whether to optimise.
+        Kind::Chainsaw => match chainsaw::parse(path) {
             Ok(rule) => {
                 vec![rule.filter]
             }
@@ -282,6 +542,11 @@ pub fn lint(kind: &Kind, path: &Path) -> crate::Result<Vec<Filter>> {
                 anyhow::bail!("{}", e);
             }
         },
+        // NOTE: Keywords have no filter logic to lint, so we just make sure that they load.
+        Kind::Keyword => {
+            keyword::load(path)?;
+            vec![]
+        }
         Kind::Sigma => match sigma::load(path) {
             Ok(yamls) => {
                 let sigma = yamls
@@ -304,3 +569,83 @@ pub fn lint(kind: &Kind, path: &Path) -> crate::Result<Vec<Filter>> {
     };
     Ok(detections)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_ids() {
+        let root = tempfile::tempdir().unwrap();
+        let event_ids = |filter: &str| {
+            let path = root.path().join("rule.yml");
+            fs::write(
+                &path,
+                format!(
+                    "title: test\ngroup: test\ndescription: test\nauthors: [test]\nkind: evtx\n\
+                     level: info\nstatus: stable\ntimestamp: Event.System.TimeCreated\n\
+                     fields: []\nfilter:\n{}",
+                    filter
+                ),
+            )
+            .unwrap();
+            let rules = load(Kind::Chainsaw, &path, &None, &None, &None).unwrap();
+            let mut ids = rules[0].event_ids().into_iter().collect::<Vec<_>>();
+            ids.sort();
+            ids
+        };
+        let ids = |condition: &str| {
+            event_ids(
+                &[
+                    format!("  condition: {}", condition).as_str(),
+                    "  logon:",
+                    "    Event.System.EventID: 4624",
+                    "  failed:",
+                    "    Event.System.EventID: 4625",
+                    "  user:",
+                    "    Event.EventData.TargetUserName: admin",
+                ]
+                .join("\n"),
+            )
+        };
+
+        assert_eq!(ids("logon"), vec![4624]);
+        assert_eq!(ids("logon and user"), vec![4624]);
+        assert_eq!(ids("logon or failed"), vec![4624, 4625]);
+        // Negated or mixed with other fields, the rule could apply to any event
+        assert_eq!(ids("not logon"), Vec::<i64>::new());
+        assert_eq!(ids("user and not logon"), Vec::<i64>::new());
+        assert_eq!(ids("logon or user"), Vec::<i64>::new());
+        // Identifiers that the condition does not use are ignored
+        assert_eq!(ids("user"), Vec::<i64>::new());
+    }
+
+    #[test]
+    fn test_infer_kind() {
+        let root = tempfile::tempdir().unwrap();
+        let infer = |name: &str, contents: &str| {
+            let path = root.path().join(name);
+            fs::write(&path, contents).unwrap();
+            infer_kind(&path)
+        };
+        assert_eq!(
+            infer("chainsaw.yml", "kind: evtx\nfilter:\n  Provider: foo\n"),
+            Some(Kind::Chainsaw)
+        );
+        assert_eq!(
+            infer("sigma.yml", "title: foo\ndetection:\n  condition: all\n"),
+            Some(Kind::Sigma)
+        );
+        // NOTE: Chainsaw wins when the document could be either.
+        assert_eq!(
+            infer(
+                "ambiguous.yml",
+                "kind: evtx\nfilter:\n  Provider: foo\ndetection:\n  condition: all\n"
+            ),
+            Some(Kind::Chainsaw)
+        );
+        assert_eq!(infer("neither.yml", "title: foo\n"), None);
+        assert_eq!(infer("invalid.yml", "title: [broken"), None);
+        assert_eq!(infer_kind(&root.path().join("missing.yml")), None);
+    }
+}
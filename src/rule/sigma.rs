@@ -41,6 +41,18 @@ pub struct Rule {
     pub tags: Option<Vec<String>>,
 }
 
+/// The rule fields that can be looked up through its [`Document`] implementation.
+pub const FIELDS: &[&str] = &[
+    "id",
+    "level",
+    "logsource.category",
+    "logsource.definition",
+    "logsource.product",
+    "logsource.service",
+    "status",
+    "title",
+];
+
 impl Document for Rule {
     fn find(&self, key: &str) -> Option<tau_engine::Value> {
         use tau_engine::Value as Tau;
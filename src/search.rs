@@ -1,41 +1,74 @@
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
-use chrono_tz::Tz;
-use regex::{RegexSet, RegexSetBuilder};
-use serde_json::Value as Json;
+use regex::{Regex, RegexBuilder, RegexSet, RegexSetBuilder};
+use serde::Serialize;
+use serde_json::{json, Value as Json};
 use tau_engine::{
     core::parser::{BoolSym, Expression},
     Document as Doc,
 };
 
-use crate::ext;
-use crate::file::{Document, Documents, Reader};
+use crate::ext::{self, timezone::Timezone};
+use crate::file::{archive, cache::Cache, Document, Documents, Kind as FileKind, Reader};
+
+/// The field that a hit's [`Location`] is recorded under when requested.
+pub const LOCATION_FIELD: &str = "chainsaw_location";
+
+/// The position of a hit within the artefact it was found in.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Location {
+    Index(usize),
+    Key(String),
+    Line(usize),
+    Record(u64),
+}
 
 pub struct Hits<'a> {
-    reader: Reader,
+    reader: Option<Reader>,
     searcher: &'a SearcherInner,
+    text: Vec<Json>,
 }
 
 impl<'a> Hits<'a> {
     pub fn iter(&mut self) -> Iter<'_> {
         Iter {
-            documents: self.reader.documents(),
+            index: 0,
+            kind: self
+                .reader
+                .as_ref()
+                .map(|r| r.kind())
+                .unwrap_or(FileKind::Unknown),
+            documents: self.reader.as_mut().map(|r| r.documents()),
             searcher: self.searcher,
+            text: std::mem::take(&mut self.text).into_iter(),
         }
     }
 }
 
 pub struct Iter<'a> {
-    documents: Documents<'a>,
+    documents: Option<Documents<'a>>,
+    index: usize,
+    kind: FileKind,
     searcher: &'a SearcherInner,
+    text: std::vec::IntoIter<Json>,
 }
 
 impl<'a> Iterator for Iter<'a> {
     type Item = crate::Result<Json>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        for document in self.documents.by_ref() {
+        if let Some(hit) = self.text.next() {
+            return Some(Ok(hit));
+        }
+        for document in self.documents.as_mut()? {
+            let index = self.index;
+            self.index += 1;
             let document = match document {
                 Ok(document) => document,
                 Err(e) => {
@@ -45,6 +78,13 @@ impl<'a> Iterator for Iter<'a> {
                     return Some(Err(e));
                 }
             };
+            let document = match document {
+                Document::Evtx(mut evtx) if self.searcher.merge_repeated_data => {
+                    crate::evtx::merge_repeated_data(&mut evtx.data);
+                    Document::Evtx(evtx)
+                }
+                document => document,
+            };
             if self.searcher.timestamp.is_some()
                 && (self.searcher.from.is_some() || self.searcher.to.is_some())
             {
@@ -134,38 +174,79 @@ impl<'a> Iterator for Iter<'a> {
                 }
             }
             // TODO: Remove duplication...
-            match document {
+            let (mut json, location) = match document {
                 Document::Evtx(evtx) => {
                     let wrapper = crate::evtx::WrapperLegacy(&evtx.data);
-                    if let Some(expression) = &self.searcher.tau {
-                        if !tau_engine::core::solve(expression, &wrapper) {
-                            continue;
-                        }
-                        if self.searcher.regex.is_empty() {
-                            return Some(Ok(evtx.data));
+                    let matched = match &self.searcher.tau {
+                        Some(expression) => {
+                            tau_engine::core::solve(expression, &wrapper)
+                                && (self.searcher.regex.is_empty()
+                                    || self.searcher.matches(&wrapper, &evtx))
                         }
+                        None => self.searcher.matches(&wrapper, &evtx),
+                    };
+                    if matched == self.searcher.invert_match {
+                        continue;
                     }
-                    if evtx.matches(&self.searcher.regex) {
-                        return Some(Ok(evtx.data));
-                    }
+                    self.searcher.count(&wrapper, &evtx);
+                    (evtx.data, Location::Record(evtx.event_record_id))
                 }
                 Document::Hve(json)
                 | Document::Json(json)
                 | Document::Xml(json)
                 | Document::Mft(json) => {
-                    if let Some(expression) = &self.searcher.tau {
-                        if !tau_engine::core::solve(expression, &json) {
-                            continue;
-                        }
-                        if self.searcher.regex.is_empty() {
-                            return Some(Ok(json));
+                    let matched = match &self.searcher.tau {
+                        Some(expression) => {
+                            tau_engine::core::solve(expression, &json)
+                                && (self.searcher.regex.is_empty()
+                                    || self.searcher.matches(&json, &json))
                         }
+                        None => self.searcher.matches(&json, &json),
+                    };
+                    if matched == self.searcher.invert_match {
+                        continue;
                     }
-                    if json.matches(&self.searcher.regex) {
+                    self.searcher.count(&json, &json);
+                    let location = match self.kind {
+                        FileKind::Hve => match json.get("path").and_then(|p| p.as_str()) {
+                            Some(path) => Location::Key(path.to_owned()),
+                            None => Location::Index(index),
+                        },
+                        FileKind::Jsonl => Location::Line(index + 1),
+                        _ => Location::Index(index),
+                    };
+                    (json, location)
+                }
+            };
+            crate::ext::redact::redact(&mut json, &self.searcher.redact, &self.searcher.redact_key);
+            if self.searcher.location {
+                let recordable = json
+                    .as_object()
+                    .map(|map| !map.contains_key(LOCATION_FIELD))
+                    .unwrap_or_default();
+                if !recordable {
+                    let e = anyhow::anyhow!(
+                        "could not record {:?} under '{}' as the document is not an object or \
+                        already has that field",
+                        location,
+                        LOCATION_FIELD
+                    );
+                    if self.searcher.skip_errors {
+                        cs_eyellowln!("{}", e);
                         return Some(Ok(json));
                     }
+                    return Some(Err(e));
                 }
-            };
+                match serde_json::to_value(location) {
+                    Ok(location) => {
+                        if let Some(map) = json.as_object_mut() {
+                            map.insert(LOCATION_FIELD.to_owned(), location);
+                        }
+                    }
+                    Err(e) => return Some(Err(e.into())),
+                }
+            }
+            return Some(Ok(json));
         }
         None
     }
@@ -173,20 +254,31 @@ impl<'a> Iterator for Iter<'a> {
 
 pub trait Searchable {
     fn matches(&self, regex: &RegexSet) -> bool;
+    /// The indexes of the patterns in the set that match.
+    fn patterns(&self, regex: &RegexSet) -> Vec<usize>;
 }
 
 #[derive(Default)]
 pub struct SearcherBuilder {
     patterns: Option<Vec<String>>,
 
+    cache_parsed: Option<PathBuf>,
+    fields: Option<Vec<String>>,
     from: Option<NaiveDateTime>,
     ignore_case: Option<bool>,
+    invert_match: Option<bool>,
     load_unknown: Option<bool>,
     local: Option<bool>,
+    location: Option<bool>,
+    merge_repeated_data: Option<bool>,
+    multiline: Option<bool>,
+    pattern_stats: Option<bool>,
+    redact: Option<Vec<String>>,
+    redact_key: Option<String>,
     skip_errors: Option<bool>,
     tau: Option<Vec<String>>,
     timestamp: Option<String>,
-    timezone: Option<Tz>,
+    timezone: Option<Timezone>,
     to: Option<NaiveDateTime>,
 }
 
@@ -196,16 +288,24 @@ impl SearcherBuilder {
     }
 
     pub fn build(self) -> crate::Result<Searcher> {
+        let cache_parsed = match self.cache_parsed {
+            Some(directory) => Some(Cache::new(directory)?),
+            None => None,
+        };
+        let fields = self.fields.unwrap_or_default();
         let ignore_case = self.ignore_case.unwrap_or_default();
+        let invert_match = self.invert_match.unwrap_or_default();
         let load_unknown = self.load_unknown.unwrap_or_default();
         let local = self.local.unwrap_or_default();
+        let multiline = self.multiline.unwrap_or_default();
         let patterns = self.patterns.unwrap_or_default();
+        let pattern_stats = self.pattern_stats.unwrap_or_default();
         let skip_errors = self.skip_errors.unwrap_or_default();
         let tau = match self.tau {
             Some(kvs) => {
                 let mut expressions = Vec::with_capacity(kvs.len());
                 for kv in &kvs {
-                    expressions.push(ext::tau::parse_kv(kv)?);
+                    expressions.push(ext::tau::parse_kv(kv, ignore_case)?);
                 }
                 if expressions.is_empty() {
                     None
@@ -216,17 +316,50 @@ impl SearcherBuilder {
             None => None,
         };
 
-        let regex = RegexSetBuilder::new(patterns)
+        let regex = RegexSetBuilder::new(&patterns)
             .case_insensitive(ignore_case)
+            .dot_matches_new_line(multiline)
             .build()?;
+        // NOTE: A set can only tell us which patterns matched and not where, so when matching
+        // plain text we also need each pattern on its own to pull out the matches.
+        let mut regexes = vec![];
+        if multiline {
+            for pattern in &patterns {
+                regexes.push(
+                    RegexBuilder::new(pattern)
+                        .case_insensitive(ignore_case)
+                        .dot_matches_new_line(true)
+                        .build()?,
+                );
+            }
+        }
+
+        let stats = if pattern_stats {
+            Some(Mutex::new(vec![0; patterns.len()]))
+        } else {
+            None
+        };
 
         Ok(Searcher {
             inner: SearcherInner {
                 regex,
+                regexes,
+                stats,
 
+                cache_parsed,
+                fields,
                 from: self.from.map(|d| DateTime::from_utc(d, Utc)),
+                invert_match,
                 load_unknown,
                 local,
+                location: self.location.unwrap_or_default(),
+                merge_repeated_data: self.merge_repeated_data.unwrap_or_default(),
+                multiline,
+                redact: self.redact.unwrap_or_default(),
+                redact_key: self
+                    .redact_key
+                    .unwrap_or_else(crate::ext::redact::generate_key)
+                    .into_bytes(),
                 skip_errors,
                 tau,
                 timestamp: self.timestamp,
@@ -236,6 +369,19 @@ impl SearcherBuilder {
         })
     }
 
+    /// A directory to reuse parsed documents from, and to write them to for artefacts that have not
+    /// been parsed before, see [`Cache`].
+    pub fn cache_parsed(mut self, directory: PathBuf) -> Self {
+        self.cache_parsed = Some(directory);
+        self
+    }
+
+    /// Only match the patterns against these fields rather than the whole document.
+    pub fn fields(mut self, fields: Vec<String>) -> Self {
+        self.fields = Some(fields);
+        self
+    }
+
     pub fn from(mut self, datetime: NaiveDateTime) -> Self {
         self.from = Some(datetime);
         self
@@ -246,6 +392,11 @@ impl SearcherBuilder {
         self
     }
 
+    pub fn invert_match(mut self, invert: bool) -> Self {
+        self.invert_match = Some(invert);
+        self
+    }
+
     pub fn load_unknown(mut self, allow: bool) -> Self {
         self.load_unknown = Some(allow);
         self
@@ -256,11 +407,50 @@ impl SearcherBuilder {
         self
     }
 
+    /// Record where each hit was found in its artefact under a `chainsaw_location` field.
+    pub fn location(mut self, location: bool) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    /// Collect the event log data values that repeat a name into an array under that name, see
+    /// [`crate::evtx::merge_repeated_data`].
+    pub fn merge_repeated_data(mut self, merge: bool) -> Self {
+        self.merge_repeated_data = Some(merge);
+        self
+    }
+
+    /// Match patterns across lines, searching plain text files that are not a known format as a
+    /// whole. This requires `load_unknown` and holds each plain text file in memory.
+    pub fn multiline(mut self, multiline: bool) -> Self {
+        self.multiline = Some(multiline);
+        self
+    }
+
     pub fn patterns(mut self, patterns: Vec<String>) -> Self {
         self.patterns = Some(patterns);
         self
     }
 
+    /// Count the hits for each pattern, see [`Searcher::pattern_stats`].
+    pub fn pattern_stats(mut self, stats: bool) -> Self {
+        self.pattern_stats = Some(stats);
+        self
+    }
+
+    /// Fields to replace with a keyed hash in the hits, this is applied after matching so does not
+    /// affect what is matched.
+    pub fn redact(mut self, fields: Vec<String>) -> Self {
+        self.redact = Some(fields);
+        self
+    }
+
+    /// The key to hash the redacted values with, a random one is used when this is not set.
+    pub fn redact_key(mut self, key: String) -> Self {
+        self.redact_key = Some(key);
+        self
+    }
+
     pub fn skip_errors(mut self, skip: bool) -> Self {
         self.skip_errors = Some(skip);
         self
@@ -276,7 +466,7 @@ impl SearcherBuilder {
         self
     }
 
-    pub fn timezone(mut self, tz: Tz) -> Self {
+    pub fn timezone(mut self, tz: Timezone) -> Self {
         self.timezone = Some(tz);
         self
     }
@@ -289,14 +479,24 @@ impl SearcherBuilder {
 
 pub struct SearcherInner {
     regex: RegexSet,
+    regexes: Vec<Regex>,
+    stats: Option<Mutex<Vec<usize>>>,
 
+    cache_parsed: Option<Cache>,
+    fields: Vec<String>,
+    invert_match: bool,
     load_unknown: bool,
     local: bool,
+    location: bool,
+    merge_repeated_data: bool,
+    multiline: bool,
     from: Option<DateTime<Utc>>,
+    redact: Vec<String>,
+    redact_key: Vec<u8>,
     skip_errors: bool,
     tau: Option<Expression>,
     timestamp: Option<String>,
-    timezone: Option<Tz>,
+    timezone: Option<Timezone>,
     to: Option<DateTime<Utc>>,
 }
 
@@ -309,11 +509,201 @@ impl Searcher {
         SearcherBuilder::new()
     }
 
+    /// The timestamp of a hit, read from the timestamp field when one has been provided.
+    pub fn timestamp(&self, hit: &Json) -> Option<NaiveDateTime> {
+        let field = self.inner.timestamp.as_ref()?;
+        let value = crate::evtx::WrapperLegacy(hit).find(field)?;
+        NaiveDateTime::parse_from_str(value.as_str()?, "%Y-%m-%dT%H:%M:%S%.6fZ").ok()
+    }
+
+    /// The number of hits for each pattern so far, when counting them with `pattern_stats`. A hit
+    /// that matches several patterns counts towards each of them.
+    pub fn pattern_stats(&self) -> Option<Vec<(&str, usize)>> {
+        let stats = self
+            .inner
+            .stats
+            .as_ref()?
+            .lock()
+            .expect("could not lock stats");
+        Some(
+            self.inner
+                .regex
+                .patterns()
+                .iter()
+                .map(|p| p.as_str())
+                .zip(stats.iter().copied())
+                .collect(),
+        )
+    }
+
+    /// Search through the file, documents are read as the returned hits are iterated so memory is
+    /// bounded, except for xml and for plain text searched with multiline which are read whole.
     pub fn search(&self, file: &Path) -> crate::Result<Hits<'_>> {
-        let reader = Reader::load(file, self.inner.load_unknown, self.inner.skip_errors)?;
+        if self.inner.multiline && self.inner.load_unknown && !is_known(file) {
+            // NOTE: Files that look binary are left to the parsers to identify as usual.
+            let text = match read_text(file) {
+                Ok(text) => text,
+                Err(e) => {
+                    if self.inner.skip_errors {
+                        cs_eyellowln!("[!] failed to load file '{}' - {}\n", file.display(), e);
+                        Some(String::new())
+                    } else {
+                        anyhow::bail!(e);
+                    }
+                }
+            };
+            if let Some(text) = text {
+                return Ok(Hits {
+                    reader: None,
+                    searcher: &self.inner,
+                    text: self.inner.text_hits(&text),
+                });
+            }
+        }
+        let reader = match &self.inner.cache_parsed {
+            Some(cache) => cache.load(file, self.inner.load_unknown, self.inner.skip_errors)?,
+            None => Reader::load(file, self.inner.load_unknown, self.inner.skip_errors)?,
+        };
         Ok(Hits {
-            reader,
+            reader: Some(reader),
             searcher: &self.inner,
+            text: vec![],
         })
     }
 }
+
+impl SearcherInner {
+    /// Whether the patterns match the document, only testing the fields provided when there are
+    /// any. String values are matched as is, rather than as they appear in the serialised document,
+    /// and array values match when any of their elements do.
+    fn matches(&self, document: &dyn Doc, searchable: &dyn Searchable) -> bool {
+        if self.fields.is_empty() {
+            return searchable.matches(&self.regex);
+        }
+        self.fields.iter().any(|field| match document.find(field) {
+            Some(value) => {
+                ext::tau::any_element(value, &mut |v| match crate::cli::tau_to_json(v) {
+                    Json::String(s) => self.regex.is_match(&s),
+                    json => self.regex.is_match(&json.to_string()),
+                })
+            }
+            None => false,
+        })
+    }
+
+    /// Adds a hit to the counts of the patterns that match the document, see `matches`.
+    fn count(&self, document: &dyn Doc, searchable: &dyn Searchable) {
+        // NOTE: Inverted hits are the documents that no pattern matched.
+        let stats = match &self.stats {
+            Some(stats) if !self.invert_match => stats,
+            _ => return,
+        };
+        let mut patterns = BTreeSet::new();
+        if self.fields.is_empty() {
+            patterns.extend(searchable.patterns(&self.regex));
+        }
+        for field in &self.fields {
+            if let Some(value) = document.find(field) {
+                ext::tau::any_element(value, &mut |v| {
+                    let matches = match crate::cli::tau_to_json(v) {
+                        Json::String(s) => self.regex.matches(&s),
+                        json => self.regex.matches(&json.to_string()),
+                    };
+                    patterns.extend(matches.into_iter());
+                    false
+                });
+            }
+        }
+        let mut stats = stats.lock().expect("could not lock stats");
+        for i in patterns {
+            stats[i] += 1;
+        }
+    }
+
+    /// The hits within a plain text file, one for each pattern match or a single hit holding the
+    /// whole text when inverting the match.
+    fn text_hits(&self, text: &str) -> Vec<Json> {
+        // NOTE: Plain text has no fields, so it can never satisfy tau expressions, field
+        // restrictions or timestamps.
+        if self.tau.is_some()
+            || !self.fields.is_empty()
+            || self.from.is_some()
+            || self.to.is_some()
+            || text.is_empty()
+        {
+            return vec![];
+        }
+        let mut matches = vec![];
+        for (i, regex) in self.regexes.iter().enumerate() {
+            for m in regex.find_iter(text) {
+                matches.push((m.start(), m.as_str()));
+                if let (Some(stats), false) = (&self.stats, self.invert_match) {
+                    stats.lock().expect("could not lock stats")[i] += 1;
+                }
+            }
+        }
+        if self.invert_match {
+            if matches.is_empty() {
+                return vec![json!({ "match": text, "location": Location::Line(1) })];
+            }
+            return vec![];
+        }
+        matches.sort_by_key(|(start, _)| *start);
+        // NOTE: The matches are in order, so the newlines are counted from the previous match
+        // rather than from the start of the text each time.
+        let (mut offset, mut line) = (0, 1);
+        matches
+            .into_iter()
+            .map(|(start, m)| {
+                line += text[offset..start].matches('\n').count();
+                offset = start;
+                json!({ "match": m, "location": Location::Line(line) })
+            })
+            .collect()
+    }
+}
+
+/// Whether the file has the extension of one of the formats we can parse.
+fn is_known(file: &Path) -> bool {
+    let extension = match file.extension().and_then(|e| e.to_str()) {
+        Some(extension) => extension,
+        None => return false,
+    };
+    [
+        FileKind::Evtx,
+        FileKind::Hve,
+        FileKind::Json,
+        FileKind::Jsonl,
+        FileKind::Mft,
+        FileKind::Xml,
+    ]
+    .iter()
+    .filter_map(|k| k.extensions())
+    .flatten()
+    .any(|e| e == extension)
+}
+
+/// The contents of the file when it looks like plain text, judged on its first few kilobytes.
+fn read_text(file: &Path) -> crate::Result<Option<String>> {
+    let extracted;
+    let path = match archive::split(file) {
+        Some((archive, name)) => {
+            extracted = archive::extract(archive, &name)?;
+            extracted.path()
+        }
+        None => file,
+    };
+    let mut f = File::open(path)?;
+    let mut bytes = vec![];
+    f.by_ref().take(8192).read_to_end(&mut bytes)?;
+    let text = match std::str::from_utf8(&bytes) {
+        Ok(_) => true,
+        // NOTE: The sample can end part way through a character.
+        Err(e) => e.error_len().is_none(),
+    };
+    if !text || bytes.contains(&0) {
+        return Ok(None);
+    }
+    f.read_to_end(&mut bytes)?;
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
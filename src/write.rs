@@ -1,9 +1,12 @@
-use std::fs::File;
-use std::path::PathBuf;
+use std::fs::{File, OpenOptions};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use flate2::{write::GzEncoder, Compression};
 
 pub static mut WRITER: Writer = Writer {
+    compress: false,
     format: Format::Std,
     output: None,
     path: None,
@@ -18,9 +21,62 @@ pub enum Format {
     Json,
 }
 
+pub enum Output {
+    File(File),
+    Gzip(GzEncoder<File>),
+}
+
+impl Output {
+    pub fn create(path: &Path, compress: bool) -> std::io::Result<Self> {
+        Ok(Self::new(File::create(path)?, compress))
+    }
+
+    pub fn append(path: &Path, compress: bool) -> std::io::Result<Self> {
+        // NOTE: Gzip streams can be concatenated, so appending a new member to an existing
+        // compressed file still decompresses to the combined output.
+        Ok(Self::new(
+            OpenOptions::new().append(true).open(path)?,
+            compress,
+        ))
+    }
+
+    fn new(file: File, compress: bool) -> Self {
+        if compress {
+            Self::Gzip(GzEncoder::new(file, Compression::default()))
+        } else {
+            Self::File(file)
+        }
+    }
+
+    /// Flush the output, writing the gzip trailer when compressing.
+    pub fn finish(self) -> std::io::Result<()> {
+        match self {
+            Self::File(mut f) => f.flush(),
+            Self::Gzip(g) => g.finish().map(|_| ()),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Self::File(f) => f.write(buf),
+            Self::Gzip(g) => g.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Self::File(f) => f.flush(),
+            Self::Gzip(g) => g.flush(),
+        }
+    }
+}
+
 pub struct Writer {
+    pub compress: bool,
     pub format: Format,
-    pub output: Option<File>,
+    pub output: Option<Output>,
     pub path: Option<PathBuf>,
     pub quiet: bool,
 }
@@ -28,6 +84,7 @@ pub struct Writer {
 impl Default for Writer {
     fn default() -> Self {
         Self {
+            compress: false,
             format: Format::Std,
             output: None,
             path: None,
@@ -45,18 +102,39 @@ where
     F: FnOnce() -> Writer,
 {
     unsafe {
+        if let Some(output) = WRITER.output.take() {
+            output.finish()?;
+        }
         WRITER = make_writer();
     }
     Ok(())
 }
 
+/// Finish writing to the output, this must be called before exiting as the writer is never dropped.
+pub fn close_writer() -> Result<()> {
+    unsafe {
+        if let Some(output) = WRITER.output.take() {
+            output.finish()?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether coloured output should be written to the stream, following https://no-color.org.
+pub fn use_colour<T: IsTerminal>(stream: &T) -> bool {
+    let disabled = std::env::var_os("NO_COLOR")
+        .map(|v| !v.is_empty())
+        .unwrap_or_default();
+    !disabled && stream.is_terminal()
+}
+
 #[macro_export]
 macro_rules! cs_print {
     ($($arg:tt)*) => ({
         use std::io::Write;
         unsafe {
-            match $crate::WRITER.output.as_ref() {
-                Some(mut f) => {
+            match $crate::WRITER.output.as_mut() {
+                Some(f) => {
                     f.write_all(format!($($arg)*).as_bytes()).expect("could not write to file");
                 }
                 None => {
@@ -72,8 +150,8 @@ macro_rules! cs_println {
     () => {
         use std::io::Write;
         unsafe {
-            match $crate::WRITER.output.as_ref() {
-                Some(mut f) => {
+            match $crate::WRITER.output.as_mut() {
+                Some(f) => {
                     f.write_all(b"\n").expect("could not write to file");
                 }
                 None => {
@@ -85,8 +163,8 @@ macro_rules! cs_println {
     ($($arg:tt)*) => {
         use std::io::Write;
         unsafe {
-            match $crate::WRITER.output.as_ref() {
-                Some(mut f) => {
+            match $crate::WRITER.output.as_mut() {
+                Some(f) => {
                     f.write_all(format!($($arg)*).as_bytes()).expect("could not write to file");
                     f.write_all(b"\n").expect("could not write to file");
                 }
@@ -114,9 +192,9 @@ macro_rules! cs_print_json {
     ($value:expr) => {{
         use std::io::Write;
         unsafe {
-            match $crate::WRITER.output.as_ref() {
-                Some(mut f) => {
-                    ::serde_json::to_writer(f, $value)?;
+            match $crate::WRITER.output.as_mut() {
+                Some(f) => {
+                    ::serde_json::to_writer(&mut *f, $value)?;
                     f.flush()
                 }
                 None => {
@@ -133,9 +211,9 @@ macro_rules! cs_print_json_pretty {
     ($value:expr) => {{
         use std::io::Write;
         unsafe {
-            match $crate::WRITER.output.as_ref() {
-                Some(mut f) => {
-                    ::serde_json::to_writer_pretty(f, $value)?;
+            match $crate::WRITER.output.as_mut() {
+                Some(f) => {
+                    ::serde_json::to_writer_pretty(&mut *f, $value)?;
                     f.flush()
                 }
                 None => {
@@ -152,9 +230,9 @@ macro_rules! cs_print_yaml {
     ($value:expr) => {{
         use std::io::Write;
         unsafe {
-            match $crate::WRITER.output.as_ref() {
-                Some(mut f) => {
-                    ::serde_yaml::to_writer(f, $value)?;
+            match $crate::WRITER.output.as_mut() {
+                Some(f) => {
+                    ::serde_yaml::to_writer(&mut *f, $value)?;
                     f.write_all(b"\n")?;
                     f.flush()
                 }
@@ -171,9 +249,9 @@ macro_rules! cs_print_yaml {
 macro_rules! cs_print_table {
     ($table:ident) => {
         unsafe {
-            match $crate::WRITER.output.as_ref() {
-                Some(mut f) => {
-                    let _ = $table.print(&mut f).expect("could not write table to file");
+            match $crate::WRITER.output.as_mut() {
+                Some(f) => {
+                    let _ = $table.print(f).expect("could not write table to file");
                 }
                 None => $table.printstd(),
             }
@@ -185,12 +263,12 @@ macro_rules! cs_greenln {
     ($($arg:tt)*) => {
         use std::io::Write;
         unsafe {
-            match $crate::WRITER.output.as_ref() {
-                Some(mut f) => {
+            match $crate::WRITER.output.as_mut() {
+                Some(f) => {
                     f.write_all(format!($($arg)*).as_bytes()).expect("could not write to file");
                     f.write_all(b"\n").expect("could not write to file");
                 }
-                None => {
+                None if $crate::use_colour(&std::io::stdout()) => {
                     let _ = std::io::stderr().lock();
                     crossterm::execute!(
                         std::io::stdout(),
@@ -200,6 +278,9 @@ macro_rules! cs_greenln {
                     ).expect("failed to write line");
                     println!()
                 }
+                None => {
+                    println!($($arg)*);
+                }
             }
         }
     };
@@ -210,14 +291,18 @@ macro_rules! cs_egreenln {
     ($($arg:tt)*) => {
         unsafe {
             if !$crate::WRITER.quiet {
-                let _ = std::io::stderr().lock();
-                crossterm::execute!(
-                    std::io::stderr(),
-                    crossterm::style::SetForegroundColor(crossterm::style::Color::Green),
-                    crossterm::style::Print(format!($($arg)*)),
-                    crossterm::style::ResetColor
-                ).expect("failed to write line");
-                eprintln!()
+                if $crate::use_colour(&std::io::stderr()) {
+                    let _ = std::io::stderr().lock();
+                    crossterm::execute!(
+                        std::io::stderr(),
+                        crossterm::style::SetForegroundColor(crossterm::style::Color::Green),
+                        crossterm::style::Print(format!($($arg)*)),
+                        crossterm::style::ResetColor
+                    ).expect("failed to write line");
+                    eprintln!()
+                } else {
+                    eprintln!($($arg)*);
+                }
             }
         }
     };
@@ -228,14 +313,18 @@ macro_rules! cs_eyellowln {
     ($($arg:tt)*) => {
         unsafe {
             if !$crate::WRITER.quiet {
-                let _ = std::io::stderr().lock();
-                crossterm::execute!(
-                    std::io::stderr(),
-                    crossterm::style::SetForegroundColor(crossterm::style::Color::Yellow),
-                    crossterm::style::Print(format!($($arg)*)),
-                    crossterm::style::ResetColor
-                ).expect("failed to write line");
-                eprintln!()
+                if $crate::use_colour(&std::io::stderr()) {
+                    let _ = std::io::stderr().lock();
+                    crossterm::execute!(
+                        std::io::stderr(),
+                        crossterm::style::SetForegroundColor(crossterm::style::Color::Yellow),
+                        crossterm::style::Print(format!($($arg)*)),
+                        crossterm::style::ResetColor
+                    ).expect("failed to write line");
+                    eprintln!()
+                } else {
+                    eprintln!($($arg)*);
+                }
             }
         }
     };
@@ -246,14 +335,18 @@ macro_rules! cs_eredln {
     ($($arg:tt)*) => {
         unsafe {
             if !$crate::WRITER.quiet {
-                let _ = std::io::stderr().lock();
-                crossterm::execute!(
-                    std::io::stderr(),
-                    crossterm::style::SetForegroundColor(crossterm::style::Color::Red),
-                    crossterm::style::Print(format!($($arg)*)),
-                    crossterm::style::ResetColor
-                ).expect("failed to write line");
-                eprintln!()
+                if $crate::use_colour(&std::io::stderr()) {
+                    let _ = std::io::stderr().lock();
+                    crossterm::execute!(
+                        std::io::stderr(),
+                        crossterm::style::SetForegroundColor(crossterm::style::Color::Red),
+                        crossterm::style::Print(format!($($arg)*)),
+                        crossterm::style::ResetColor
+                    ).expect("failed to write line");
+                    eprintln!()
+                } else {
+                    eprintln!($($arg)*);
+                }
             }
         }
     };
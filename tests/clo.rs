@@ -18,6 +18,77 @@ fn search_jq_simple_string() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[test]
+fn search_jq_compress_simple_string() -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Read;
+
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let sample_expected_output_path = Path::new(root).join("tests/evtx").join("clo_search_qj_simple_string.txt");
+    let output = tempfile::tempdir()?;
+    let output_path = output.path().join("output.json");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("4624").arg(sample_path).arg("-jq").arg("-o").arg(&output_path).arg("--compress");
+    cmd.assert().success();
+
+    let mut decoder = flate2::read::GzDecoder::new(std::fs::File::open(output.path().join("output.json.gz"))?);
+    let mut decompressed = String::new();
+    decoder.read_to_string(&mut decompressed)?;
+    assert_eq!(decompressed, std::fs::read_to_string(sample_expected_output_path)?);
+
+    Ok(())
+}
+
+#[test]
+fn search_q_output_format_inferred() -> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let sample_expected_output_path = Path::new(root).join("tests/evtx").join("clo_search_q_jsonl_simple_string.txt");
+    let output = tempfile::tempdir()?;
+    let output_path = output.path().join("output.jsonl");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("-o").arg(&output_path);
+    cmd.assert().success();
+
+    assert_eq!(std::fs::read_to_string(&output_path)?, std::fs::read_to_string(&sample_expected_output_path)?);
+
+    // The inferred format is not taken as a path when the positional arguments follow a '--'.
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("search").arg("-q").arg("-o").arg(&output_path).arg("--").arg("4624").arg(&sample_path);
+    cmd.assert().success();
+
+    assert_eq!(std::fs::read_to_string(&output_path)?, std::fs::read_to_string(&sample_expected_output_path)?);
+
+    Ok(())
+}
+
+#[test]
+fn hunt_r_output_format_inferred() -> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let output = tempfile::tempdir()?;
+
+    // NOTE: Csv is never inferred, as hunt writes it to a directory rather than the output file.
+    let output_path = output.path().join("results.csv");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("hunt").arg(&sample_path).arg("-r").arg(&rule_path).arg("-q").arg("-o").arg(&output_path);
+    cmd.assert().success();
+    assert!(output_path.is_file());
+
+    // The inferred format is checked against the other arguments like any other.
+    let output_path = output.path().join("results.json");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("hunt").arg(&sample_path).arg("-r").arg(&rule_path).arg("-q").arg("--show-matches").arg("-o").arg(&output_path);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+
+    Ok(())
+}
+
 #[test]
 fn search_q_jsonl_simple_string()-> Result<(), Box<dyn std::error::Error>> {
     let root = env!("CARGO_MANIFEST_DIR");
@@ -33,6 +104,102 @@ fn search_q_jsonl_simple_string()-> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 #[test]
+fn search_q_jsonl_tail()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let expected = std::fs::read_to_string(Path::new(root).join("tests/evtx").join("clo_search_q_jsonl_simple_string.txt"))?;
+    let last = expected.lines().last().unwrap();
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("4624").arg(sample_path).arg("-q").arg("--jsonl").arg("--tail").arg("1");
+    cmd.assert()
+        .success()
+        .stdout(format!("{}\n", last));
+    
+    Ok(())
+}
+#[test]
+fn search_q_jsonl_field()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let sample_expected_output_path = Path::new(root).join("tests/evtx").join("clo_search_q_jsonl_simple_string.txt");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("--jsonl").arg("--field").arg("Event.System.EventID");
+    cmd.assert()
+        .success()
+        .stdout( predicate::path::eq_file(sample_expected_output_path).utf8().unwrap());
+
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("--jsonl").arg("--field").arg("Event.EventData.ProcessName");
+    cmd.assert()
+        .success()
+        .stdout("");
+    
+    Ok(())
+}
+#[test]
+fn search_q_jsonl_last()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    // NOTE: The sample was recorded in 2022, so nothing falls within the last week.
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("--jsonl").arg("--last").arg("7d")
+        .arg("--timestamp").arg("Event.System.TimeCreated_attributes.SystemTime");
+    cmd.assert()
+        .success()
+        .stdout("");
+
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("--jsonl").arg("--last").arg("1000w")
+        .arg("--timestamp").arg("Event.System.TimeCreated_attributes.SystemTime");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"EventID\":4624"));
+
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("--last").arg("1000000000w");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("too large"));
+    
+    Ok(())
+}
+#[test]
+fn search_q_jsonl_merge_data()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("repeated_data.evtx");
+
+    for (merge, array) in [(false, false), (true, true)] {
+        let mut cmd = Command::cargo_bin("chainsaw")?;
+        cmd.arg("search").arg("Backup Operators").arg(&sample_path).arg("-q").arg("--jsonl");
+        if merge {
+            cmd.arg("--merge-data");
+        }
+        let output = cmd.assert().success().get_output().stdout.clone();
+        let document: serde_json::Value = serde_json::from_slice(&output)?;
+        assert_eq!(document["Event"]["EventData"]["Group"].is_array(), array);
+    }
+
+    Ok(())
+}
+#[test]
+fn search_q_jsonl_location()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("--jsonl").arg("--location");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let records = String::from_utf8(output)?.lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).map(|d| d["chainsaw_location"]["record"].as_u64()))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(records, vec![Some(31794), Some(31799)]);
+
+    Ok(())
+}
+#[test]
 fn search_q_simple_string()-> Result<(), Box<dyn std::error::Error>> {
     let root = env!("CARGO_MANIFEST_DIR");
     let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
@@ -48,9 +215,69 @@ fn search_q_simple_string()-> Result<(), Box<dyn std::error::Error>> {
 }
 
 #[test]
-fn hunt_r_any_logon()-> Result<(), Box<dyn std::error::Error>> {
+fn search_q_count_simple_string()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("--count");
+    cmd.assert()
+        .success()
+        .stdout(format!("{}:2\n", sample_path.display()));
+    
+    Ok(())
+}
+
+#[test]
+fn search_q_files_with_matches_simple_string()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("-l");
+    cmd.assert()
+        .success()
+        .stdout(format!("{}\n", sample_path.display()));
+    
+    Ok(())
+}
+
+#[test]
+fn search_q_count_var_pattern()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("-e").arg("${EVENT_ID}").arg(&sample_path).arg("-q").arg("--count").arg("--var").arg("EVENT_ID=4624");
+    cmd.assert()
+        .success()
+        .stdout(format!("{}:2\n", sample_path.display()));
+    
+    Ok(())
+}
+
+#[test]
+fn search_q_count_pattern_file()-> Result<(), Box<dyn std::error::Error>> {
     let root = env!("CARGO_MANIFEST_DIR");
     let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let dir = tempfile::tempdir()?;
+    let pattern_path = dir.path().join("patterns.txt");
+    std::fs::write(&pattern_path, "# Successful logons\n\n4624\n")?;
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg(&sample_path).arg("-q").arg("--count").arg("--pattern-file").arg(&pattern_path);
+    cmd.assert()
+        .success()
+        .stdout(format!("{}:2\n", sample_path.display()));
+    
+    Ok(())
+}
+
+#[test]
+fn hunt_r_any_logon()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    // NOTE: The table shows the source path, so it is relative for the output to be stable.
+    let sample_path = Path::new("tests/evtx/security_sample.evtx");
     let sample_expected_output_path = Path::new(root).join("tests/evtx").join("clo_hunt_r_any_logon.txt");
     let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
     let mut cmd = Command::cargo_bin("chainsaw")?;
@@ -61,4 +288,630 @@ fn hunt_r_any_logon()-> Result<(), Box<dyn std::error::Error>> {
         .stdout( predicate::path::eq_file(sample_expected_output_path).utf8().unwrap());
     
     Ok(())
-}
\ No newline at end of file
+}
+#[test]
+fn hunt_r_log_output_template()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("-q").arg("--log").arg("--output-template").arg("{level} {rule} {Event.System.EventRecordID}");
+    cmd.assert()
+        .success()
+        .stdout("info Any Logon 31794\ninfo Any Logon 31799\n");
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_count_only()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("-q").arg("--count-only");
+    cmd.assert()
+        .success()
+        .stdout("2 Detections found on 2 documents\n");
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_coverage()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule = std::fs::read_to_string(Path::new(root).join("tests/evtx").join("rule-any-logon.yml"))?;
+    let dir = tempfile::tempdir()?;
+    let rule_path = dir.path().join("rule-unseen.yml");
+    std::fs::write(&rule_path, rule.replace("Event.System.EventID: 4624", "Event.System.EventID: 9999"))?;
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("-q").arg("--coverage");
+    cmd.assert()
+        .success()
+        .stdout("Any Logon (event ids: 9999)\n");
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_jsonl_matched()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("-q").arg("--jsonl");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"matched\":{\"Event.System.EventID\":\"4624\"}"));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_json_tee()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    // NOTE: The table shows the source path, so it is relative for the output to be stable.
+    let sample_path = Path::new("tests/evtx/security_sample.evtx");
+    let sample_expected_output_path = Path::new(root).join("tests/evtx").join("clo_hunt_r_any_logon.txt");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let dir = tempfile::tempdir()?;
+    let output_path = dir.path().join("results.json");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("--json").arg("--output").arg(&output_path).arg("--tee");
+    cmd.assert()
+        .success()
+        .stdout( predicate::path::eq_file(sample_expected_output_path).utf8().unwrap());
+    let results: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&output_path)?)?;
+    assert_eq!(results.as_array().map(|a| a.len()), Some(2));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_jsonl_ignore_records()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let dir = tempfile::tempdir()?;
+    let ignore_path = dir.path().join("reviewed.txt");
+    std::fs::write(&ignore_path, "# reviewed\nsecurity_sample.evtx:31794\n")?;
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("-q").arg("--jsonl").arg("--ignore-records").arg(ignore_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"EventRecordID\":31799").and(predicate::str::contains("\"EventRecordID\":31794").not()));
+    
+    Ok(())
+}
+#[test]
+fn lint_report_json()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let dir = tempfile::tempdir()?;
+    let rules = dir.path().join("rules");
+    std::fs::create_dir(&rules)?;
+    std::fs::copy(Path::new(root).join("tests/evtx").join("rule-any-logon.yml"), rules.join("good.yml"))?;
+    std::fs::write(rules.join("bad.yml"), "title: [broken")?;
+    let report_path = dir.path().join("report.json");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("lint").arg(&rules).arg("--kind").arg("chainsaw").arg("--report-json").arg(&report_path);
+    cmd.assert().success();
+    let report: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&report_path)?)?;
+    let mut statuses = report.as_array().unwrap().iter()
+        .map(|r| (r["file"].as_str().unwrap().ends_with("good.yml"), r["status"].as_str().unwrap().to_owned(), r["error"].is_null()))
+        .collect::<Vec<_>>();
+    statuses.sort();
+    assert_eq!(statuses, vec![(false, "failed".to_owned(), false), (true, "loaded".to_owned(), true)]);
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_append_csv()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let dir = tempfile::tempdir()?;
+    let output = dir.path().join("results");
+
+    for _ in 0..2 {
+        let mut cmd = Command::cargo_bin("chainsaw")?;
+        cmd.arg("hunt").arg(&sample_path).arg("-r").arg(&rule_path).arg("-q").arg("--csv").arg("--output").arg(&output).arg("--append-csv");
+        cmd.assert().success();
+    }
+    let csv = std::fs::read_to_string(output.join("lateral_movement.csv"))?;
+    assert_eq!(csv.lines().count(), 5);
+    assert_eq!(csv.lines().filter(|l| l.starts_with("timestamp,")).count(), 1);
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_jsonl_array_element()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/json").join("array_field.json");
+    let rule_path = Path::new(root).join("tests/json").join("rule-array-element.yml");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("-q").arg("--jsonl");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("host-a").and(predicate::str::contains("host-b").not()));
+    
+    Ok(())
+}
+#[test]
+fn search_q_jsonl_field_array_element()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/json").join("array_field.json");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("^192\\.168\\.1\\.5$").arg(sample_path).arg("-q").arg("--jsonl").arg("--field").arg("IpAddresses");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("host-a").and(predicate::str::contains("host-b").not()));
+    
+    Ok(())
+}
+#[test]
+fn search_multiline()-> Result<(), Box<dyn std::error::Error>> {
+    let dir = tempfile::tempdir()?;
+    let log_path = dir.path().join("app.log");
+    std::fs::write(&log_path, "started\nBEGIN\ncrashed\nEND\n")?;
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("BEGIN.*END").arg(log_path).arg("--load-unknown").arg("--multiline").arg("-q").arg("--jsonl");
+    cmd.assert()
+        .success()
+        .stdout("{\"location\":{\"line\":2},\"match\":\"BEGIN\\ncrashed\\nEND\"}\n");
+    
+    Ok(())
+}
+#[test]
+fn tz_filter()-> Result<(), Box<dyn std::error::Error>> {
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("tz").arg("london");
+    cmd.assert()
+        .success()
+        .stdout("Europe/London\n");
+    
+    Ok(())
+}
+#[test]
+fn search_error_json()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("missing.evtx");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("--error-json").arg("search").arg("4624").arg(sample_path).arg("-q");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::starts_with("{\"causes\":[],\"error\":"));
+    
+    Ok(())
+}
+#[test]
+fn lint_test_event()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let rule_path = Path::new(root).join("tests/json").join("rule-array-element.yml");
+    let dir = tempfile::tempdir()?;
+    let matched = dir.path().join("matched.json");
+    std::fs::write(&matched, r#"{"Computer": "host-a", "IpAddresses": "192.168.1.5"}"#)?;
+    let unmatched = dir.path().join("unmatched.json");
+    std::fs::write(&unmatched, r#"{"Computer": "host-b", "IpAddresses": "10.0.0.2"}"#)?;
+
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("lint").arg(&rule_path).arg("--kind").arg("chainsaw").arg("--test-event").arg(&matched);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(": matched").and(predicate::str::contains("IpAddresses: 192.168.1.5")));
+
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("lint").arg(&rule_path).arg("--kind").arg("chainsaw").arg("--test-event").arg(&unmatched);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(": not matched"));
+    
+    Ok(())
+}
+#[test]
+fn lint_json()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("lint").arg(&rule_path).arg("--kind").arg("chainsaw").arg("--json");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let rule: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(rule["name"], "Any Logon");
+    assert_eq!(rule["kind"], "chainsaw");
+    assert!(rule["logic"]["detection"].is_object());
+    
+    Ok(())
+}
+#[test]
+fn lint_fix()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let dir = tempfile::tempdir()?;
+    let original = std::fs::read_to_string(Path::new(root).join("tests/evtx").join("rule-any-logon.yml"))?
+        .replace("    Event.System.EventID: 4624", "    Event.System.EventID: 4624\n    Event.EventData.TargetUserName: '?admin$'");
+    let rule_path = dir.path().join("rule.yml");
+    std::fs::write(&rule_path, &original)?;
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("lint").arg(dir.path()).arg("--kind").arg("chainsaw").arg("--fix");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Fixed 1 detection rules"));
+    assert_eq!(std::fs::read_to_string(&rule_path)?, original.replace("'?admin$'", "'*admin'"));
+    assert_eq!(std::fs::read_to_string(dir.path().join("rule.yml.bak"))?, original);
+
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("lint").arg(dir.path()).arg("--kind").arg("chainsaw");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("Validated 1 detection rules out of 1"));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_summary_output()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let dir = tempfile::tempdir()?;
+    let summary_path = dir.path().join("summary.json");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("-q").arg("--jsonl").arg("--summary-output").arg(&summary_path);
+    cmd.assert().success();
+    let summary: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&summary_path)?)?;
+    assert_eq!(summary["detections"], 2);
+    assert_eq!(summary["rules"].as_object().map(|r| r.values().filter_map(|c| c.as_u64()).sum::<u64>()), Some(2));
+    assert!(summary["span"]["earliest"].is_string());
+    
+    Ok(())
+}
+#[test]
+fn search_exclude_extension()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("4624").arg(sample_path).arg("-q").arg("--exclude-extension").arg("evtx");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("No forensic artefacts were found"));
+    
+    Ok(())
+}
+#[test]
+fn search_q_jsonl_large_json_array()-> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("large.json");
+    let mut file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+    write!(file, "[")?;
+    for i in 0..200_000 {
+        if i > 0 {
+            write!(file, ",")?;
+        }
+        write!(file, r#"{{"id": {}, "message": "{}"}}"#, i, if i == 199_999 { "needle" } else { "hay" })?;
+    }
+    write!(file, "]")?;
+    file.flush()?;
+    drop(file);
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("needle").arg(&path).arg("-q").arg("--jsonl");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"id\":199999"));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_tag()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let dir = tempfile::tempdir()?;
+    let rule_path = dir.path().join("rule-tagged.yml");
+    let rule = std::fs::read_to_string(Path::new(root).join("tests/evtx").join("rule-any-logon.yml"))?;
+    std::fs::write(&rule_path, format!("{}\ntags:\n  - attack.t1078\n", rule))?;
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("--jsonl").arg("--tag").arg("attack.t10*");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("1 detection rules remaining after filtering by tag"))
+        .stdout(predicate::str::contains("\"EventRecordID\":31794"));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_output_dir_distinct_names()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let dir = tempfile::tempdir()?;
+    // NOTE: These paths would have the same name if the separators were just replaced.
+    for parent in ["a/b_c", "a_b/c"] {
+        std::fs::create_dir_all(dir.path().join("logs").join(parent))?;
+        std::fs::copy(&sample_path, dir.path().join("logs").join(parent).join("security_sample.evtx"))?;
+    }
+    let output = dir.path().join("output");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(dir.path().join("logs")).arg("-r").arg(rule_path).arg("-q").arg("--jsonl").arg("--output-dir").arg(&output);
+    cmd.assert().success();
+    let names = std::fs::read_dir(&output)?
+        .map(|e| e.map(|e| e.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(names.len(), 2);
+    assert!(names.iter().all(|n| n.contains("security_sample.evtx-") && n.ends_with(".jsonl")));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_explain()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("-q").arg("--jsonl").arg("--explain");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    for line in String::from_utf8(output)?.lines() {
+        let detection: serde_json::Value = serde_json::from_str(line)?;
+        let explanation = detection["explanation"].as_str().expect("missing explanation");
+        assert!(explanation.starts_with("[+] "));
+    }
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_jsonl_source_path()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(&sample_path).arg("-r").arg(rule_path).arg("-q").arg("--jsonl");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    for line in String::from_utf8(output)?.lines() {
+        let detection: serde_json::Value = serde_json::from_str(line)?;
+        assert_eq!(detection["source_path"].as_str(), sample_path.to_str());
+    }
+    
+    Ok(())
+}
+#[test]
+fn search_pattern_stats_json()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let dir = tempfile::tempdir()?;
+    let stats_path = dir.path().join("stats.json");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg(sample_path).arg("-e").arg("4624").arg("-e").arg("no-such-pattern").arg("-q").arg("--pattern-stats-json").arg(&stats_path);
+    cmd.assert().success();
+    let stats: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&stats_path)?)?;
+    assert_eq!(stats[0]["pattern"], "4624");
+    assert!(stats[0]["hits"].as_u64() > Some(0));
+    assert_eq!(stats[1]["hits"], 0);
+    
+    Ok(())
+}
+#[test]
+fn search_jq_redact()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("4624").arg(sample_path).arg("-jq").arg("--redact").arg("Event.System.Computer");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("DESKTOP-JK4Q86I").not())
+        .stdout(predicate::str::contains("\"Computer\":\"[REDACTED:"))
+        .stdout(predicate::str::contains("\"TargetUserName\":\"SYSTEM\""));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_rule_allowlist()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let dir = tempfile::tempdir()?;
+    let allowlist_path = dir.path().join("allowlist.txt");
+    std::fs::write(&allowlist_path, "# investigation shortlist\nAny Log?n*\nMissing Rule\n")?;
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("--jsonl").arg("--rule-allowlist").arg(allowlist_path);
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("1 detection rules remaining after filtering by allowlist (1 of 2 entries found)"))
+        .stderr(predicate::str::contains("No detection rules matched allowlist entry 'Missing Rule'"))
+        .stdout(predicate::str::contains("\"EventRecordID\":31794"));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_jsonl_field_rename()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("-q").arg("--jsonl")
+        .arg("--field-rename").arg("name=rule").arg("--field-rename").arg("timestamp=@timestamp");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"rule\":\"Any Logon\""))
+        .stdout(predicate::str::contains("\"@timestamp\":"))
+        .stdout(predicate::str::contains("\"name\":\"Any Logon\"").not());
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_search_cache_parsed()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let dir = tempfile::tempdir()?;
+    let cache_path = dir.path().join("parsed");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(&sample_path).arg("-r").arg(&rule_path).arg("-q").arg("--jsonl").arg("--cache-parsed").arg(&cache_path);
+    let hunted = cmd.assert().success().get_output().stdout.clone();
+    assert_eq!(std::fs::read_dir(&cache_path)?.count(), 1);
+
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("hunt").arg(&sample_path).arg("-r").arg(&rule_path).arg("-q").arg("--jsonl").arg("--cache-parsed").arg(&cache_path);
+    cmd.assert().success().stdout(hunted);
+
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("--jsonl").arg("--cache-parsed").arg(&cache_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"EventRecordID\":31794"));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_file_timeout()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("--jsonl").arg("--file-timeout").arg("60");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("exceeded the file timeout").not())
+        .stdout(predicate::str::contains("\"EventRecordID\":31794"));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_stix()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("-q").arg("--stix");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let bundle: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(bundle["type"], "bundle");
+    let objects = bundle["objects"].as_array().expect("missing objects");
+    let indicator = objects.iter().find(|o| o["type"] == "indicator").expect("missing indicator");
+    assert_eq!(indicator["name"], "Any Logon");
+    assert_eq!(indicator["pattern"], "[x-chainsaw-detection:rule = 'Any Logon']");
+    let sighting = objects.iter().find(|o| o["type"] == "sighting").expect("missing sighting");
+    assert_eq!(sighting["sighting_of_ref"], indicator["id"]);
+    assert!(sighting["count"].as_u64().unwrap_or_default() > 0);
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_min_hits()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("--jsonl").arg("--min-hits").arg("2");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("with fewer than 2 hits"))
+        .stdout(predicate::str::is_empty());
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_checkpoint_append_json()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let dir = tempfile::tempdir()?;
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(sample_path).arg("-r").arg(rule_path).arg("--json").arg("--append-json")
+        .arg("-o").arg(dir.path().join("results.json")).arg("--checkpoint").arg(dir.path().join("checkpoint"));
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_tz_from_event()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let rule_path = Path::new(root).join("tests/json").join("rule-array-element.yml");
+    let dir = tempfile::tempdir()?;
+    let sample_path = dir.path().join("events.json");
+    std::fs::write(&sample_path, r#"[
+        {"timestamp": "2022-10-11T19:26:52Z", "Computer": "host-a", "IpAddresses": "192.168.1.5", "TimeZone": "+05:30"},
+        {"timestamp": "2022-10-11T19:27:52Z", "Computer": "host-b", "IpAddresses": "192.168.1.5"}
+    ]"#)?;
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("hunt").arg(&sample_path).arg("-r").arg(rule_path).arg("-q").arg("--jsonl").arg("--tz-from-event").arg("TimeZone");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let mut timestamps = String::from_utf8(output)?.lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).map(|d| (d["document"]["data"]["Computer"].to_string(), d["timestamp"].to_string())))
+        .collect::<Result<Vec<_>, _>>()?;
+    timestamps.sort();
+    assert_eq!(timestamps.len(), 2);
+    assert!(timestamps[0].1.ends_with("+05:30\""));
+    assert!(timestamps[1].1.ends_with("+00:00\""));
+    
+    Ok(())
+}
+#[test]
+fn hunt_r_since_record()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let rule_path = Path::new(root).join("tests/evtx").join("rule-any-logon.yml");
+    let dir = tempfile::tempdir()?;
+    let records = dir.path().join("records.tsv");
+
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("hunt").arg(&sample_path).arg("-r").arg(&rule_path).arg("-q").arg("--jsonl").arg("--since-record").arg(&records);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("\"EventRecordID\":31794").and(predicate::str::contains("\"EventRecordID\":31799")));
+    assert!(records.exists());
+
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("hunt").arg(&sample_path).arg("-r").arg(&rule_path).arg("-q").arg("--jsonl").arg("--since-record").arg(&records);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("EventRecordID").not());
+    
+    Ok(())
+}
+#[test]
+fn search_q_jsonl_sort()-> Result<(), Box<dyn std::error::Error>> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let sample_path = Path::new(root).join("tests/evtx").join("security_sample.evtx");
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("--jsonl").arg("--sort")
+        .arg("--timestamp").arg("Event.System.TimeCreated_attributes.SystemTime");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let timestamps = String::from_utf8(output)?.lines()
+        .map(|l| serde_json::from_str::<serde_json::Value>(l).map(|d| d["Event"]["System"]["TimeCreated_attributes"]["SystemTime"].as_str().unwrap_or_default().to_owned()))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!(!timestamps.is_empty());
+    assert!(timestamps.windows(2).all(|w| w[0] <= w[1]));
+
+    let mut cmd = Command::cargo_bin("chainsaw")?;
+    cmd.arg("search").arg("4624").arg(&sample_path).arg("-q").arg("--jsonl").arg("--sort");
+    cmd.assert().failure();
+    
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use std::collections::HashSet;
+use std::fs;
+
+use chainsaw::{file_size, get_files};
+
+#[cfg(unix)]
+#[test]
+fn get_files_cyclic_symlink() {
+    let root = tempfile::tempdir().unwrap();
+    let collection = root.path().join("collection");
+    fs::create_dir(&collection).unwrap();
+    fs::write(collection.join("security.evtx"), b"").unwrap();
+    std::os::unix::fs::symlink(&collection, collection.join("loop")).unwrap();
+
+    let path = collection.to_path_buf();
+    let files = get_files(&path, &None, false, false, None).unwrap();
+    assert_eq!(files.len(), 1);
+
+    let files = get_files(&path, &None, true, false, None).unwrap();
+    assert_eq!(files.len(), 1);
+}
+
+#[test]
+fn get_files_depth() {
+    let root = tempfile::tempdir().unwrap();
+    let rules = root.path().join("rules");
+    fs::create_dir_all(rules.join("nested")).unwrap();
+    fs::write(rules.join("rule.yml"), b"").unwrap();
+    fs::write(rules.join("nested").join("rule.yml"), b"").unwrap();
+
+    let path = rules.to_path_buf();
+    let files = get_files(&path, &None, false, false, Some(1)).unwrap();
+    assert_eq!(files.len(), 1);
+
+    let files = get_files(&path, &None, false, false, None).unwrap();
+    assert_eq!(files.len(), 2);
+}
+
+#[test]
+fn get_files_zip() {
+    use std::io::Write;
+
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join("host.zip");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+    let options = zip::write::FileOptions::default();
+    zip.start_file("Logs/security.evtx", options).unwrap();
+    zip.write_all(b"").unwrap();
+    zip.start_file("notes.txt", options).unwrap();
+    zip.write_all(b"").unwrap();
+    zip.finish().unwrap();
+
+    let extensions = Some(HashSet::from(["evtx".to_owned()]));
+    let files = get_files(&root.path().to_path_buf(), &extensions, false, false, None).unwrap();
+    assert_eq!(files, vec![path.join("Logs").join("security.evtx")]);
+    assert_eq!(file_size(&files[0]).unwrap(), 0);
+}
@@ -0,0 +1,357 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use chainsaw::{lint_mapping, load, Hunter, RuleKind};
+
+#[test]
+fn hunter_merges_mappings() {
+    let root = tempfile::tempdir().unwrap();
+    let first = root.path().join("first.yml");
+    fs::write(
+        &first,
+        r#"
+name: first
+kind: evtx
+rules: sigma
+extensions:
+  preconditions:
+    - for:
+        logsource.service: sysmon
+      filter:
+        Provider: Microsoft-Windows-Sysmon
+groups:
+  - name: Sigma
+    timestamp: Event.System.TimeCreated
+    filter:
+      Provider: "*"
+    fields:
+      - name: Event ID
+        from: EventID
+        to: Event.System.EventID
+      - name: Computer
+        from: Computer
+        to: Event.System.Computer
+"#,
+    )
+    .unwrap();
+    let second = root.path().join("second.yml");
+    fs::write(
+        &second,
+        r#"
+name: second
+kind: evtx
+rules: sigma
+extensions:
+  preconditions:
+    - for:
+        logsource.service: security
+      filter:
+        Provider: Microsoft-Windows-Security-Auditing
+groups:
+  - name: Sigma
+    timestamp: Event.System.TimeCreated
+    filter:
+      Provider: "*"
+    fields:
+      - name: Computer
+        from: Hostname
+        to: Event.EventData.Hostname
+      - name: User
+        from: User
+        to: Event.EventData.User
+"#,
+    )
+    .unwrap();
+
+    let hunter = Hunter::builder()
+        .mappings(vec![first, second])
+        .build()
+        .unwrap();
+    let hunts = hunter.hunts();
+    assert_eq!(hunts.len(), 1);
+
+    let fields: Vec<_> = hunts[0]
+        .mapper
+        .fields()
+        .iter()
+        .map(|f| (f.name.as_str(), f.to.as_str()))
+        .collect();
+    assert_eq!(
+        fields,
+        vec![
+            ("Event ID", "Event.System.EventID"),
+            ("Computer", "Event.EventData.Hostname"),
+            ("User", "Event.EventData.User"),
+        ]
+    );
+}
+
+#[test]
+fn lint_mapping_warnings() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join("mapping.yml");
+    fs::write(
+        &path,
+        r#"
+name: typos
+kind: evtx
+rules: sigma
+extensions:
+  preconditions:
+    - for:
+        logsource.servce: sysmon
+      filter:
+        Provider: Microsoft-Windows-Sysmon
+groups:
+  - name: Sigma
+    timestamp: Event.System.TimeCreated
+    filter:
+      Provider: "*"
+    fields:
+      - name: Computer
+        from: Computer
+        to: Event.System.Computer
+      - name: Computer
+        from: Hostname
+        to: Event.EventData.Hostname
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        lint_mapping(&path).unwrap(),
+        vec![
+            "field 'Computer' is defined more than once in group 'Sigma'".to_owned(),
+            "precondition references 'logsource.servce' which is not a supported sigma rule field"
+                .to_owned(),
+        ]
+    );
+}
+
+#[test]
+fn hunter_rejects_unknown_mapping_kind() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join("mapping.yml");
+    fs::write(
+        &path,
+        r#"
+name: unknown
+kind: unknown
+rules: sigma
+groups:
+  - name: Sigma
+    timestamp: Event.System.TimeCreated
+    filter:
+      Provider: "*"
+    fields:
+      - name: Computer
+        from: Computer
+        to: Event.System.Computer
+"#,
+    )
+    .unwrap();
+
+    assert!(lint_mapping(&path).is_err());
+    let error = Hunter::builder()
+        .mappings(vec![path])
+        .build()
+        .err()
+        .unwrap();
+    assert!(error.to_string().contains("known kind of file"));
+}
+
+#[test]
+fn hunter_selects_groups_by_logsource() {
+    let root = tempfile::tempdir().unwrap();
+    let mapping = root.path().join("mapping.yml");
+    fs::write(
+        &mapping,
+        r#"
+name: logsources
+kind: evtx
+rules: sigma
+groups:
+  - name: Security
+    logsource:
+      service: security
+    timestamp: Event.System.TimeCreated
+    filter:
+      Provider: "*"
+    fields:
+      - name: Event ID
+        from: EventID
+        to: Event.System.EventID
+  - name: Sysmon
+    logsource:
+      service: sysmon
+    timestamp: Event.System.TimeCreated
+    filter:
+      Provider: "*"
+    fields:
+      - name: Event ID
+        from: EventID
+        to: Event.System.EventID
+"#,
+    )
+    .unwrap();
+    let mut rules = vec![];
+    for service in ["security", "sysmon"] {
+        let path = root.path().join(format!("{}.yml", service));
+        fs::write(
+            &path,
+            format!(
+                r#"
+title: Logon via {service}
+id: logon-{service}
+status: stable
+level: low
+description: A logon
+author: chainsaw
+logsource:
+  product: windows
+  service: {service}
+detection:
+  selection:
+    EventID: 4624
+  condition: selection
+"#
+            ),
+        )
+        .unwrap();
+        rules.extend(load(RuleKind::Sigma, &path, &None, &None, &None).unwrap());
+    }
+
+    let hunter = Hunter::builder()
+        .rules(rules)
+        .mappings(vec![mapping])
+        .build()
+        .unwrap();
+    let sample = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/evtx/security_sample.evtx");
+    let detections = hunter.hunt(&sample, &None).unwrap();
+    let mut matched = BTreeSet::new();
+    for hit in detections.iter().flat_map(|d| d.hits.iter()) {
+        let metadata = hunter.metadata(hit).unwrap();
+        assert_eq!(metadata.source, RuleKind::Sigma);
+        matched.insert((
+            metadata.group.clone(),
+            metadata.name.clone(),
+            metadata.id.cloned(),
+        ));
+    }
+    assert_eq!(
+        matched,
+        BTreeSet::from([
+            (
+                "Security".to_owned(),
+                "Logon via security".to_owned(),
+                Some("logon-security".to_owned())
+            ),
+            (
+                "Sysmon".to_owned(),
+                "Logon via sysmon".to_owned(),
+                Some("logon-sysmon".to_owned())
+            ),
+        ])
+    );
+}
+
+#[test]
+fn hunter_matches_keywords() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join("keywords.txt");
+    fs::write(
+        &path,
+        "# Annotated indicators\n\nSERVICES.EXE\n31794\nnot-in-the-sample\n",
+    )
+    .unwrap();
+    let rules = load(RuleKind::Keyword, &path, &None, &None, &None).unwrap();
+    assert_eq!(rules.len(), 3);
+
+    let hunter = Hunter::builder().rules(rules).build().unwrap();
+    let sample = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/evtx/security_sample.evtx");
+    let detections = hunter.hunt(&sample, &None).unwrap();
+    let mut matched = vec![];
+    for hit in detections.iter().flat_map(|d| d.hits.iter()) {
+        let metadata = hunter.metadata(hit).unwrap();
+        assert_eq!(metadata.source, RuleKind::Keyword);
+        assert_eq!(metadata.group, "Keywords");
+        matched.push(metadata.name.clone());
+    }
+    // NOTE: Keywords are matched without case, and against numeric values as well as strings.
+    assert!(matched.contains(&"Keyword: SERVICES.EXE".to_owned()));
+    assert_eq!(matched.iter().filter(|n| *n == "Keyword: 31794").count(), 1);
+    assert!(!matched.contains(&"Keyword: not-in-the-sample".to_owned()));
+}
+
+#[test]
+fn hunter_matches_keywords_in_json() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join("keywords.txt");
+    fs::write(&path, "192.168.1.5\n").unwrap();
+    let sample = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/json/array_field.json");
+
+    // NOTE: Without a timestamp field for json documents the keywords are not hunted for in them.
+    let rules = load(RuleKind::Keyword, &path, &None, &None, &None).unwrap();
+    let hunter = Hunter::builder().rules(rules).build().unwrap();
+    assert!(hunter.hunt(&sample, &None).unwrap().is_empty());
+
+    let rules = load(RuleKind::Keyword, &path, &None, &None, &None).unwrap();
+    let hunter = Hunter::builder()
+        .rules(rules)
+        .keyword_timestamp("timestamp".to_owned())
+        .build()
+        .unwrap();
+    let detections = hunter.hunt(&sample, &None).unwrap();
+    assert_eq!(detections.len(), 1);
+    let metadata = hunter.metadata(&detections[0].hits[0]).unwrap();
+    assert_eq!(metadata.name, "Keyword: 192.168.1.5");
+}
+
+#[test]
+fn load_rejects_empty_keywords() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join("keywords.txt");
+    fs::write(&path, "# Nothing but comments\n\n").unwrap();
+    assert!(load(RuleKind::Keyword, &path, &None, &None, &None).is_err());
+}
+
+#[test]
+fn hunter_merges_repeated_data() {
+    let root = tempfile::tempdir().unwrap();
+    let path = root.path().join("rule.yml");
+    fs::write(
+        &path,
+        r#"
+title: Backup Operators Member Added
+group: Persistence
+description: A member was added to a group that includes Backup Operators.
+authors:
+  - chainsaw
+kind: evtx
+level: medium
+status: stable
+timestamp: Event.System.TimeCreated
+fields:
+  - name: User
+    to: Event.EventData.TargetUserName
+filter:
+  condition: backup_operators
+  backup_operators:
+    Event.EventData.Group: Backup Operators
+"#,
+    )
+    .unwrap();
+    // NOTE: The fixture holds a single event that repeats the Group data name.
+    let sample = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/evtx/repeated_data.evtx");
+    for (merge, expected) in [(false, 0), (true, 1)] {
+        let rules = load(RuleKind::Chainsaw, &path, &None, &None, &None).unwrap();
+        let hunter = Hunter::builder()
+            .rules(rules)
+            .merge_repeated_data(merge)
+            .build()
+            .unwrap();
+        let detections = hunter.hunt(&sample, &None).unwrap();
+        assert_eq!(detections.len(), expected);
+    }
+}